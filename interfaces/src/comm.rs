@@ -0,0 +1,709 @@
+//! A client of the websocket of the swarm. [`Comm`] keeps the connection open
+//! (reconnecting when it is lost), gives every command an id and routes the
+//! responses to the command ([`Pending`]) or subscription ([`Subscription`])
+//! they belong to.
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{SinkExt, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio::{
+    net::TcpStream,
+    sync::{
+        mpsc::{UnboundedReceiver, UnboundedSender},
+        watch,
+    },
+};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, http::header::AUTHORIZATION, Message},
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{
+    events::{EventMessage, Subscribe, Unsubscribe},
+    query::{
+        BlockInfo, BlockRegion, BotInfo, EntityInfo, GetBlock, GetBlocks, GetBots, GetEntities,
+        GetPlayers, PlayerInfo,
+    },
+    types::{BlockLocation, Location, Selection2D},
+    Assign, Attack, Cancel, Command, Error, GoTo, Id, Mine, Outcome, Response, Script, Selector,
+    Tag,
+};
+
+type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// the time to wait before reconnecting. It doubles with every failed
+/// attempt up to [`MAX_RECONNECT_DELAY`].
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// How to connect to the swarm
+#[derive(Debug, Clone, Default)]
+pub struct Connect {
+    /// the token sent as `Authorization: Bearer {token}`. Needed if the swarm
+    /// was launched with tokens
+    pub token: Option<String>,
+    /// connect with TLS (`wss://`)
+    pub tls: bool,
+    /// a PEM certificate to trust besides the system roots (i.e., the
+    /// self-signed certificate of the swarm)
+    pub ca_cert: Option<Vec<u8>>,
+}
+
+/// Why a command sent with [`Comm`] did not get an answer
+#[derive(Debug)]
+pub enum CommError {
+    /// the swarm could not be connected to
+    Connect(Box<dyn std::error::Error + Send + Sync>),
+    /// the connection was lost before the swarm answered
+    Disconnected,
+    /// the swarm rejected the command
+    Rejected(Error),
+    /// the swarm answered with data of another type than expected
+    Decode(serde_json::Error),
+}
+
+impl Display for CommError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect(e) => write!(f, "could not connect: {e}"),
+            Self::Disconnected => write!(f, "disconnected from the swarm"),
+            Self::Rejected(e) => write!(f, "rejected ({:?}): {}", e.kind, e.message),
+            Self::Decode(e) => write!(f, "unexpected answer: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CommError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Connect(e) => Some(e.as_ref()),
+            Self::Decode(e) => Some(e),
+            Self::Disconnected | Self::Rejected(_) => None,
+        }
+    }
+}
+
+fn connect_error(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> CommError {
+    CommError::Connect(e.into())
+}
+
+/// open a websocket to the swarm at `addr` (`host:port`)
+pub async fn connect(addr: &str, options: &Connect) -> Result<WebSocket, CommError> {
+    let scheme = if options.tls { "wss" } else { "ws" };
+    let mut request = format!("{scheme}://{addr}")
+        .into_client_request()
+        .map_err(connect_error)?;
+
+    if let Some(token) = &options.token {
+        let value = format!("Bearer {token}").parse().map_err(connect_error)?;
+        request.headers_mut().insert(AUTHORIZATION, value);
+    }
+
+    let connector = if options.tls {
+        let mut builder = native_tls::TlsConnector::builder();
+        if let Some(pem) = &options.ca_cert {
+            builder.add_root_certificate(
+                native_tls::Certificate::from_pem(pem).map_err(connect_error)?,
+            );
+        }
+        Some(Connector::NativeTls(
+            builder.build().map_err(connect_error)?,
+        ))
+    } else {
+        None
+    };
+
+    let (ws, _) = tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector)
+        .await
+        .map_err(connect_error)?;
+    Ok(ws)
+}
+
+/// What the connection task is asked to do
+enum Request {
+    /// send `command` and route its responses to `responses`. Subscriptions
+    /// are sent again after reconnecting.
+    Send {
+        command: Command,
+        responses: UnboundedSender<Response>,
+        subscription: bool,
+    },
+    /// stop routing the responses of a command
+    Forget(Id),
+}
+
+/// Where the responses of a command go
+struct Route {
+    responses: UnboundedSender<Response>,
+    /// the command to send again after reconnecting (for subscriptions)
+    resubscribe: Option<Command>,
+}
+
+/// the id of the command a response is for
+const fn response_id(response: &Response) -> Option<Id> {
+    match response {
+        Response::Ack(ack) => Some(ack.id),
+        Response::Progress(progress) => Some(progress.id),
+        Response::Finished(finished) => Some(finished.id),
+        Response::Cancelled(cancelled) => Some(cancelled.id),
+        Response::Data(data) => Some(data.id),
+        Response::Event(event) => Some(event.subscription),
+        Response::Error(error) => error.id,
+    }
+}
+
+/// The task which owns the websocket
+struct Connection {
+    addr: String,
+    options: Connect,
+    routes: HashMap<Id, Route>,
+    requests: UnboundedReceiver<Request>,
+    connected: watch::Sender<bool>,
+}
+
+impl Connection {
+    /// keep the connection open until every [`Comm`] is dropped
+    async fn run(mut self, mut ws: WebSocket) {
+        loop {
+            if !self.serve(&mut ws).await {
+                let _ = ws.close(None).await;
+                return;
+            }
+
+            self.disconnected();
+
+            let Some(reconnected) = self.reconnect().await else {
+                return;
+            };
+            ws = reconnected;
+            let _ = self.connected.send(true);
+        }
+    }
+
+    /// pass messages until the connection is lost (true) or every [`Comm`]
+    /// is dropped (false)
+    async fn serve(&mut self, ws: &mut WebSocket) -> bool {
+        let resubscribe: Vec<_> = self
+            .routes
+            .values()
+            .filter_map(|route| route.resubscribe.as_ref())
+            .map(encode)
+            .collect();
+
+        for msg in resubscribe {
+            if ws.send(msg).await.is_err() {
+                return true;
+            }
+        }
+
+        loop {
+            tokio::select! {
+                msg = ws.next() => {
+                    let Some(Ok(msg)) = msg else {
+                        return true;
+                    };
+                    let Ok(text) = msg.to_text() else {
+                        continue;
+                    };
+                    if let Ok(response) = serde_json::from_str(text) {
+                        self.route(response);
+                    }
+                }
+                request = self.requests.recv() => {
+                    let Some(request) = request else {
+                        return false;
+                    };
+                    let Some(command) = self.request(request) else {
+                        continue;
+                    };
+                    if ws.send(encode(&command)).await.is_err() {
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// keep a route for `request`. Returns the command to send (if any).
+    fn request(&mut self, request: Request) -> Option<Command> {
+        match request {
+            Request::Send {
+                command,
+                responses,
+                subscription,
+            } => {
+                let resubscribe = subscription.then(|| command.clone());
+                self.routes.insert(
+                    command.id,
+                    Route {
+                        responses,
+                        resubscribe,
+                    },
+                );
+                Some(command)
+            }
+            Request::Forget(id) => {
+                self.routes.remove(&id);
+                None
+            }
+        }
+    }
+
+    fn route(&mut self, response: Response) {
+        let Some(id) = response_id(&response) else {
+            return;
+        };
+
+        // no more responses follow these
+        let last = matches!(
+            response,
+            Response::Finished(_) | Response::Cancelled(_) | Response::Data(_) | Response::Error(_)
+        );
+
+        let Some(route) = self.routes.get(&id) else {
+            return;
+        };
+
+        if route.responses.send(response).is_err() || last {
+            self.routes.remove(&id);
+        }
+    }
+
+    /// commands sent over the lost connection will not be answered.
+    /// Subscriptions are kept to be sent again.
+    fn disconnected(&mut self) {
+        let _ = self.connected.send(false);
+        self.routes.retain(|_, route| route.resubscribe.is_some());
+    }
+
+    /// connect again. Commands sent in the meantime fail right away. Returns
+    /// [`None`] if every [`Comm`] was dropped.
+    async fn reconnect(&mut self) -> Option<WebSocket> {
+        let mut delay = MIN_RECONNECT_DELAY;
+
+        loop {
+            let sleep = tokio::time::sleep(delay);
+            tokio::pin!(sleep);
+
+            loop {
+                tokio::select! {
+                    () = &mut sleep => break,
+                    request = self.requests.recv() => {
+                        let request = request?;
+                        // the command is dropped with its route unless it
+                        // is a subscription
+                        if self.request(request).is_some() {
+                            self.routes.retain(|_, route| route.resubscribe.is_some());
+                        }
+                    }
+                }
+            }
+
+            if let Ok(ws) = connect(&self.addr, &self.options).await {
+                return Some(ws);
+            }
+
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+}
+
+fn encode(command: &Command) -> Message {
+    Message::Text(serde_json::to_string(command).unwrap())
+}
+
+/// A connection to the swarm. Cloned handles share the connection, which is
+/// closed once every handle is dropped. Commands sent while the connection is
+/// lost fail with [`CommError::Disconnected`] while [`Subscription`]s carry
+/// on after reconnecting.
+#[derive(Clone)]
+pub struct Comm {
+    requests: UnboundedSender<Request>,
+    next_id: Arc<AtomicU64>,
+    connected: watch::Receiver<bool>,
+}
+
+impl Comm {
+    /// connect to the swarm at `addr` (`host:port`). Must be called within a
+    /// tokio runtime.
+    pub async fn connect(addr: impl Into<String>, options: Connect) -> Result<Self, CommError> {
+        let addr = addr.into();
+        let ws = connect(&addr, &options).await?;
+
+        let (requests_tx, requests) = tokio::sync::mpsc::unbounded_channel();
+        let (connected_tx, connected) = watch::channel(true);
+
+        let connection = Connection {
+            addr,
+            options,
+            routes: HashMap::new(),
+            requests,
+            connected: connected_tx,
+        };
+        tokio::spawn(connection.run(ws));
+
+        Ok(Self {
+            requests: requests_tx,
+            next_id: Arc::new(AtomicU64::new(1)),
+            connected,
+        })
+    }
+
+    /// if the connection is open (it is reconnecting otherwise)
+    pub fn is_connected(&self) -> bool {
+        *self.connected.borrow()
+    }
+
+    fn request(
+        &self,
+        command: Command,
+        subscription: bool,
+    ) -> Result<UnboundedReceiver<Response>, CommError> {
+        let (responses, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.requests
+            .send(Request::Send {
+                command,
+                responses,
+                subscription,
+            })
+            .map_err(|_| CommError::Disconnected)?;
+        Ok(rx)
+    }
+
+    /// send any command
+    pub fn send<T: Tag>(&self, command: &T) -> Result<Pending, CommError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let responses = self.request(command.command(id), false)?;
+
+        Ok(Pending {
+            id,
+            responses,
+            requests: self.requests.clone(),
+        })
+    }
+
+    /// run a query (see [`crate::query`]) and parse its answer
+    pub async fn query<T: Tag, R: DeserializeOwned>(&self, query: &T) -> Result<R, CommError> {
+        let mut pending = self.send(query)?;
+
+        loop {
+            match pending.next().await.ok_or(CommError::Disconnected)? {
+                Response::Data(data) => {
+                    return serde_json::from_value(data.data).map_err(CommError::Decode)
+                }
+                Response::Error(error) => return Err(CommError::Rejected(error)),
+                _ => {}
+            }
+        }
+    }
+
+    /// receive events until the [`Subscription`] is dropped
+    pub async fn subscribe(&self, filter: &Subscribe) -> Result<Subscription, CommError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut responses = self.request(filter.command(id), true)?;
+
+        loop {
+            match responses.recv().await.ok_or(CommError::Disconnected)? {
+                Response::Ack(_) => break,
+                Response::Error(error) => return Err(CommError::Rejected(error)),
+                _ => {}
+            }
+        }
+
+        Ok(Subscription {
+            id,
+            responses,
+            comm: self.clone(),
+        })
+    }
+
+    pub fn goto(&self, location: BlockLocation, bots: Selector) -> Result<Pending, CommError> {
+        self.send(&GoTo { location, bots })
+    }
+
+    pub fn mine(&self, sel: Selection2D, bots: Selector) -> Result<Pending, CommError> {
+        self.send(&Mine { sel, bots })
+    }
+
+    pub fn attack(&self, name: impl Into<String>, bots: Selector) -> Result<Pending, CommError> {
+        self.send(&Attack {
+            name: name.into(),
+            bots,
+        })
+    }
+
+    pub fn assign(&self, bots: Selector, group: Option<String>) -> Result<Pending, CommError> {
+        self.send(&Assign { bots, group })
+    }
+
+    pub fn cancel(&self, command: Id) -> Result<Pending, CommError> {
+        self.send(&Cancel { command })
+    }
+
+    pub fn script(&self, source: impl Into<String>, bots: Selector) -> Result<Pending, CommError> {
+        self.send(&Script {
+            source: source.into(),
+            bots,
+        })
+    }
+
+    pub async fn get_bots(&self, bots: Selector) -> Result<Vec<BotInfo>, CommError> {
+        self.query(&GetBots { bots }).await
+    }
+
+    pub async fn get_block(
+        &self,
+        location: BlockLocation,
+        server: Option<String>,
+    ) -> Result<BlockInfo, CommError> {
+        self.query(&GetBlock { location, server }).await
+    }
+
+    pub async fn get_blocks(
+        &self,
+        from: BlockLocation,
+        to: BlockLocation,
+        server: Option<String>,
+    ) -> Result<BlockRegion, CommError> {
+        self.query(&GetBlocks { from, to, server }).await
+    }
+
+    pub async fn get_entities(
+        &self,
+        location: Location,
+        radius: f64,
+        server: Option<String>,
+    ) -> Result<Vec<EntityInfo>, CommError> {
+        self.query(&GetEntities {
+            location,
+            radius,
+            server,
+        })
+        .await
+    }
+
+    pub async fn get_players(&self, server: Option<String>) -> Result<Vec<PlayerInfo>, CommError> {
+        self.query(&GetPlayers { server }).await
+    }
+}
+
+/// A command which was sent. Responses to it are dropped once this is
+/// dropped.
+pub struct Pending {
+    id: Id,
+    responses: UnboundedReceiver<Response>,
+    requests: UnboundedSender<Request>,
+}
+
+impl Pending {
+    pub const fn id(&self) -> Id {
+        self.id
+    }
+
+    /// the next response to the command. [`None`] once the command ended or
+    /// the connection was lost
+    pub async fn next(&mut self) -> Option<Response> {
+        self.responses.recv().await
+    }
+
+    /// wait until the swarm accepts the command. Returns the bots working on
+    /// it.
+    pub async fn ack(&mut self) -> Result<Vec<u32>, CommError> {
+        loop {
+            match self.next().await.ok_or(CommError::Disconnected)? {
+                Response::Ack(ack) => return Ok(ack.bots),
+                Response::Error(error) => return Err(CommError::Rejected(error)),
+                _ => {}
+            }
+        }
+    }
+
+    /// wait until every bot stopped working on the command
+    pub async fn done(&mut self) -> Result<Outcome, CommError> {
+        loop {
+            match self.next().await.ok_or(CommError::Disconnected)? {
+                Response::Finished(_) => return Ok(Outcome::Finished),
+                Response::Cancelled(_) => return Ok(Outcome::Cancelled),
+                Response::Error(error) => return Err(CommError::Rejected(error)),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        let _ = self.requests.send(Request::Forget(self.id));
+    }
+}
+
+/// The events of a [`Subscribe`] command as a [`Stream`]. The stream carries
+/// on after reconnecting and ends if the swarm ends the subscription.
+/// Dropping it unsubscribes.
+pub struct Subscription {
+    id: Id,
+    responses: UnboundedReceiver<Response>,
+    comm: Comm,
+}
+
+impl Subscription {
+    pub const fn id(&self) -> Id {
+        self.id
+    }
+}
+
+impl Stream for Subscription {
+    type Item = EventMessage;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<EventMessage>> {
+        let this = self.get_mut();
+        loop {
+            match this.responses.poll_recv(cx) {
+                Poll::Ready(Some(Response::Event(event))) => return Poll::Ready(Some(event)),
+                Poll::Ready(Some(_)) => {}
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let _ = self.comm.requests.send(Request::Forget(self.id));
+        // the answer is not needed
+        let _ = self.comm.send(&Unsubscribe {
+            subscription: self.id,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+    use crate::{
+        events::{Event, EventMessage, Subscribe},
+        query::PlayerInfo,
+        types::BlockLocation,
+        Ack, Comm, Command, Connect, Data, Finished, Outcome, Response, Selector,
+    };
+
+    async fn accept(listener: &TcpListener) -> WebSocketStream<TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio_tungstenite::accept_async(stream).await.unwrap()
+    }
+
+    async fn recv(ws: &mut WebSocketStream<TcpStream>) -> Command {
+        let msg = ws.next().await.unwrap().unwrap();
+        serde_json::from_str(msg.to_text().unwrap()).unwrap()
+    }
+
+    async fn reply(ws: &mut WebSocketStream<TcpStream>, response: Response) {
+        let text = serde_json::to_string(&response).unwrap();
+        ws.send(Message::Text(text)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_comm() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let player = PlayerInfo {
+            name: "Notch".to_string(),
+            uuid: "069a79f444e94726a5befca90e38aaf5".to_string(),
+        };
+        let players = serde_json::to_value([&player]).unwrap();
+
+        // a swarm which loses the connection after the subscription
+        let swarm = tokio::spawn(async move {
+            let mut ws = accept(&listener).await;
+
+            let goto = recv(&mut ws).await;
+            assert_eq!(goto.path, "goto");
+            reply(
+                &mut ws,
+                Response::Ack(Ack {
+                    id: goto.id,
+                    bots: vec![1],
+                }),
+            )
+            .await;
+            reply(&mut ws, Response::Finished(Finished { id: goto.id })).await;
+
+            let subscribe = recv(&mut ws).await;
+            reply(
+                &mut ws,
+                Response::Ack(Ack {
+                    id: subscribe.id,
+                    bots: Vec::new(),
+                }),
+            )
+            .await;
+            drop(ws);
+
+            // the subscription is sent again
+            let mut ws = accept(&listener).await;
+            assert_eq!(recv(&mut ws).await, subscribe);
+            reply(
+                &mut ws,
+                Response::Ack(Ack {
+                    id: subscribe.id,
+                    bots: Vec::new(),
+                }),
+            )
+            .await;
+
+            let event = EventMessage {
+                subscription: subscribe.id,
+                bot: 1,
+                server: "localhost:25565".to_string(),
+                event: Event::Death,
+            };
+            reply(&mut ws, Response::Event(event)).await;
+
+            let query = recv(&mut ws).await;
+            assert_eq!(query.path, "get_players");
+            reply(
+                &mut ws,
+                Response::Data(Data {
+                    id: query.id,
+                    data: players,
+                }),
+            )
+            .await;
+
+            ws
+        });
+
+        let comm = Comm::connect(addr, Connect::default()).await.unwrap();
+
+        let mut goto = comm
+            .goto(BlockLocation::new(1, 64, 3), Selector::default())
+            .unwrap();
+        assert_eq!(goto.ack().await.unwrap(), [1]);
+        assert_eq!(goto.done().await.unwrap(), Outcome::Finished);
+
+        let mut subscription = comm.subscribe(&Subscribe::default()).await.unwrap();
+        let event = subscription.next().await.unwrap();
+        assert_eq!(event.event, Event::Death);
+        assert!(comm.is_connected());
+
+        assert_eq!(comm.get_players(None).await.unwrap(), [player]);
+
+        swarm.await.unwrap();
+    }
+}
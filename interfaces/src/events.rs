@@ -0,0 +1,139 @@
+//! Events pushed to websocket clients which [`Subscribe`]d to them. Each event
+//! is sent as a [`Response::Event`](crate::Response::Event) tagged with the id
+//! of the subscription.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{query::BlockInfo, types::BlockLocation, Id, Outcome, Selector, Tag};
+
+/// Receive events until [`Unsubscribe`]d. Events are only sent if they pass
+/// every filter.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Subscribe {
+    /// the kinds of events to receive. All kinds if empty
+    pub events: Vec<EventKind>,
+
+    /// the bots to receive events of. This does not filter events about the
+    /// world (see [`Event::is_world`])
+    pub bots: Selector,
+
+    /// only receive events from this server (`host:port`)
+    pub server: Option<String>,
+
+    /// the region to receive block changes in. Block changes are only sent if
+    /// this is given
+    pub region: Option<Region>,
+}
+
+impl Tag for Subscribe {
+    const PATH: &'static str = "subscribe";
+}
+
+/// Stop the subscription with the id of the [`Subscribe`] command
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Unsubscribe {
+    pub subscription: Id,
+}
+
+impl Tag for Unsubscribe {
+    const PATH: &'static str = "unsubscribe";
+}
+
+/// The blocks between `from` and `to` (inclusive)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub from: BlockLocation,
+    pub to: BlockLocation,
+}
+
+impl Region {
+    pub fn contains(&self, location: BlockLocation) -> bool {
+        let between = |a: i32, b: i32, v: i32| (a.min(b)..=a.max(b)).contains(&v);
+        between(self.from.x, self.to.x, location.x)
+            && between(
+                i32::from(self.from.y),
+                i32::from(self.to.y),
+                i32::from(location.y),
+            )
+            && between(self.from.z, self.to.z, location.z)
+    }
+}
+
+/// Something which happened to a bot or in its world
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// the bot received a chat message
+    Chat { message: String },
+    /// the bot joined the server
+    Joined { username: String },
+    /// the bot was disconnected
+    Disconnected { reason: String },
+    /// the bot died
+    Death,
+    /// the health or food of the bot changed
+    Health { health: f32, food: u8 },
+    /// the bot stopped a task
+    Task { task: String, outcome: Outcome },
+    /// a block changed
+    BlockChange {
+        location: BlockLocation,
+        block: BlockInfo,
+    },
+    /// a player joined the server
+    PlayerJoin { name: String, uuid: String },
+    /// a player left the server
+    PlayerLeave { name: Option<String>, uuid: String },
+}
+
+/// The kind of an [`Event`] (for filtering)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Chat,
+    Joined,
+    Disconnected,
+    Death,
+    Health,
+    Task,
+    BlockChange,
+    PlayerJoin,
+    PlayerLeave,
+}
+
+impl Event {
+    pub const fn kind(&self) -> EventKind {
+        match self {
+            Self::Chat { .. } => EventKind::Chat,
+            Self::Joined { .. } => EventKind::Joined,
+            Self::Disconnected { .. } => EventKind::Disconnected,
+            Self::Death => EventKind::Death,
+            Self::Health { .. } => EventKind::Health,
+            Self::Task { .. } => EventKind::Task,
+            Self::BlockChange { .. } => EventKind::BlockChange,
+            Self::PlayerJoin { .. } => EventKind::PlayerJoin,
+            Self::PlayerLeave { .. } => EventKind::PlayerLeave,
+        }
+    }
+
+    /// if the event is about the world the bot is in rather than the bot.
+    /// These events are only sent once even if several bots saw them.
+    pub const fn is_world(&self) -> bool {
+        matches!(
+            self,
+            Self::BlockChange { .. } | Self::PlayerJoin { .. } | Self::PlayerLeave { .. }
+        )
+    }
+}
+
+/// An event for a subscription. `bot` is the bot the event happened to (or
+/// which saw it) on `server`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EventMessage {
+    pub subscription: Id,
+    pub bot: u32,
+    pub server: String,
+    #[serde(flatten)]
+    pub event: Event,
+}
@@ -1,39 +1,79 @@
-use futures::{SinkExt, StreamExt};
-use serde::{Deserialize, Serialize};
-use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
-use tungstenite::Message;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::types::{BlockLocation, Selection2D};
+pub use crate::comm::{connect, Comm, CommError, Connect, Pending, Subscription};
+use crate::{
+    events::EventMessage,
+    types::{BlockLocation, Selection2D},
+};
 
+mod comm;
+pub mod events;
+pub mod query;
 pub mod types;
 
-type Id = u64;
+/// The id of a [`Command`]. Ids are chosen by the client and only have to be
+/// unique per connection.
+pub type Id = u64;
 
 pub trait Tag: Serialize {
     const PATH: &'static str;
 
-    fn encode(&self) -> String {
-        let mut v = serde_json::to_value(self).unwrap();
+    /// the command with the given id
+    fn command(&self, id: Id) -> Command {
+        Command {
+            id,
+            path: Self::PATH.to_string(),
+            data: serde_json::to_value(self).unwrap(),
+        }
+    }
 
-        let Some(map) = v.as_object_mut() else {
-            panic!("expected object")
-        };
+    /// the command with the given id as JSON
+    fn encode(&self, id: Id) -> String {
+        serde_json::to_string(&self.command(id)).unwrap()
+    }
+}
 
-        map.insert(
-            "path".to_string(),
-            serde_json::Value::String(Self::PATH.to_string()),
-        );
+/// Which bots a command is for. Bots are selected if they match any of
+/// `ids`, `names` or `groups` (or all bots if none are given). Of those, only
+/// idle bots are selected if `idle` is set, and only the `count` nearest to
+/// `location` if `nearest` is given.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Selector {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub idle: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nearest: Option<Nearest>,
+}
 
-        serde_json::to_string(&v).unwrap()
+impl Selector {
+    /// if every bot is selected
+    pub fn is_all(&self) -> bool {
+        *self == Self::default()
     }
 }
 
+/// The `count` bots nearest to `location`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nearest {
+    pub location: BlockLocation,
+    pub count: usize,
+}
+
 /// The mine command.
 /// Mine the given selection.
 /// A global command. The process should allocate appropriately to children.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Mine {
     pub sel: Selection2D,
+    #[serde(default, skip_serializing_if = "Selector::is_all")]
+    pub bots: Selector,
 }
 
 impl Tag for Mine {
@@ -44,6 +84,8 @@ impl Tag for Mine {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GoTo {
     pub location: BlockLocation,
+    #[serde(default, skip_serializing_if = "Selector::is_all")]
+    pub bots: Selector,
 }
 
 impl Tag for GoTo {
@@ -54,140 +96,160 @@ impl Tag for GoTo {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Attack {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Selector::is_all")]
+    pub bots: Selector,
 }
 
 impl Tag for Attack {
     const PATH: &'static str = "attack";
 }
 
+/// Put the selected bots in `group` (or in no group if [`None`]). A bot is
+/// in at most one group.
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Cancelled {
-    pub id: Id,
+pub struct Assign {
+    pub bots: Selector,
+    pub group: Option<String>,
+}
+
+impl Tag for Assign {
+    const PATH: &'static str = "assign";
 }
 
+/// Cancel the command with the given id. Bots working on it stop.
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Finished {
-    pub id: Id,
+pub struct Cancel {
+    pub command: Id,
+}
+
+impl Tag for Cancel {
+    const PATH: &'static str = "cancel";
 }
 
+/// Run a [Rhai](https://rhai.rs) script on the selected bots. Each bot runs
+/// its own copy until the script is done.
 #[derive(Serialize, Deserialize, Debug)]
+pub struct Script {
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Selector::is_all")]
+    pub bots: Selector,
+}
+
+impl Tag for Script {
+    const PATH: &'static str = "script";
+}
+
+/// A command sent to the swarm. `data` depends on `path` (i.e., [`GoTo`] for
+/// [`GoTo::PATH`]).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Command {
-    id: u64,
-    path: String,
-    data: serde_json::Value,
+    pub id: Id,
+    pub path: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
 }
 
-pub struct Comm {
-    rx: tokio::sync::mpsc::UnboundedReceiver<Command>,
-    tx: tokio::sync::mpsc::UnboundedSender<Command>,
+impl Command {
+    /// parse the data of the command
+    pub fn parse<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_value(self.data.clone())
+    }
 }
 
-type Res<T = ()> = Result<T, Box<dyn std::error::Error>>;
+/// The command was accepted and given to `bots`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Ack {
+    pub id: Id,
+    pub bots: Vec<u32>,
+}
 
-fn incoming(msg: tungstenite::Message) -> Res<Command> {
-    let data = msg.into_data();
-    let command: Command = serde_json::from_slice(&data)?;
-    Ok(command)
+/// How a bot stopped working on a command
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Finished,
+    Cancelled,
+    /// the bot could not do what it was told (i.e., the player to attack left)
+    Failed,
 }
 
-fn outgoing(command: Command) -> Res<Message> {
-    let string = serde_json::to_string(&command)?;
-    Ok(Message::Text(string))
+/// A bot stopped working on a command. `remaining` bots are still working on
+/// it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub id: Id,
+    pub bot: u32,
+    pub outcome: Outcome,
+    pub remaining: usize,
 }
 
-impl Comm {
-    pub async fn recv(&mut self) -> Command {
-        self.rx.recv().await.unwrap()
-    }
+/// Every bot finished the command
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Finished {
+    pub id: Id,
+}
 
-    pub fn send(&mut self, command: Command) {
-        self.tx.send(command).unwrap();
-    }
+/// Every bot stopped working on the command and at least one did not finish
+/// (it was cancelled or failed)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Cancelled {
+    pub id: Id,
+}
 
-    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Res<Self> {
-        let stream = TcpStream::connect(addr).await?;
-        let (recv_tx, recv_rx) = tokio::sync::mpsc::unbounded_channel();
-        let (send_tx, mut send_rx) = tokio::sync::mpsc::unbounded_channel();
-        let mut ws = tokio_tungstenite::accept_async(stream).await?;
-        tokio::spawn(async move {
-            let mut msg_to_send = None;
-            tokio::select! {
-                val = ws.next() => {
-                    if let Some(Ok(msg)) = val {
-                       if let Ok(cmd) = incoming(msg)  {
-                            let _ = recv_tx.send(cmd);
-                        }
-                    }
-                }
-                val = send_rx.recv() => {
-                    if let Some(cmd) = val {
-                        if let Ok(msg) = outgoing(cmd) {
-                            msg_to_send = Some(msg);
-                        }
-                    }
-                }
-            }
-
-            if let Some(msg) = msg_to_send {
-                let _ = ws.send(msg).await;
-            }
-        });
+/// What went wrong with a message
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// the message is not a valid [`Command`]
+    InvalidMessage,
+    /// there is no command with the path
+    UnknownPath,
+    /// the data does not fit the path
+    InvalidData,
+    /// something the command refers to (i.e., a player) does not exist
+    NotFound,
+    /// the command could not be run
+    Failed,
+    /// the client may not run the command (i.e., it only has a read-only
+    /// token)
+    Forbidden,
+}
 
-        Ok(Self {
-            rx: recv_rx,
-            tx: send_tx,
-        })
-    }
+/// The command with `id` failed. `id` is [`None`] if the message did not have
+/// an id.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub id: Option<Id>,
+    pub kind: ErrorKind,
+    pub message: String,
+}
 
-    pub async fn host<A: ToSocketAddrs>(addr: A) -> Res<Self> {
-        let server = TcpListener::bind(addr).await?;
-        let (recv_tx, recv_rx) = tokio::sync::mpsc::unbounded_channel();
-        let (send_tx, mut send_rx) = tokio::sync::mpsc::unbounded_channel();
-
-        tokio::spawn(async move {
-            loop {
-                let _ignored: Res = async {
-                    let (stream, _) = server.accept().await?;
-                    let mut ws = tokio_tungstenite::accept_async(stream).await?;
-
-                    let mut msg_to_send = None;
-                    tokio::select! {
-                        val = ws.next() => {
-                            if let Some(Ok(msg)) = val {
-                               if let Ok(cmd) = incoming(msg)  {
-                                    let _ = recv_tx.send(cmd);
-                                }
-                            }
-                        }
-                        val = send_rx.recv() => {
-                            if let Some(cmd) = val {
-                                if let Ok(msg) = outgoing(cmd) {
-                                    msg_to_send = Some(msg)
-                                }
-                            }
-                        }
-                    }
-
-                    if let Some(msg) = msg_to_send {
-                        let _ = ws.send(msg).await;
-                    }
-
-                    Ok(())
-                }
-                .await;
-            }
-        });
+/// The answer to a query (see [`query`])
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Data {
+    pub id: Id,
+    pub data: serde_json::Value,
+}
 
-        Ok(Self {
-            rx: recv_rx,
-            tx: send_tx,
-        })
-    }
+/// A message sent by the swarm in response to a [`Command`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Ack(Ack),
+    Progress(Progress),
+    Finished(Finished),
+    Cancelled(Cancelled),
+    Data(Data),
+    Event(EventMessage),
+    Error(Error),
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Command;
+    use crate::{
+        events::{Event, EventMessage},
+        Attack, Command, Error, ErrorKind, Finished, Response, Selector, Tag,
+    };
 
     #[test]
     fn test() {
@@ -199,6 +261,53 @@ mod tests {
             }),
         };
 
-        serde_json::to_string(&command).unwrap();
+        let encoded = Attack {
+            name: "hello".to_string(),
+            bots: Selector::default(),
+        }
+        .encode(123);
+
+        assert_eq!(serde_json::from_str::<Command>(&encoded).unwrap(), command);
+        assert_eq!(command.parse::<Attack>().unwrap().name, "hello");
+    }
+
+    #[test]
+    fn test_response() {
+        let finished = Response::Finished(Finished { id: 5 });
+        assert_eq!(
+            serde_json::to_value(&finished).unwrap(),
+            serde_json::json!({ "type": "finished", "id": 5 })
+        );
+
+        let event = Response::Event(EventMessage {
+            subscription: 1,
+            bot: 2,
+            server: "localhost:25565".to_string(),
+            event: Event::Health {
+                health: 20.0,
+                food: 18,
+            },
+        });
+        let json = serde_json::json!({
+            "type": "event",
+            "subscription": 1,
+            "bot": 2,
+            "server": "localhost:25565",
+            "event": "health",
+            "health": 20.0,
+            "food": 18
+        });
+        assert_eq!(serde_json::to_value(&event).unwrap(), json);
+        assert_eq!(serde_json::from_value::<Response>(json).unwrap(), event);
+
+        let error = r#"{"type":"error","id":null,"kind":"invalid_message","message":"oops"}"#;
+        assert_eq!(
+            serde_json::from_str::<Response>(error).unwrap(),
+            Response::Error(Error {
+                id: None,
+                kind: ErrorKind::InvalidMessage,
+                message: "oops".to_string()
+            })
+        );
     }
 }
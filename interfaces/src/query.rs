@@ -0,0 +1,178 @@
+//! Read-only queries of the state of the swarm. Queries are sent like any
+//! other [`Command`](crate::Command) and answered with a single
+//! [`Response::Data`](crate::Response::Data) instead of an acknowledgement.
+//!
+//! Bots on different servers do not share a world, so world queries take the
+//! `server` (`host:port`) to query. It can be left out if the swarm is only on
+//! one server.
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    types::{BlockLocation, Dimension, Location, SimpleType},
+    Selector, Tag,
+};
+
+/// The most blocks a [`GetBlocks`] query can return
+pub const MAX_BLOCKS: usize = 32_768;
+
+/// The state of the selected bots. Answered with a list of [`BotInfo`].
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct GetBots {
+    #[serde(default)]
+    pub bots: Selector,
+}
+
+impl Tag for GetBots {
+    const PATH: &'static str = "get_bots";
+}
+
+/// The block at `location`. Answered with a [`BlockInfo`].
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetBlock {
+    pub location: BlockLocation,
+    #[serde(default)]
+    pub server: Option<String>,
+}
+
+impl Tag for GetBlock {
+    const PATH: &'static str = "get_block";
+}
+
+/// The blocks between `from` and `to` (inclusive). Answered with a
+/// [`BlockRegion`]. At most [`MAX_BLOCKS`] blocks can be queried at once.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetBlocks {
+    pub from: BlockLocation,
+    pub to: BlockLocation,
+    #[serde(default)]
+    pub server: Option<String>,
+}
+
+impl Tag for GetBlocks {
+    const PATH: &'static str = "get_blocks";
+}
+
+/// The entities within `radius` blocks of `location`. Answered with a list of
+/// [`EntityInfo`].
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetEntities {
+    pub location: Location,
+    pub radius: f64,
+    #[serde(default)]
+    pub server: Option<String>,
+}
+
+impl Tag for GetEntities {
+    const PATH: &'static str = "get_entities";
+}
+
+/// The players in the tab list. Answered with a list of [`PlayerInfo`].
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct GetPlayers {
+    #[serde(default)]
+    pub server: Option<String>,
+}
+
+impl Tag for GetPlayers {
+    const PATH: &'static str = "get_players";
+}
+
+/// An item in an inventory
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ItemInfo {
+    pub slot: usize,
+    pub id: u32,
+    pub count: u8,
+    pub damage: u16,
+}
+
+/// A bot
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BotInfo {
+    pub id: u32,
+    pub username: String,
+    pub server: String,
+    pub group: Option<String>,
+    pub health: f32,
+    pub food: u8,
+    pub dimension: Dimension,
+    pub location: Location,
+    pub on_ground: bool,
+    /// the name of the current task
+    pub task: Option<String>,
+    /// what the current task is doing
+    #[serde(default)]
+    pub activity: Option<TaskInfo>,
+    /// the id of the websocket command the bot is working on (if it was sent
+    /// on the same connection)
+    pub command: Option<u64>,
+    /// the selected hotbar slot (0-8)
+    pub selected_slot: u8,
+    pub inventory: Vec<ItemInfo>,
+}
+
+/// What a task is doing. Tasks which run other tasks (i.e., a sequence of
+/// tasks) have the one they are on as `current`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TaskInfo {
+    pub name: String,
+    /// how far the task is from 0 to 1 (if it knows)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<Box<TaskInfo>>,
+}
+
+impl Display for TaskInfo {
+    /// the chain of tasks (i.e., `compound 50% > navigate 20%`)
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(progress) = self.progress {
+            write!(f, " {:.0}%", progress * 100.0)?;
+        }
+        if let Some(current) = &self.current {
+            write!(f, " > {current}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A block as far as the swarm knows
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockInfo {
+    /// the chunk is not loaded
+    Unknown,
+    /// the chunk is loaded
+    Known { id: u32, metadata: u8 },
+    /// the chunk is not loaded, but the type of block is known (i.e., from a
+    /// schematic)
+    Estimate { simple: SimpleType },
+}
+
+/// Blocks from `from` to `to`. Blocks are ordered by y, then z, then x.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlockRegion {
+    pub from: BlockLocation,
+    pub to: BlockLocation,
+    pub blocks: Vec<BlockInfo>,
+}
+
+/// An entity
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EntityInfo {
+    pub id: u32,
+    pub location: Location,
+    /// the UUID if the entity is a player
+    pub player: Option<String>,
+}
+
+/// A player in the tab list
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlayerInfo {
+    pub name: String,
+    pub uuid: String,
+}
@@ -52,6 +52,15 @@ pub struct Chat {
 }
 
 impl Chat {
+    /// the text of all sections without any formatting
+    pub fn plain(&self) -> String {
+        self.extra
+            .iter()
+            .flatten()
+            .map(|section| section.text.as_str())
+            .collect()
+    }
+
     pub fn colorize(self) -> String {
         if let Some(extra) = self.extra {
             extra.into_iter().map(|section| section.colorize()).join("")
@@ -632,7 +641,8 @@ impl From<Displacement> for Direction {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Dimension {
     Nether,
     Overworld,
@@ -725,7 +735,7 @@ impl Selection2D {
     }
 }
 
-#[derive(Writable, Readable, Debug, Copy, Clone, Default, PartialEq)]
+#[derive(Writable, Readable, Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq)]
 pub struct Location {
     pub x: f64,
     pub y: f64,
@@ -998,7 +1008,8 @@ impl BlockApprox {
     }
 }
 
-#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SimpleType {
     Solid,
     Water,
@@ -1047,6 +1058,7 @@ impl BlockKind {
     pub const STONE: BlockKind = BlockKind(1);
     pub const DIRT: BlockKind = BlockKind(3);
     pub const GLASS: BlockKind = BlockKind(20);
+    pub const VINE: BlockKind = BlockKind(106);
 
     #[inline]
     pub fn id(self) -> u32 {
@@ -1071,6 +1083,23 @@ impl BlockKind {
         matches!(self.id(), 4)
     }
 
+    /// if players can climb up and down it (ladders and vines)
+    pub fn climbable(self) -> bool {
+        matches!(self, Self::LADDER | Self::VINE)
+    }
+
+    /// if this is a door (including iron doors)
+    pub fn is_door(self) -> bool {
+        matches!(self.id(), 64 | 71 | 193..=197)
+    }
+
+    /// if players can open it by right-clicking (wooden doors, trapdoors and
+    /// fence gates). Iron doors and iron trapdoors need redstone, so they are
+    /// not.
+    pub fn openable(self) -> bool {
+        matches!(self.id(), 64 | 96 | 107 | 183..=187 | 193..=197)
+    }
+
     pub fn mineable(&self, blocks: &BlockData) -> bool {
         // we can't mine air
         if self.0 == 0 {
@@ -1163,14 +1192,35 @@ impl BlockState {
     }
 
     pub fn is_water(&self) -> bool {
-        matches!(
-            self.id(),
-            8 | 9 | 65 // ladder ... this is VERY jank
-        )
+        matches!(self.id(), 8 | 9)
     }
 
     pub fn walk_through(&self) -> bool {
-        self.is_water() || self.no_motion_effect()
+        self.is_water()
+            || self.kind().climbable()
+            || self.no_motion_effect()
+            || self.kind().openable() && (self.is_open() || self.is_upper_door())
+    }
+
+    /// if this is the top half of a door. Only the bottom half stores whether
+    /// the door is open.
+    pub fn is_upper_door(&self) -> bool {
+        self.kind().is_door() && self.metadata() & 0b1000 != 0
+    }
+
+    /// if a door (bottom half), trapdoor or fence gate is open
+    pub fn is_open(&self) -> bool {
+        self.metadata() & 0b0100 != 0
+    }
+
+    /// if this has to be right-clicked open before we can walk through it
+    pub fn is_closed(&self) -> bool {
+        self.kind().openable() && !self.is_upper_door() && !self.is_open()
+    }
+
+    /// the state of a door (bottom half), trapdoor or fence gate once opened
+    pub fn opened(self) -> BlockState {
+        BlockState(self.0 | 0b0100)
     }
 
     pub fn no_motion_effect(&self) -> bool {
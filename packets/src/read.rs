@@ -160,6 +160,12 @@ impl ByteReadable for u64 {
     }
 }
 
+impl ByteReadable for i64 {
+    fn read_from_bytes(byte_reader: &mut ByteReader) -> Self {
+        byte_reader.bytes.get_i64()
+    }
+}
+
 impl<const T: usize> ByteReadable for [u8; T] {
     fn read_from_bytes(byte_reader: &mut ByteReader) -> Self {
         let mut bytes = [0; T];
@@ -0,0 +1,235 @@
+//! The `cache` subcommands used to manage the account cache
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context};
+
+use crate::bootstrap::{
+    config::ProxyPolicy,
+    opts::{CacheCommand, CacheFormat, CacheOptions, Validity},
+    storage::{load_proxies, time, CachedUser, UserCache, CACHE_FILE},
+};
+
+/// how long ago a unix time (seconds) was, for instance `3h ago`
+fn ago(unix: u64) -> String {
+    if unix == 0 {
+        return "never".to_string();
+    }
+
+    let secs = time().saturating_sub(unix);
+    match secs {
+        0..=59 => format!("{secs}s ago"),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+/// the format of `file`, inferred from the extension if not given
+fn format_of(file: &str, format: Option<CacheFormat>) -> CacheFormat {
+    format.unwrap_or_else(
+        || match Path::new(file).extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => CacheFormat::Csv,
+            _ => CacheFormat::Json,
+        },
+    )
+}
+
+fn export(users: &[CachedUser], format: CacheFormat, out: impl Write) -> anyhow::Result<()> {
+    match format {
+        CacheFormat::Json => serde_json::to_writer_pretty(out, users)?,
+        CacheFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for user in users {
+                writer.serialize(user)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn import(format: CacheFormat, input: impl Read) -> anyhow::Result<Vec<CachedUser>> {
+    let users = match format {
+        CacheFormat::Json => serde_json::from_reader(input)?,
+        CacheFormat::Csv => csv::Reader::from_reader(input)
+            .deserialize()
+            .collect::<Result<_, _>>()?,
+    };
+    Ok(users)
+}
+
+/// print all cached users
+fn list(cache: &UserCache) {
+    let users = cache.users();
+    println!(
+        "{:<40} {:<10} {:<16} last checked",
+        "email", "status", "username"
+    );
+    for user in &users {
+        println!(
+            "{:<40} {:<10} {:<16} {}",
+            user.email,
+            format!("{:?}", user.status).to_lowercase(),
+            user.username,
+            ago(user.last_checked)
+        );
+    }
+    println!("{} users", users.len());
+}
+
+/// Run a `cache` subcommand
+pub async fn run(opts: CacheOptions) -> anyhow::Result<()> {
+    let CacheOptions {
+        command,
+        encrypt_cache,
+    } = opts;
+
+    let mut cache = UserCache::load(CACHE_FILE.into(), encrypt_cache)?;
+
+    match command {
+        CacheCommand::List => {
+            list(&cache);
+
+            // nothing changed so there is no need to save
+            return Ok(());
+        }
+        CacheCommand::Validate {
+            emails,
+            auth,
+            proxy,
+            proxies_file,
+        } => {
+            let policy = if proxy {
+                ProxyPolicy::Cycle
+            } else {
+                ProxyPolicy::None
+            };
+            let mut proxies = load_proxies(&proxies_file, policy)?.peekable();
+
+            // a custom auth server is discovered through the first proxy
+            let first = proxies.peek().cloned().flatten();
+            let auth = auth.resolve(first.as_ref()).await?;
+
+            let emails = if emails.is_empty() {
+                cache.users().into_iter().map(|user| user.email).collect()
+            } else {
+                emails
+            };
+
+            for email in emails {
+                match cache.revalidate(&email, &auth, &mut proxies).await {
+                    Ok(true) => println!("{email} is valid"),
+                    Ok(false) => println!("{email} is invalid"),
+                    Err(e) => println!("could not validate {email} .. {e}"),
+                }
+            }
+        }
+        CacheCommand::Mark { status, emails } => {
+            let valid = matches!(status, Validity::Valid);
+            for email in emails {
+                if !cache.mark(&email, valid) {
+                    println!("{email} is not cached");
+                }
+            }
+        }
+        CacheCommand::Remove { emails } => {
+            for email in emails {
+                if !cache.remove(&email) {
+                    println!("{email} is not cached");
+                }
+            }
+        }
+        CacheCommand::Import { file, format } => {
+            let format = format_of(&file, format);
+            let input = File::open(&file).with_context(|| format!("could not open {file}"))?;
+            let users = import(format, input).with_context(|| format!("could not read {file}"))?;
+
+            println!("imported {} users", users.len());
+            for user in users {
+                cache.insert(user);
+            }
+        }
+        CacheCommand::Export {
+            file,
+            format,
+            status,
+        } => {
+            let users: Vec<_> = cache
+                .users()
+                .into_iter()
+                .filter(|user| status.map_or(true, |status| user.status == status))
+                .collect();
+
+            match file {
+                None => export(
+                    &users,
+                    format.unwrap_or(CacheFormat::Json),
+                    std::io::stdout(),
+                )?,
+                Some(file) => {
+                    if Path::new(&file).exists() {
+                        bail!("{file} already exists");
+                    }
+                    let out =
+                        File::create(&file).with_context(|| format!("could not create {file}"))?;
+                    export(&users, format_of(&file, format), out)?;
+                    println!("exported {} users to {file}", users.len());
+                }
+            }
+
+            return Ok(());
+        }
+    }
+
+    cache.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bootstrap::{
+        accounts::{export, import},
+        opts::CacheFormat,
+        storage::{CachedUser, UserStatus},
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let users = vec![
+            CachedUser {
+                email: "a@example.com".to_string(),
+                status: UserStatus::Valid,
+                password: "pass:word".to_string(),
+                username: "Alice".to_string(),
+                uuid: "069a79f444e94726a5befca90e38aaf5".to_string(),
+                access_id: "access".to_string(),
+                client_id: "client".to_string(),
+                refresh_token: String::new(),
+                expires_at: 0,
+                last_checked: 123,
+            },
+            CachedUser {
+                email: "b@example.com".to_string(),
+                status: UserStatus::Invalid,
+                password: "hunter2".to_string(),
+                username: String::new(),
+                uuid: String::new(),
+                access_id: String::new(),
+                client_id: String::new(),
+                refresh_token: String::new(),
+                expires_at: 0,
+                last_checked: 0,
+            },
+        ];
+
+        for format in [CacheFormat::Csv, CacheFormat::Json] {
+            let mut data = Vec::new();
+            export(&users, format, &mut data).unwrap();
+            assert_eq!(import(format, data.as_slice()).unwrap(), users);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Encryption of the account cache (`cache.db`) at rest
+
+use std::io;
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{bail, Context};
+use crossterm::{
+    event,
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+
+/// The environment variable the cache passphrase is read from. If it is not
+/// set, the passphrase is prompted for.
+pub const PASSPHRASE_ENV: &str = "SWARMBOT_CACHE_PASSPHRASE";
+
+/// The start of every encrypted cache. Unencrypted caches are a raw bincode
+/// `Root` and never start with this.
+const MAGIC: &[u8; 8] = b"SBCACHE1";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// A key derived from the cache passphrase along with the salt it was derived
+/// with. The salt is stored in the clear at the start of the encrypted file.
+pub struct CacheKey {
+    key: [u8; 32],
+    salt: [u8; SALT_LEN],
+}
+
+impl CacheKey {
+    /// derive a key from `passphrase` with Argon2id
+    fn derive(passphrase: &str, salt: [u8; SALT_LEN]) -> anyhow::Result<Self> {
+        let mut key = [0; 32];
+        argon2::Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow::anyhow!("could not derive cache key: {e}"))?;
+        Ok(Self { key, salt })
+    }
+
+    /// derive a key for a new encrypted cache
+    pub fn new(passphrase: &str) -> anyhow::Result<Self> {
+        Self::derive(passphrase, rand::random())
+    }
+
+    /// derive the key of an existing encrypted cache
+    pub fn for_file(passphrase: &str, data: &[u8]) -> anyhow::Result<Self> {
+        let salt = data
+            .get(MAGIC.len()..MAGIC.len() + SALT_LEN)
+            .context("encrypted cache is truncated")?;
+        Self::derive(passphrase, salt.try_into()?)
+    }
+
+    /// encrypt `plain` into the on-disk format
+    /// `MAGIC | salt | nonce | ciphertext`
+    pub fn encrypt(&self, plain: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let cipher = Aes256Gcm::new(&self.key.into());
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let cipher_text = cipher
+            .encrypt(&nonce, plain)
+            .map_err(|_| anyhow::anyhow!("could not encrypt cache"))?;
+
+        let mut res = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + cipher_text.len());
+        res.extend_from_slice(MAGIC);
+        res.extend_from_slice(&self.salt);
+        res.extend_from_slice(&nonce);
+        res.extend_from_slice(&cipher_text);
+        Ok(res)
+    }
+
+    /// decrypt data written by [`CacheKey::encrypt`]
+    pub fn decrypt(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let header = MAGIC.len() + SALT_LEN;
+        let nonce = data
+            .get(header..header + NONCE_LEN)
+            .context("encrypted cache is truncated")?;
+        let cipher_text = &data[header + NONCE_LEN..];
+
+        let cipher = Aes256Gcm::new(&self.key.into());
+        cipher
+            .decrypt(Nonce::from_slice(nonce), cipher_text)
+            .map_err(|_| anyhow::anyhow!("could not decrypt cache (wrong passphrase?)"))
+    }
+}
+
+/// if the data is an encrypted cache
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// read a line from the terminal without echoing it
+fn read_hidden() -> io::Result<String> {
+    terminal::enable_raw_mode()?;
+
+    let mut passphrase = String::new();
+    let res = loop {
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = (match event::read() {
+            Ok(event) => event,
+            Err(e) => break Err(e),
+        })
+        else {
+            continue;
+        };
+
+        match code {
+            KeyCode::Enter => break Ok(passphrase),
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                break Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+            }
+            KeyCode::Char(c) => passphrase.push(c),
+            KeyCode::Backspace => {
+                passphrase.pop();
+            }
+            _ => {}
+        }
+    };
+
+    terminal::disable_raw_mode()?;
+    println!();
+    res
+}
+
+/// Get the cache passphrase from [`PASSPHRASE_ENV`] or prompt for it. A new
+/// passphrase has to be entered twice.
+pub fn passphrase(new: bool) -> anyhow::Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        if passphrase.is_empty() {
+            bail!("the cache passphrase cannot be empty ({PASSPHRASE_ENV} is set but empty)");
+        }
+        return Ok(passphrase);
+    }
+
+    print!("cache.db passphrase: ");
+    io::Write::flush(&mut io::stdout())?;
+    let passphrase = read_hidden().context("could not read passphrase")?;
+
+    if new {
+        print!("repeat passphrase: ");
+        io::Write::flush(&mut io::stdout())?;
+        if read_hidden().context("could not read passphrase")? != passphrase {
+            bail!("passphrases do not match");
+        }
+    }
+
+    if passphrase.is_empty() {
+        bail!("the cache passphrase cannot be empty");
+    }
+
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bootstrap::cache_key::{is_encrypted, CacheKey};
+
+    #[test]
+    fn test_round_trip() {
+        let key = CacheKey::new("hunter2").unwrap();
+        let data = key.encrypt(b"email:password").unwrap();
+
+        assert!(is_encrypted(&data));
+        assert!(!data.windows(8).any(|w| w == b"password"));
+
+        let key = CacheKey::for_file("hunter2", &data).unwrap();
+        assert_eq!(key.decrypt(&data).unwrap(), b"email:password");
+
+        let wrong = CacheKey::for_file("hunter3", &data).unwrap();
+        assert!(wrong.decrypt(&data).is_err());
+    }
+}
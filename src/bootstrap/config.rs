@@ -0,0 +1,506 @@
+//! The TOML configuration file. Launcher settings in the file are overridden
+//! by CLI flags.
+//!
+//! ```toml
+//! host = "localhost"
+//! port = 25565
+//! count = 2
+//! proxy = "random"
+//!
+//! # the defaults of every bot
+//! [bot.path]
+//! parkour = false
+//!
+//! [bot.chat]
+//! allowed_players = ["Notch"]
+//!
+//! # 5 more bots which override the defaults
+//! [[groups]]
+//! name = "miners"
+//! count = 5
+//! users_file = "miners.csv"
+//!
+//! [groups.path.costs]
+//! mine_unrelated = 5.0
+//!
+//! # 50 bots on another server. Its world is kept separate.
+//! [[servers]]
+//! name = "lobby"
+//! host = "lobby.example.com"
+//! count = 50
+//! users_file = "lobby.csv"
+//! ```
+
+use std::{net::IpAddr, path::Path};
+
+use anyhow::Context;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+
+use crate::{
+    bootstrap::{Address, Proxy},
+    client::state::local::config::BotConfig,
+};
+
+/// The config file used if `--config` is not given (only if it exists)
+pub const DEFAULT_CONFIG_FILE: &str = "swarmbot.toml";
+
+/// How proxies are assigned to bots
+#[derive(clap::ValueEnum, Deserialize, Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyPolicy {
+    /// no proxies are used
+    #[default]
+    None,
+    /// bots go through the proxies in order. Once all proxies are used, bots
+    /// go back to the first proxy
+    Cycle,
+    /// every bot uses a random proxy
+    Random,
+}
+
+impl ProxyPolicy {
+    /// the proxy for each successive bot
+    pub fn assign(self, proxies: Vec<Proxy>) -> Box<dyn Iterator<Item = Option<Proxy>>> {
+        match self {
+            Self::None => Box::new(std::iter::repeat(None)),
+            Self::Cycle => Box::new(proxies.into_iter().map(Some).cycle()),
+            Self::Random => Box::new(std::iter::repeat_with(move || {
+                proxies.choose(&mut rand::thread_rng()).cloned()
+            })),
+        }
+    }
+}
+
+/// A named group of bots in the config file
+#[derive(Deserialize, Debug)]
+pub struct GroupConfig {
+    pub name: String,
+    pub count: usize,
+    pub users_file: Option<String>,
+    pub proxies_file: Option<String>,
+    pub proxy: Option<ProxyPolicy>,
+
+    /// the name of the server the group joins. The default server if not given
+    pub server: Option<String>,
+
+    /// the overrides of the `[bot]` section
+    #[serde(flatten)]
+    pub bot: toml::Table,
+}
+
+/// A server in the config file. Bots on different servers do not share any
+/// world state.
+#[derive(Deserialize, Debug)]
+pub struct ServerConfig {
+    pub name: String,
+    pub host: String,
+    pub port: Option<u16>,
+    pub version: Option<usize>,
+
+    /// the number of bots which are not in a group
+    #[serde(default)]
+    pub count: usize,
+    pub users_file: Option<String>,
+    pub proxies_file: Option<String>,
+    pub proxy: Option<ProxyPolicy>,
+
+    /// the overrides of the `[bot]` section for bots on this server. Groups
+    /// on this server override these again
+    #[serde(flatten)]
+    pub bot: toml::Table,
+}
+
+/// The config file
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub version: Option<usize>,
+    pub online: Option<bool>,
+    pub count: Option<usize>,
+    pub delay_ms: Option<u64>,
+    pub ws_port: Option<u16>,
+    pub ws_bind: Option<IpAddr>,
+    pub ws_tokens: Vec<String>,
+    pub ws_read_tokens: Vec<String>,
+    pub ws_cert: Option<String>,
+    pub ws_key: Option<String>,
+    pub metrics_port: Option<u16>,
+    pub users_file: Option<String>,
+    pub proxies_file: Option<String>,
+    pub proxy: Option<ProxyPolicy>,
+
+    /// the [`BotConfig`] of every bot
+    pub bot: toml::Table,
+
+    /// named groups of bots
+    pub groups: Vec<GroupConfig>,
+
+    /// servers besides the default server (`host`)
+    pub servers: Vec<ServerConfig>,
+}
+
+/// The launcher settings with CLI flags and the config file combined. These
+/// are the defaults of servers and groups.
+#[derive(Debug)]
+pub struct Defaults {
+    /// the default server. Only groups and servers with their own host can be
+    /// launched if [`None`]
+    pub host: Option<String>,
+    pub port: u16,
+    pub version: usize,
+
+    /// the number of bots on the default server which are not in a group
+    pub count: usize,
+    pub users_file: String,
+    pub proxies_file: String,
+    pub proxy: ProxyPolicy,
+}
+
+/// A group of bots which are launched on the same server with the same
+/// accounts, proxies and [`BotConfig`]
+#[derive(Debug)]
+pub struct BotGroup {
+    /// the server the bots join
+    pub server: Address,
+
+    /// the protocol version of the server
+    pub version: usize,
+
+    pub count: usize,
+    pub users_file: String,
+    pub proxies_file: String,
+    pub proxy: ProxyPolicy,
+    pub config: BotConfig,
+}
+
+/// recursively merge `over` into `base`. Tables are merged and everything else
+/// is replaced.
+fn merge(base: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(over)) => merge(base, over),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+impl FileConfig {
+    /// Load the config at `path`. If no path is given [`DEFAULT_CONFIG_FILE`]
+    /// is used if it exists.
+    pub fn load(path: Option<&str>) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => DEFAULT_CONFIG_FILE,
+            None => return Ok(Self::default()),
+        };
+
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read config file {path}"))?;
+
+        Self::parse(&contents).with_context(|| format!("invalid config file {path}"))
+    }
+
+    fn parse(contents: &str) -> anyhow::Result<Self> {
+        let config: Self = toml::from_str(contents)?;
+
+        // so invalid bot settings are reported before launching
+        config.bot_config(None, None)?;
+        for server in &config.servers {
+            config
+                .bot_config(Some(server), None)
+                .with_context(|| format!("invalid server {}", server.name))?;
+        }
+        for group in &config.groups {
+            let server = config
+                .server(group)
+                .with_context(|| format!("invalid group {}", group.name))?;
+            config
+                .bot_config(server, Some(group))
+                .with_context(|| format!("invalid group {}", group.name))?;
+        }
+
+        Ok(config)
+    }
+
+    /// the server `group` joins. [`None`] is the default server.
+    fn server(&self, group: &GroupConfig) -> anyhow::Result<Option<&ServerConfig>> {
+        let Some(name) = &group.server else {
+            return Ok(None);
+        };
+
+        self.servers
+            .iter()
+            .find(|server| &server.name == name)
+            .with_context(|| format!("server {name} does not exist"))
+            .map(Some)
+    }
+
+    /// the [`BotConfig`] of bots on `server` in `group`. The `[bot]` section is
+    /// overridden by the server which is overridden by the group.
+    fn bot_config(
+        &self,
+        server: Option<&ServerConfig>,
+        group: Option<&GroupConfig>,
+    ) -> anyhow::Result<BotConfig> {
+        let mut table = self.bot.clone();
+        if let Some(server) = server {
+            merge(&mut table, server.bot.clone());
+        }
+        if let Some(group) = group {
+            merge(&mut table, group.bot.clone());
+        }
+
+        let mut config: BotConfig = toml::Value::Table(table).try_into()?;
+        config.group = group.map(|group| group.name.clone());
+        Ok(config)
+    }
+
+    /// The groups of bots to launch. `defaults.count` bots are launched on the
+    /// default server which are not in a named group, followed by the bots of
+    /// each server which are not in a named group, followed by the bots of
+    /// each named group.
+    pub fn groups(&self, defaults: &Defaults) -> anyhow::Result<Vec<BotGroup>> {
+        let default_server = || {
+            defaults
+                .host
+                .as_ref()
+                .map(|host| Address {
+                    host: host.clone(),
+                    port: defaults.port,
+                })
+                .context("no host given. Pass it as an argument or set `host` in the config file")
+        };
+
+        let mut groups = Vec::new();
+
+        if defaults.count > 0 {
+            groups.push(BotGroup {
+                server: default_server()?,
+                version: defaults.version,
+                count: defaults.count,
+                users_file: defaults.users_file.clone(),
+                proxies_file: defaults.proxies_file.clone(),
+                proxy: defaults.proxy,
+                config: self.bot_config(None, None)?,
+            });
+        }
+
+        for server in &self.servers {
+            if server.count == 0 {
+                continue;
+            }
+            groups.push(BotGroup {
+                server: server.address(defaults),
+                version: server.version.unwrap_or(defaults.version),
+                count: server.count,
+                users_file: server
+                    .users_file
+                    .clone()
+                    .unwrap_or_else(|| defaults.users_file.clone()),
+                proxies_file: server
+                    .proxies_file
+                    .clone()
+                    .unwrap_or_else(|| defaults.proxies_file.clone()),
+                proxy: server.proxy.unwrap_or(defaults.proxy),
+                config: self.bot_config(Some(server), None)?,
+            });
+        }
+
+        for group in &self.groups {
+            let server = self.server(group)?;
+
+            // groups fall back to the settings of their server
+            let users_file = group
+                .users_file
+                .clone()
+                .or_else(|| server.and_then(|server| server.users_file.clone()))
+                .unwrap_or_else(|| defaults.users_file.clone());
+            let proxies_file = group
+                .proxies_file
+                .clone()
+                .or_else(|| server.and_then(|server| server.proxies_file.clone()))
+                .unwrap_or_else(|| defaults.proxies_file.clone());
+            let proxy = group
+                .proxy
+                .or_else(|| server.and_then(|server| server.proxy))
+                .unwrap_or(defaults.proxy);
+
+            groups.push(BotGroup {
+                server: match server {
+                    Some(server) => server.address(defaults),
+                    None => default_server()
+                        .with_context(|| format!("group {} has no server", group.name))?,
+                },
+                version: server
+                    .and_then(|server| server.version)
+                    .unwrap_or(defaults.version),
+                count: group.count,
+                users_file,
+                proxies_file,
+                proxy,
+                config: self.bot_config(server, Some(group))?,
+            });
+        }
+
+        Ok(groups)
+    }
+}
+
+impl ServerConfig {
+    fn address(&self, defaults: &Defaults) -> Address {
+        Address {
+            host: self.host.clone(),
+            port: self.port.unwrap_or(defaults.port),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use interfaces::types::BlockLocation;
+
+    use crate::bootstrap::config::{Defaults, FileConfig, ProxyPolicy};
+
+    const CONFIG: &str = r#"
+        host = "localhost"
+        count = 2
+        proxy = "random"
+
+        [bot.path]
+        parkour = false
+
+        [bot.path.costs]
+        block_walk = 2.0
+
+        [bot.chat]
+        allowed_players = ["Notch"]
+
+        [bot.blackboard]
+        home = { x = 0, y = 64, z = 0 }
+
+        [[groups]]
+        name = "miners"
+        count = 5
+        users_file = "miners.csv"
+        proxy = "cycle"
+        behavior = "autonomous"
+
+        [groups.path.costs]
+        mine_unrelated = 5.0
+
+        [groups.chat]
+        commands = false
+
+        [[servers]]
+        name = "lobby"
+        host = "lobby.example.com"
+        count = 3
+        users_file = "lobby.csv"
+
+        [servers.chat]
+        print = false
+
+        [[groups]]
+        name = "guards"
+        count = 1
+        server = "lobby"
+        proxy = "none"
+    "#;
+
+    fn defaults() -> Defaults {
+        Defaults {
+            host: Some("localhost".to_string()),
+            port: 25565,
+            version: 340,
+            count: 2,
+            users_file: "users.csv".to_string(),
+            proxies_file: "proxies.csv".to_string(),
+            proxy: ProxyPolicy::Random,
+        }
+    }
+
+    #[test]
+    fn test_groups() {
+        let config = FileConfig::parse(CONFIG).unwrap();
+        assert_eq!(config.host.as_deref(), Some("localhost"));
+
+        let groups = config.groups(&defaults()).unwrap();
+        let [default, lobby, miners, guards] = groups.as_slice() else {
+            panic!("expected four groups");
+        };
+
+        assert_eq!(default.count, 2);
+        assert!(!default.config.path.parkour);
+        assert!(default.config.chat.allows("Notch"));
+        assert!(!default.config.chat.allows("jeb_"));
+
+        // the group keeps the defaults it does not override
+        assert_eq!(miners.count, 5);
+        assert_eq!(miners.users_file, "miners.csv");
+        assert_eq!(miners.proxy, ProxyPolicy::Cycle);
+        assert_eq!(miners.config.group.as_deref(), Some("miners"));
+        assert!(!miners.config.path.parkour);
+        assert!((miners.config.path.costs.block_walk - 2.0).abs() < f64::EPSILON);
+        assert!((miners.config.path.costs.mine_unrelated - 5.0).abs() < f64::EPSILON);
+        assert!(!miners.config.chat.allows("Notch"));
+        assert_eq!(miners.server.host, "localhost");
+
+        // servers override the defaults and their groups override the server
+        assert_eq!(lobby.count, 3);
+        assert_eq!(lobby.server.host, "lobby.example.com");
+        assert_eq!(lobby.server.port, 25565);
+        assert!(!lobby.config.chat.print);
+        assert_eq!(guards.server.host, "lobby.example.com");
+        assert_eq!(guards.users_file, "lobby.csv");
+        assert_eq!(guards.proxy, ProxyPolicy::None);
+        assert!(!guards.config.chat.print);
+        assert!(guards.config.chat.allows("Notch"));
+    }
+
+    #[test]
+    fn test_behavior() {
+        let config = FileConfig::parse(CONFIG).unwrap();
+        let groups = config.groups(&defaults()).unwrap();
+        let miners = &groups[2].config;
+
+        assert_eq!(miners.behavior.as_deref(), Some("autonomous"));
+        assert_eq!(
+            miners.blackboard.get("home"),
+            Some(BlockLocation::new(0, 64, 0))
+        );
+    }
+
+    #[test]
+    fn test_servers() {
+        let config = FileConfig::parse(
+            "[[servers]]\nname = \"a\"\nhost = \"a.example.com\"\nversion = 340\ncount = 4",
+        )
+        .unwrap();
+
+        // bots can be launched without a default server
+        let mut defaults = defaults();
+        defaults.host = None;
+        defaults.count = 0;
+        let groups = config.groups(&defaults).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].count, 4);
+
+        // unless they need it
+        defaults.count = 1;
+        assert!(config.groups(&defaults).is_err());
+
+        assert!(FileConfig::parse("[[groups]]\nname = \"a\"\ncount = 1\nserver = \"b\"").is_err());
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        assert!(FileConfig::parse("hots = \"localhost\"").is_err());
+        assert!(FileConfig::parse("[bot.path]\nparkor = true").is_err());
+        assert!(FileConfig::parse("[[groups]]\nname = \"a\"\ncount = 1\nchta = {}").is_err());
+    }
+}
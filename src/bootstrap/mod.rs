@@ -10,9 +10,16 @@ use tokio::{
     sync::mpsc::Receiver,
 };
 use tokio_socks::tcp::Socks5Stream;
+use tracing::debug;
 
-use crate::bootstrap::storage::{BotConnectionData, BotData};
+use crate::{
+    bootstrap::storage::{BotConnectionData, BotData},
+    client::state::local::config::BotConfig,
+};
 
+pub mod accounts;
+pub mod cache_key;
+pub mod config;
 pub mod csv;
 pub mod dns;
 pub mod mojang;
@@ -20,7 +27,7 @@ pub mod opts;
 pub mod storage;
 
 /// A server address
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Address {
     /// the hostname
     pub host: String,
@@ -43,6 +50,9 @@ pub struct BotConnection {
     /// the address being logged into
     pub server_address: Address,
 
+    /// the config of the bot
+    pub config: BotConfig,
+
     /// A read stream (from the server)
     pub read: OwnedReadHalf,
 
@@ -52,11 +62,13 @@ pub struct BotConnection {
 
 /// Obtain a concrete TCP connection to the sever `address`. This only
 /// establishes a connection and does not anything involving
-async fn obtain_connection(
-    user: BotConnectionData,
-    server_address: Address,
-) -> anyhow::Result<BotConnection> {
-    let BotConnectionData { bot, proxy } = user;
+async fn obtain_connection(user: BotConnectionData) -> anyhow::Result<BotConnection> {
+    let BotConnectionData {
+        bot,
+        proxy,
+        config,
+        server: server_address,
+    } = user;
 
     let target = String::from(&server_address);
 
@@ -73,7 +85,7 @@ async fn obtain_connection(
 
         conn.into_inner()
     } else {
-        println!("connecting to {target}");
+        debug!("connecting to {target}");
         TcpStream::connect(target.as_str()).await.unwrap()
     };
 
@@ -81,24 +93,23 @@ async fn obtain_connection(
     Ok(BotConnection {
         bot,
         server_address,
+        config,
         read,
         write,
     })
 }
 
 impl BotConnection {
-    /// Generates connections given [`BotConnectionData`] and an address
+    /// Generates connections to the server of each [`BotConnectionData`]
     pub fn stream(
-        server_address: Address,
         mut users: impl Stream<Item = BotConnectionData> + Unpin + 'static,
     ) -> Receiver<anyhow::Result<Self>> {
         let (tx, rx) = tokio::sync::mpsc::channel(1);
         tokio::task::spawn_local(async move {
             while let Some(user) = users.next().await {
                 let tx = tx.clone();
-                let address = server_address.clone();
                 tokio::task::spawn_local(async move {
-                    let connection = obtain_connection(user, address).await;
+                    let connection = obtain_connection(user).await;
                     tx.send(connection).await.unwrap();
                 });
             }
@@ -1,23 +1,221 @@
-//! Module for interacting with Mojang
+//! Module for interacting with Mojang and the Microsoft → Xbox Live → XSTS →
+//! Minecraft services token chain
 
-use std::convert::TryFrom;
+use std::{collections::BTreeMap, convert::TryFrom, sync::Mutex, time::Duration};
 
+use anyhow::{anyhow, bail, Context};
 use num_bigint::BigInt;
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha1::{Digest, Sha1};
 use swarm_bot_packets::types::UUID;
+use tracing::info;
 
 use crate::{bootstrap::Proxy, default};
 
+/// How online users are authenticated
+#[derive(clap::ValueEnum, Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum AuthMode {
+    /// the legacy Yggdrasil `authenticate`/`validate`/`refresh` flow
+    #[default]
+    Mojang,
+    /// the Microsoft device-code login followed by the Xbox Live, XSTS and
+    /// Minecraft services token exchanges
+    Microsoft,
+}
+
+/// The base URLs of every auth service. These are configurable so the chain
+/// can be pointed at a local stand-in or a custom auth server.
+#[derive(Clone, Debug)]
+pub struct AuthEndpoints {
+    /// the Microsoft login (`/devicecode` and `/token` live under this)
+    pub microsoft: String,
+    /// Xbox Live user authentication
+    pub xbox_live: String,
+    /// Xbox secure token service
+    pub xsts: String,
+    /// Minecraft services (`login_with_xbox` and the profile)
+    pub minecraft_services: String,
+    /// the Azure application client ID used for the Microsoft login
+    pub client_id: Option<String>,
+    /// the Yggdrasil auth server (`/authenticate`, `/refresh` and `/validate`
+    /// live under this)
+    pub auth_server: String,
+    /// the Yggdrasil session server (`/join` lives under this)
+    pub session_server: String,
+}
+
+impl Default for AuthEndpoints {
+    fn default() -> Self {
+        Self {
+            microsoft: "https://login.microsoftonline.com/consumers/oauth2/v2.0".to_string(),
+            xbox_live: "https://user.auth.xboxlive.com".to_string(),
+            xsts: "https://xsts.auth.xboxlive.com".to_string(),
+            minecraft_services: "https://api.minecraftservices.com".to_string(),
+            client_id: None,
+            auth_server: "https://authserver.mojang.com".to_string(),
+            session_server: "https://sessionserver.mojang.com/session/minecraft".to_string(),
+        }
+    }
+}
+
+/// The metadata an authlib-injector API root responds with
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YggdrasilMetadata {
+    meta: serde_json::Value,
+    signature_publickey: String,
+}
+
+/// the header an authlib-injector server can use to point at its real API root
+const API_LOCATION_HEADER: &str = "X-Authlib-Injector-API-Location";
+
+impl AuthEndpoints {
+    /// Point the Yggdrasil endpoints at an authlib-injector compatible server.
+    ///
+    /// The API root has to respond with the metadata document (`meta` and
+    /// `signaturePublickey`), otherwise this is not an authlib-injector server
+    /// and an error is returned. An `X-Authlib-Injector-API-Location` header
+    /// on the response is followed once. Requests go through `proxy` if given.
+    pub async fn authlib_injector(
+        &mut self,
+        api_root: &str,
+        proxy: Option<&Proxy>,
+    ) -> anyhow::Result<()> {
+        let client = http_client(proxy)?;
+
+        let mut api_root = api_root.trim_end_matches('/').to_string();
+        let mut res = client.get(&api_root).send().await?;
+
+        let location = res
+            .headers()
+            .get(API_LOCATION_HEADER)
+            .and_then(|location| location.to_str().ok())
+            .map(|location| res.url().join(location))
+            .transpose()?;
+
+        if let Some(location) = location {
+            if location.as_str().trim_end_matches('/') != api_root {
+                api_root = location.as_str().trim_end_matches('/').to_string();
+                res = client.get(&api_root).send().await?;
+            }
+        }
+
+        let status = res.status();
+        if !status.is_success() {
+            bail!("{api_root} is not an authlib-injector API root. Error code: {status}");
+        }
+
+        let YggdrasilMetadata {
+            meta,
+            signature_publickey,
+        } = res.json().await.with_context(|| {
+            format!("{api_root} did not respond with authlib-injector metadata")
+        })?;
+
+        if !meta.is_object() || signature_publickey.is_empty() {
+            bail!("{api_root} responded with invalid authlib-injector metadata");
+        }
+
+        let name = meta
+            .get("serverName")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("unnamed");
+        info!("using Yggdrasil server {name} at {api_root}");
+
+        self.auth_server = format!("{api_root}/authserver");
+        self.session_server = format!("{api_root}/sessionserver/session/minecraft");
+
+        Ok(())
+    }
+}
+
+/// The auth server refused the account, as opposed to a network error, an
+/// outage or a misconfiguration. Only accounts failing with this are cached as
+/// invalid.
+#[derive(Debug)]
+pub struct Rejected(String);
+
+impl std::fmt::Display for Rejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+/// the error for an auth request which failed with `status`. Client errors
+/// (apart from rate limiting) mean the account was refused.
+fn failed(status: StatusCode, message: String) -> anyhow::Error {
+    if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+        Rejected(message).into()
+    } else {
+        anyhow!(message)
+    }
+}
+
+/// The device-code prompts of Microsoft logins
+struct Prompts {
+    /// if prompts are printed. They are printed rather than logged so log
+    /// filters cannot hide them.
+    print: bool,
+    /// the prompts of the logins in progress by email
+    pending: BTreeMap<String, String>,
+}
+
+static PROMPTS: Mutex<Prompts> = Mutex::new(Prompts {
+    print: true,
+    pending: BTreeMap::new(),
+});
+
+/// Stop printing device-code prompts (i.e., because the dashboard would be
+/// drawn over them). They can still be read with [`device_code_prompts`].
+pub fn hide_device_code_prompts() {
+    PROMPTS.lock().unwrap().print = false;
+}
+
+/// the device-code prompts of the Microsoft logins in progress
+pub fn device_code_prompts() -> Vec<String> {
+    PROMPTS.lock().unwrap().pending.values().cloned().collect()
+}
+
+fn show_prompt(email: &str, prompt: String) {
+    let mut prompts = PROMPTS.lock().unwrap();
+    if prompts.print {
+        eprintln!("{prompt}");
+    }
+    prompts.pending.insert(email.to_string(), prompt);
+}
+
+/// An HTTP client which optionally goes through a SOCKS5 proxy
+fn http_client(proxy: Option<&Proxy>) -> anyhow::Result<reqwest::Client> {
+    let Some(proxy) = proxy else {
+        return Ok(default());
+    };
+
+    let address = proxy.address();
+    let user = &proxy.user;
+    let pass = &proxy.pass;
+    let full_address = format!("socks5://{address}");
+
+    let proxy = reqwest::Proxy::https(full_address)?.basic_auth(user, pass);
+
+    Ok(reqwest::Client::builder().proxy(proxy).build()?)
+}
+
 #[derive(Debug)]
 pub struct MojangClient {
     client: reqwest::Client,
+    endpoints: AuthEndpoints,
 }
 
 impl Default for MojangClient {
     fn default() -> Self {
-        Self { client: default() }
+        Self {
+            client: default(),
+            endpoints: default(),
+        }
     }
 }
 
@@ -25,16 +223,7 @@ impl TryFrom<&Proxy> for MojangClient {
     type Error = anyhow::Error;
 
     fn try_from(proxy: &Proxy) -> Result<Self, Self::Error> {
-        let address = proxy.address();
-        let user = &proxy.user;
-        let pass = &proxy.pass;
-        let full_address = format!("socks5://{address}");
-
-        let proxy = reqwest::Proxy::https(full_address)?.basic_auth(user, pass);
-
-        let client = reqwest::Client::builder().proxy(proxy).build()?;
-
-        Ok(Self { client })
+        Self::new(Some(proxy), default())
     }
 }
 
@@ -42,7 +231,7 @@ impl TryFrom<Option<&Proxy>> for MojangClient {
     type Error = anyhow::Error;
 
     fn try_from(value: Option<&Proxy>) -> Result<Self, Self::Error> {
-        value.map_or_else(|| Ok(Self::default()), Self::try_from)
+        Self::new(value, default())
     }
 }
 
@@ -85,7 +274,330 @@ pub struct AuthResponse {
     pub uuid: UUID,
 }
 
+/// The code a user has to enter at `verification_uri` to log in
+#[derive(Deserialize, Debug)]
+pub struct DeviceCode {
+    #[serde(rename = "device_code")]
+    pub code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_in: u64,
+    pub interval: u64,
+    pub message: Option<String>,
+}
+
+/// A token from the Microsoft login
+#[derive(Deserialize, Debug)]
+pub struct MicrosoftToken {
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+    error: String,
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XboxResponse {
+    token: String,
+    display_claims: XboxDisplayClaims,
+}
+
+#[derive(Deserialize)]
+struct XboxDisplayClaims {
+    xui: Vec<XboxUserInfo>,
+}
+
+#[derive(Deserialize)]
+struct XboxUserInfo {
+    uhs: String,
+}
+
+#[derive(Deserialize)]
+struct MinecraftToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct MinecraftProfile {
+    id: String,
+    name: String,
+}
+
+/// The result of the full Microsoft token chain
+#[derive(Debug)]
+pub struct MicrosoftAuth {
+    /// the Minecraft services access token (used to join servers)
+    pub access_token: String,
+    /// seconds until `access_token` expires
+    pub expires_in: u64,
+    /// the Microsoft refresh token used to redo the chain later
+    pub refresh_token: String,
+    pub username: String,
+    pub uuid: UUID,
+}
+
 impl MojangClient {
+    /// Create a client which optionally goes through a SOCKS5 proxy
+    pub fn new(proxy: Option<&Proxy>, endpoints: AuthEndpoints) -> anyhow::Result<Self> {
+        let client = http_client(proxy)?;
+        Ok(Self { client, endpoints })
+    }
+
+    pub fn client_id(&self) -> anyhow::Result<&str> {
+        self.endpoints
+            .client_id
+            .as_deref()
+            .context("a Microsoft client ID is required for Microsoft auth (--ms-client-id)")
+    }
+
+    /// Start a device-code login. The user has to visit
+    /// [`DeviceCode::verification_uri`] and enter [`DeviceCode::user_code`].
+    pub async fn device_code(&self) -> anyhow::Result<DeviceCode> {
+        let res = self
+            .client
+            .post(format!("{}/devicecode", self.endpoints.microsoft))
+            .form(&[
+                ("client_id", self.client_id()?),
+                ("scope", "XboxLive.signin offline_access"),
+            ])
+            .send()
+            .await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let info = res.text().await.unwrap_or_default();
+            bail!("could not request device code. Error code: {status}, info: {info}");
+        }
+
+        Ok(res.json().await?)
+    }
+
+    /// Poll the token endpoint until the user has finished the device-code
+    /// login or the code expires
+    pub async fn poll_device_code(&self, code: &DeviceCode) -> anyhow::Result<MicrosoftToken> {
+        let mut interval = code.interval.max(1);
+        let mut waited = 0;
+
+        while waited < code.expires_in {
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+            waited += interval;
+
+            let res = self
+                .client
+                .post(format!("{}/token", self.endpoints.microsoft))
+                .form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                    ("client_id", self.client_id()?),
+                    ("device_code", &code.code),
+                ])
+                .send()
+                .await?;
+
+            if res.status().is_success() {
+                return Ok(res.json().await?);
+            }
+
+            let OAuthError {
+                error,
+                error_description,
+            } = res.json().await?;
+
+            match error.as_str() {
+                "authorization_pending" => {}
+                "slow_down" => interval += 5,
+                "authorization_declined" | "bad_verification_code" => bail!(Rejected(format!(
+                    "device code login failed: {error} {}",
+                    error_description.unwrap_or_default()
+                ))),
+                _ => bail!(
+                    "device code login failed: {error} {}",
+                    error_description.unwrap_or_default()
+                ),
+            }
+        }
+
+        bail!("device code expired before the login was completed")
+    }
+
+    /// Obtain a new Microsoft token from a refresh token
+    pub async fn refresh_microsoft(&self, refresh_token: &str) -> anyhow::Result<MicrosoftToken> {
+        let res = self
+            .client
+            .post(format!("{}/token", self.endpoints.microsoft))
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("client_id", self.client_id()?),
+                ("refresh_token", refresh_token),
+                ("scope", "XboxLive.signin offline_access"),
+            ])
+            .send()
+            .await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let info = res.text().await.unwrap_or_default();
+            bail!("could not refresh Microsoft token. Error code: {status}, info: {info}");
+        }
+
+        Ok(res.json().await?)
+    }
+
+    async fn xbox_request(
+        &self,
+        url: String,
+        payload: serde_json::Value,
+    ) -> anyhow::Result<XboxResponse> {
+        let res = self
+            .client
+            .post(url)
+            .header("Accept", "application/json")
+            .json(&payload)
+            .send()
+            .await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let info = res.text().await.unwrap_or_default();
+            return Err(failed(
+                status,
+                format!("Xbox authentication failed. Error code: {status}, info: {info}"),
+            ));
+        }
+
+        Ok(res.json().await?)
+    }
+
+    /// Exchange a Microsoft access token for the Minecraft services token
+    /// going through Xbox Live and XSTS
+    pub async fn minecraft_from_microsoft(
+        &self,
+        token: MicrosoftToken,
+    ) -> anyhow::Result<MicrosoftAuth> {
+        let xbl = self
+            .xbox_request(
+                format!("{}/user/authenticate", self.endpoints.xbox_live),
+                json!({
+                    "Properties": {
+                        "AuthMethod": "RPS",
+                        "SiteName": "user.auth.xboxlive.com",
+                        "RpsTicket": format!("d={}", token.access_token),
+                    },
+                    "RelyingParty": "http://auth.xboxlive.com",
+                    "TokenType": "JWT",
+                }),
+            )
+            .await
+            .context("Xbox Live")?;
+
+        let xsts = self
+            .xbox_request(
+                format!("{}/xsts/authorize", self.endpoints.xsts),
+                json!({
+                    "Properties": {
+                        "SandboxId": "RETAIL",
+                        "UserTokens": [xbl.token],
+                    },
+                    "RelyingParty": "rp://api.minecraftservices.com/",
+                    "TokenType": "JWT",
+                }),
+            )
+            .await
+            .context("XSTS")?;
+
+        let uhs = &xsts
+            .display_claims
+            .xui
+            .first()
+            .context("XSTS response has no user hash")?
+            .uhs;
+
+        let res = self
+            .client
+            .post(format!(
+                "{}/authentication/login_with_xbox",
+                self.endpoints.minecraft_services
+            ))
+            .json(&json!({
+                "identityToken": format!("XBL3.0 x={uhs};{}", xsts.token),
+            }))
+            .send()
+            .await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let info = res.text().await.unwrap_or_default();
+            return Err(failed(
+                status,
+                format!("Minecraft services login failed. Error code: {status}, info: {info}"),
+            ));
+        }
+
+        let MinecraftToken {
+            access_token,
+            expires_in,
+        } = res.json().await?;
+
+        let res = self
+            .client
+            .get(format!(
+                "{}/minecraft/profile",
+                self.endpoints.minecraft_services
+            ))
+            .bearer_auth(&access_token)
+            .send()
+            .await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            return Err(failed(
+                status,
+                format!("account does not own Minecraft (profile lookup failed with {status})"),
+            ));
+        }
+
+        let MinecraftProfile { id, name } = res.json().await?;
+
+        Ok(MicrosoftAuth {
+            access_token,
+            expires_in,
+            refresh_token: token.refresh_token,
+            username: name,
+            uuid: UUID::from(&id),
+        })
+    }
+
+    /// Log in interactively with a device code and run the full token chain
+    pub async fn login_microsoft(&self, email: &str) -> anyhow::Result<MicrosoftAuth> {
+        let code = self.device_code().await?;
+
+        let prompt = match &code.message {
+            Some(message) => format!("[{email}] {message}"),
+            None => format!(
+                "[{email}] go to {} and enter the code {}",
+                code.verification_uri, code.user_code
+            ),
+        };
+        show_prompt(email, prompt);
+
+        let token = self.poll_device_code(&code).await;
+        PROMPTS.lock().unwrap().pending.remove(email);
+        self.minecraft_from_microsoft(token?).await
+    }
+
+    /// Redo the token chain using a stored Microsoft refresh token
+    pub async fn refresh_microsoft_chain(
+        &self,
+        refresh_token: &str,
+    ) -> anyhow::Result<MicrosoftAuth> {
+        let token = self.refresh_microsoft(refresh_token).await?;
+        self.minecraft_from_microsoft(token).await
+    }
+
     pub async fn authenticate(&self, email: &str, password: &str) -> anyhow::Result<AuthResponse> {
         let payload = json!({
             "agent": {
@@ -101,7 +613,8 @@ impl MojangClient {
 
         let res = self
             .client
-            .post("https://authserver.mojang.com/authenticate")
+            .post(format!("{}/authenticate", self.endpoints.auth_server))
+            .header("Content-Type", "application/json")
             .body(payload)
             .send()
             .await?;
@@ -109,7 +622,10 @@ impl MojangClient {
         let status = res.status();
         if status != 200 {
             let info = res.text().await.unwrap_or_default();
-            anyhow::bail!("Invalid credentials! Error code: {status}, info: {info}");
+            return Err(failed(
+                status,
+                format!("Invalid credentials! Error code: {status}, info: {info}"),
+            ));
         }
 
         let auth: RawAuthResponse = res.json().await?;
@@ -136,12 +652,21 @@ impl MojangClient {
 
         let res = self
             .client
-            .post("https://authserver.mojang.com/refresh")
+            .post(format!("{}/refresh", self.endpoints.auth_server))
+            .header("Content-Type", "application/json")
             .body(payload)
             .send()
             .await?;
 
-        let _status = res.status();
+        let status = res.status();
+        if status != 200 {
+            let info = res.text().await.unwrap_or_default();
+            return Err(failed(
+                status,
+                format!("could not refresh. Error code: {status}, info: {info}"),
+            ));
+        }
+
         let auth: RawAuthResponse = res.json().await?;
         let auth = AuthResponse {
             access_token: auth.access_token,
@@ -161,7 +686,8 @@ impl MojangClient {
 
         let res = self
             .client
-            .post("https://authserver.mojang.com/validate")
+            .post(format!("{}/validate", self.endpoints.auth_server))
+            .header("Content-Type", "application/json")
             .body(payload)
             .send()
             .await?;
@@ -188,7 +714,8 @@ impl MojangClient {
 
         let res = self
             .client
-            .post("https://sessionserver.mojang.com/session/minecraft/join")
+            .post(format!("{}/join", self.endpoints.session_server))
+            .header("Content-Type", "application/json")
             .body(payload)
             .send()
             .await?;
@@ -205,9 +732,14 @@ impl MojangClient {
 
 #[cfg(test)]
 mod tests {
+    use reqwest::StatusCode;
     use sha1::{Digest, Sha1};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
 
-    use crate::bootstrap::mojang::hexdigest;
+    use crate::bootstrap::mojang::{failed, hexdigest, AuthEndpoints, MojangClient, Rejected};
 
     fn sha1(input: &[u8]) -> String {
         let mut sha1 = Sha1::new();
@@ -221,4 +753,181 @@ mod tests {
         assert_eq!(sha1(b"simon"), "88e16a1019277b15d58faf0541e11910eb756f6");
         assert_eq!(sha1(b"Notch"), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
     }
+
+    /// A stand-in HTTP server. `respond` maps a raw request to extra response
+    /// headers and a JSON body.
+    async fn stand_in(listener: TcpListener, respond: fn(&str) -> (String, String)) {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+
+            // read the headers and then as much body as `Content-Length` says
+            let request = loop {
+                let mut chunk = [0; 1024];
+                let len = stream.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..len]);
+
+                let request = String::from_utf8_lossy(&buf).to_string();
+                let Some((head, body)) = request.split_once("\r\n\r\n") else {
+                    continue;
+                };
+                let content_length = head
+                    .lines()
+                    .find_map(|line| {
+                        line.to_lowercase()
+                            .strip_prefix("content-length: ")
+                            .map(str::to_string)
+                    })
+                    .map_or(0, |len| len.trim().parse().unwrap());
+
+                if body.len() >= content_length || len == 0 {
+                    break request;
+                }
+            };
+
+            let (headers, body) = respond(&request);
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{headers}Connection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    /// run `f` with the base URL of a [`stand_in`] server
+    fn with_stand_in<F: std::future::Future<Output = ()>>(
+        respond: fn(&str) -> (String, String),
+        f: impl FnOnce(String) -> F,
+    ) {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let base = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(stand_in(listener, respond));
+            f(base).await;
+        });
+    }
+
+    fn microsoft_chain(request: &str) -> (String, String) {
+        let path = request.split(' ').nth(1).unwrap_or_default();
+
+        let body = match path {
+            "/ms/token" => {
+                assert!(request.contains("grant_type=refresh_token"));
+                r#"{"access_token":"ms","refresh_token":"new-refresh","expires_in":3600}"#
+            }
+            "/xbl/user/authenticate" => {
+                assert!(request.contains("d=ms"));
+                r#"{"Token":"xbl","DisplayClaims":{"xui":[{"uhs":"hash"}]}}"#
+            }
+            "/xsts/xsts/authorize" => {
+                assert!(request.contains("xbl"));
+                r#"{"Token":"xsts","DisplayClaims":{"xui":[{"uhs":"hash"}]}}"#
+            }
+            "/mc/authentication/login_with_xbox" => {
+                assert!(request.contains("XBL3.0 x=hash;xsts"));
+                r#"{"access_token":"minecraft","expires_in":86400}"#
+            }
+            "/mc/minecraft/profile" => {
+                assert!(request.contains("Bearer minecraft"));
+                r#"{"id":"069a79f444e94726a5befca90e38aaf5","name":"Notch"}"#
+            }
+            other => panic!("unexpected path {other}"),
+        };
+
+        (String::new(), body.to_string())
+    }
+
+    #[test]
+    fn test_microsoft_chain() {
+        with_stand_in(microsoft_chain, |base| async move {
+            let endpoints = AuthEndpoints {
+                microsoft: format!("{base}/ms"),
+                xbox_live: format!("{base}/xbl"),
+                xsts: format!("{base}/xsts"),
+                minecraft_services: format!("{base}/mc"),
+                client_id: Some("client".to_string()),
+                ..AuthEndpoints::default()
+            };
+
+            let client = MojangClient::new(None, endpoints).unwrap();
+            let auth = client.refresh_microsoft_chain("refresh").await.unwrap();
+
+            assert_eq!(auth.access_token, "minecraft");
+            assert_eq!(auth.refresh_token, "new-refresh");
+            assert_eq!(auth.username, "Notch");
+            assert_eq!(auth.expires_in, 86400);
+        });
+    }
+
+    fn authlib_injector(request: &str) -> (String, String) {
+        let path = request.split(' ').nth(1).unwrap_or_default();
+
+        match path {
+            // the root redirects to the real API location
+            "/" => (
+                "X-Authlib-Injector-API-Location: /api/yggdrasil/\r\n".to_string(),
+                "{}".to_string(),
+            ),
+            "/api/yggdrasil" => (
+                String::new(),
+                r#"{"meta":{"serverName":"test"},"skinDomains":[],"signaturePublickey":"key"}"#
+                    .to_string(),
+            ),
+            "/api/yggdrasil/authserver/authenticate" => {
+                assert!(request.contains("application/json"));
+                (
+                    String::new(),
+                    r#"{"accessToken":"access","clientToken":"client","selectedProfile":{"id":"069a79f444e94726a5befca90e38aaf5","name":"Notch"}}"#
+                        .to_string(),
+                )
+            }
+            other => panic!("unexpected path {other}"),
+        }
+    }
+
+    #[test]
+    fn test_authlib_injector() {
+        with_stand_in(authlib_injector, |base| async move {
+            let mut endpoints = AuthEndpoints::default();
+            endpoints.authlib_injector(&base, None).await.unwrap();
+
+            assert_eq!(
+                endpoints.session_server,
+                format!("{base}/api/yggdrasil/sessionserver/session/minecraft")
+            );
+
+            let client = MojangClient::new(None, endpoints).unwrap();
+            let auth = client.authenticate("email", "password").await.unwrap();
+
+            assert_eq!(auth.access_token, "access");
+            assert_eq!(auth.username, "Notch");
+        });
+    }
+
+    #[test]
+    fn test_authlib_injector_requires_metadata() {
+        with_stand_in(
+            |_| (String::new(), r#"{"hello":"world"}"#.to_string()),
+            |base| async move {
+                let mut endpoints = AuthEndpoints::default();
+                assert!(endpoints.authlib_injector(&base, None).await.is_err());
+            },
+        );
+    }
+
+    #[test]
+    fn test_rejected() {
+        let rejected = |status| failed(status, String::new()).is::<Rejected>();
+
+        assert!(rejected(StatusCode::UNAUTHORIZED));
+        assert!(rejected(StatusCode::NOT_FOUND));
+        assert!(!rejected(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!rejected(StatusCode::SERVICE_UNAVAILABLE));
+    }
 }
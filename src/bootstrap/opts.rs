@@ -1,17 +1,42 @@
 //! Module to interact with cargo options
 
-use clap::Parser;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use anyhow::{bail, Context};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    bootstrap::{
+        config::{FileConfig, ProxyPolicy},
+        mojang::{AuthEndpoints, AuthMode},
+        storage::{Auth, UserStatus},
+        Proxy,
+    },
+    client::commands::{Tls, WsOptions},
+};
 
 /// Options parsed from CLI
 #[derive(Parser, Debug)]
-#[command(about, author, version)]
+#[command(about, author, version, args_conflicts_with_subcommands = true)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct CliOptions {
-    /// The host which the bot will connect to
-    pub host: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// The host which the bot will connect to. Required unless given in the
+    /// config file or a subcommand is given
+    pub host: Option<String>,
 
-    /// The number of bots that will be launched
-    #[clap(short, long, default_value = "1")]
-    pub count: usize,
+    /// The TOML config file. Launcher options in the file are overridden by
+    /// the flags given here. `swarmbot.toml` is used if it exists and no
+    /// file is given
+    #[clap(long)]
+    pub config: Option<String>,
+
+    /// The number of bots that will be launched which are not in a group of
+    /// the config file [default: 1 or 0 if the config file has groups]
+    #[clap(short, long)]
+    pub count: Option<usize>,
 
     /// If a proxy will be used to log the bots in and join the server.
     /// This is recommended as Mojang API has rate limits as do most
@@ -23,19 +48,31 @@ pub struct CliOptions {
     #[clap(short)]
     pub proxy: bool,
 
-    /// The port of the server which is being connected to
-    #[clap(long, default_value = "25565")]
-    pub port: u16,
+    /// How proxies are assigned to bots. `-p` is the same as `cycle`
+    /// [default: none]
+    #[clap(long, value_enum, conflicts_with = "proxy")]
+    pub proxy_policy: Option<ProxyPolicy>,
 
-    /// The port of the web socket that is used to communicate bot commands
-    /// to. This is used to interface with the `SwarmBot` mod, although it
-    /// can be used for anything.
-    #[clap(long, default_value = "8080")]
-    pub ws_port: u16,
+    /// The port of the server which is being connected to [default: 25565]
+    #[clap(long)]
+    pub port: Option<u16>,
+
+    #[command(flatten)]
+    pub ws: WsArgs,
 
-    /// The delay for launching the bots
-    #[clap(short, long, default_value = "500")]
-    pub delay_ms: u64,
+    /// The port of a local HTTP server which serves Prometheus metrics at
+    /// `/metrics`. Metrics are not served if not given
+    #[clap(long)]
+    pub metrics_port: Option<u16>,
+
+    /// Show an interactive dashboard of all bots instead of logging to the
+    /// terminal. Logs are written to `swarmbot.log` instead
+    #[clap(long)]
+    pub tui: bool,
+
+    /// The delay for launching the bots [default: 500]
+    #[clap(short, long)]
+    pub delay_ms: Option<u64>,
 
     /// The file that the users will be read from. This is a CSV file of
     /// the form of
@@ -46,8 +83,9 @@ pub struct CliOptions {
     ///
     /// This is purely to specify credentials.
     /// If you are using the --offline flag, then this is not needed.
-    #[clap(long, default_value = "users.csv")]
-    pub users_file: String,
+    /// [default: users.csv]
+    #[clap(long)]
+    pub users_file: Option<String>,
 
     /// The file that the proxies will be read from. This is a CSV file of
     /// the form of
@@ -55,19 +93,35 @@ pub struct CliOptions {
     /// 111.111.11.11:3333:username:password
     ///
     /// Note, instead of commas as a delimiter, colons are used
-    #[clap(long, default_value = "proxies.csv")]
-    pub proxies_file: String,
+    /// [default: proxies.csv]
+    #[clap(long)]
+    pub proxies_file: Option<String>,
 
     /// The version number that the bots will be launched on. To see a list
     /// of versions see
     ///
     /// <https://minecraft.fandom.com/wiki/Protocol_version>
-    #[clap(short, long, default_value = "340")]
-    pub ver: usize,
+    /// [default: 340]
+    #[clap(short, long)]
+    pub ver: Option<usize>,
+
+    /// if we are launching in online mode. `--online=false` turns off
+    /// `online = true` from the config file [default: false]
+    #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub online: Option<bool>,
+
+    #[command(flatten)]
+    pub auth: AuthOptions,
+
+    #[command(flatten)]
+    pub log: LogOptions,
 
-    /// if we are launching in offline mode
+    /// Encrypt the account cache (`cache.db`) with a passphrase. The
+    /// passphrase is read from `SWARMBOT_CACHE_PASSPHRASE` or prompted for.
+    /// An existing plain text cache is migrated. Once encrypted, the cache
+    /// always needs the passphrase and is never written as plain text again
     #[clap(long)]
-    pub online: bool,
+    pub encrypt_cache: bool,
 }
 
 impl CliOptions {
@@ -75,3 +129,330 @@ impl CliOptions {
         Self::parse()
     }
 }
+
+/// The format logs are written in
+#[derive(ValueEnum, Copy, Clone, Debug, Default)]
+pub enum LogFormat {
+    /// human readable lines
+    #[default]
+    Text,
+    /// a JSON object per line including the spans (such as the bot) the event
+    /// happened in
+    Json,
+}
+
+/// Which logs are shown and how
+#[derive(Args, Debug, Default)]
+pub struct LogOptions {
+    /// Which logs are shown as `tracing` filter directives, for instance
+    /// `info,swarm_bot::client::pathfind=debug`. Defaults to `RUST_LOG` or
+    /// `info`. Spans can be filtered by field, for instance
+    /// `[bot{username=Bot0001}]=trace`
+    #[clap(long = "log")]
+    pub filter: Option<String>,
+
+    /// Only show logs of the bots with these usernames. Logs of other bots
+    /// and logs outside of bots are only shown if they are warnings or errors
+    #[clap(long = "log-bot")]
+    pub bots: Vec<String>,
+
+    /// The format logs are written to stderr in
+    #[clap(long = "log-format", value_enum, default_value = "text")]
+    pub format: LogFormat,
+}
+
+/// How the web socket is served
+#[derive(Args, Debug)]
+pub struct WsArgs {
+    /// The port of the web socket that is used to communicate bot commands
+    /// to. This is used to interface with the `SwarmBot` mod, although it
+    /// can be used for anything. [default: 8080]
+    #[clap(id = "ws_port", long = "ws-port")]
+    pub port: Option<u16>,
+
+    /// The address the web socket listens on. Addresses other than loopback
+    /// need a token [default: 127.0.0.1]
+    #[clap(id = "ws_bind", long = "ws-bind")]
+    pub bind: Option<IpAddr>,
+
+    /// A token which lets web socket clients run any command. Clients send it
+    /// as `Authorization: Bearer {token}`. Clients need a token if any
+    /// control or read tokens are given
+    #[clap(
+        id = "ws_tokens",
+        long = "ws-token",
+        env = "SWARMBOT_WS_TOKENS",
+        value_delimiter = ','
+    )]
+    pub tokens: Vec<String>,
+
+    /// A token which lets web socket clients only query the swarm and
+    /// subscribe to events
+    #[clap(
+        id = "ws_read_tokens",
+        long = "ws-read-token",
+        env = "SWARMBOT_WS_READ_TOKENS",
+        value_delimiter = ','
+    )]
+    pub read_tokens: Vec<String>,
+
+    /// The certificate chain (PEM) to serve the web socket over TLS
+    /// (`wss://`) with. Needs `--ws-key`
+    #[clap(id = "ws_cert", long = "ws-cert")]
+    pub cert: Option<String>,
+
+    /// The private key (PKCS #8 PEM) of `--ws-cert`
+    #[clap(id = "ws_key", long = "ws-key")]
+    pub key: Option<String>,
+}
+
+impl WsArgs {
+    /// Combine the flags with the config file. Tokens of both are used.
+    pub fn resolve(self, file: &FileConfig) -> anyhow::Result<WsOptions> {
+        let Self {
+            port,
+            bind,
+            tokens,
+            read_tokens,
+            cert,
+            key,
+        } = self;
+
+        let port = port.or(file.ws_port).unwrap_or(8080);
+        let bind = bind
+            .or(file.ws_bind)
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        let tls = match (
+            cert.or_else(|| file.ws_cert.clone()),
+            key.or_else(|| file.ws_key.clone()),
+        ) {
+            (Some(cert), Some(key)) => Some(Tls {
+                cert: cert.into(),
+                key: key.into(),
+            }),
+            (None, None) => None,
+            _ => bail!("the web socket needs both a certificate and a key for TLS"),
+        };
+
+        Ok(WsOptions {
+            address: SocketAddr::new(bind, port),
+            control_tokens: tokens.into_iter().chain(file.ws_tokens.clone()).collect(),
+            read_tokens: read_tokens
+                .into_iter()
+                .chain(file.ws_read_tokens.clone())
+                .collect(),
+            tls,
+        })
+    }
+}
+
+/// How users are authenticated
+#[derive(Args, Debug)]
+pub struct AuthOptions {
+    /// How online users are authenticated. Microsoft accounts log in with a
+    /// device code which is printed to the terminal the first time
+    #[clap(long = "auth", value_enum, default_value = "mojang")]
+    pub mode: AuthMode,
+
+    /// The Azure application client ID used for Microsoft auth
+    #[clap(long)]
+    pub ms_client_id: Option<String>,
+
+    /// The base URL of the Microsoft login endpoints
+    #[clap(
+        long,
+        default_value = "https://login.microsoftonline.com/consumers/oauth2/v2.0"
+    )]
+    pub ms_auth_url: String,
+
+    /// The base URL of Xbox Live user authentication
+    #[clap(long, default_value = "https://user.auth.xboxlive.com")]
+    pub xbl_auth_url: String,
+
+    /// The base URL of the Xbox secure token service
+    #[clap(long, default_value = "https://xsts.auth.xboxlive.com")]
+    pub xsts_auth_url: String,
+
+    /// The base URL of the Minecraft services API
+    #[clap(long, default_value = "https://api.minecraftservices.com")]
+    pub mc_services_url: String,
+
+    /// The base URL of the Yggdrasil auth server used by `--auth mojang`
+    #[clap(long, default_value = "https://authserver.mojang.com")]
+    pub auth_server: String,
+
+    /// The base URL of the session server used to join online-mode servers
+    #[clap(
+        long,
+        default_value = "https://sessionserver.mojang.com/session/minecraft"
+    )]
+    pub session_server: String,
+
+    /// The API root of an authlib-injector compatible Yggdrasil server, for
+    /// instance `https://example.com/api/yggdrasil`. Its metadata is checked
+    /// on launch and it overrides `--auth-server` and `--session-server`
+    #[clap(long)]
+    pub yggdrasil_api: Option<String>,
+}
+
+impl AuthOptions {
+    /// Resolve the options into an [`Auth`]. This checks the metadata of an
+    /// authlib-injector server (through `proxy`) if one was given.
+    pub async fn resolve(self, proxy: Option<&Proxy>) -> anyhow::Result<Auth> {
+        let Self {
+            mode,
+            ms_client_id,
+            ms_auth_url,
+            xbl_auth_url,
+            xsts_auth_url,
+            mc_services_url,
+            auth_server,
+            session_server,
+            yggdrasil_api,
+        } = self;
+
+        let mut endpoints = AuthEndpoints {
+            microsoft: ms_auth_url,
+            xbox_live: xbl_auth_url,
+            xsts: xsts_auth_url,
+            minecraft_services: mc_services_url,
+            client_id: ms_client_id,
+            auth_server,
+            session_server,
+        };
+
+        // custom auth servers (authlib-injector)
+        if let Some(api_root) = yggdrasil_api {
+            endpoints
+                .authlib_injector(&api_root, proxy)
+                .await
+                .with_context(|| format!("could not use Yggdrasil server {api_root}"))?;
+        }
+
+        Ok(Auth { mode, endpoints })
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Manage the account cache (`cache.db`)
+    Cache(CacheOptions),
+}
+
+#[derive(Args, Debug)]
+pub struct CacheOptions {
+    #[command(subcommand)]
+    pub command: CacheCommand,
+
+    /// Encrypt the account cache. See the launch option of the same name
+    #[clap(long)]
+    pub encrypt_cache: bool,
+}
+
+/// The format users are imported and exported in
+#[derive(ValueEnum, Copy, Clone, Debug)]
+pub enum CacheFormat {
+    Csv,
+    Json,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+pub enum Validity {
+    Valid,
+    Invalid,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// List cached users with their status and last check time
+    List,
+
+    /// Authenticate users again regardless of when they were last checked
+    Validate {
+        /// The emails of the users. All users are validated if none are given
+        emails: Vec<String>,
+
+        #[command(flatten)]
+        auth: Box<AuthOptions>,
+
+        /// If proxies from `--proxies-file` are used
+        #[clap(short)]
+        proxy: bool,
+
+        /// The file that the proxies will be read from
+        #[clap(long, default_value = "proxies.csv")]
+        proxies_file: String,
+    },
+
+    /// Mark users as valid or invalid. A user marked valid is authenticated
+    /// again the next time it is used
+    Mark {
+        #[clap(value_enum)]
+        status: Validity,
+
+        /// The emails of the users
+        #[clap(required = true)]
+        emails: Vec<String>,
+    },
+
+    /// Remove users from the cache
+    Remove {
+        /// The emails of the users
+        #[clap(required = true)]
+        emails: Vec<String>,
+    },
+
+    /// Import users, replacing cached users with the same email
+    Import {
+        /// The file to import from
+        file: String,
+
+        /// The format of the file. Inferred from the extension if not given
+        #[clap(long, value_enum)]
+        format: Option<CacheFormat>,
+    },
+
+    /// Export users. This writes passwords and tokens as plain text
+    Export {
+        /// The file to export to. Printed if not given
+        file: Option<String>,
+
+        /// The format of the file. Inferred from the extension if not given
+        #[clap(long, value_enum)]
+        format: Option<CacheFormat>,
+
+        /// Only export users with this status
+        #[clap(long, value_enum)]
+        status: Option<UserStatus>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, Parser};
+
+    use crate::bootstrap::opts::CliOptions;
+
+    #[test]
+    fn test_cli() {
+        CliOptions::command().debug_assert();
+    }
+
+    #[test]
+    fn test_online() {
+        let online = |args: &[&str]| {
+            let args = std::iter::once(&"swarm-bot").chain(args);
+            CliOptions::try_parse_from(args).unwrap().online
+        };
+
+        assert_eq!(online(&[]), None);
+        assert_eq!(online(&["--online"]), Some(true));
+        assert_eq!(online(&["--online=false"]), Some(false));
+
+        // the host is not taken as the value
+        let options = CliOptions::try_parse_from(["swarm-bot", "--online", "localhost"]).unwrap();
+        assert_eq!(options.online, Some(true));
+        assert_eq!(options.host.as_deref(), Some("localhost"));
+    }
+}
@@ -1,25 +1,36 @@
 //! Utils to store information
 
 use std::{
-    collections::HashMap,
-    convert::TryFrom,
+    collections::{HashMap, HashSet},
     fs::{File, OpenOptions},
     io::{Read, Write},
     path::PathBuf,
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
 use swarm_bot_packets::types::UUID;
 use tokio::sync::mpsc::Receiver;
 use tokio_stream::Stream;
+use tracing::{debug, error, info, warn};
 
 use crate::{
     bootstrap,
-    bootstrap::{mojang::MojangClient, CSVUser, Proxy},
+    bootstrap::{
+        cache_key,
+        cache_key::CacheKey,
+        config::{BotGroup, ProxyPolicy},
+        mojang::{AuthEndpoints, AuthMode, MicrosoftAuth, MojangClient, Rejected},
+        Address, CSVUser, Proxy,
+    },
+    client::state::local::config::BotConfig,
 };
 
+/// The file the account cache is stored in
+pub const CACHE_FILE: &str = "cache.db";
+
 #[derive(Encode, Decode, Debug)]
 struct Root {
     users: Vec<User>,
@@ -29,6 +40,9 @@ struct Root {
 enum User {
     Valid(OnlineUser),
     Invalid(InvalidUser),
+    /// a user authenticated through the Microsoft token chain. This is the
+    /// last variant so caches written before it existed still decode.
+    Microsoft(MicrosoftUser),
 }
 
 #[derive(Encode, Decode, Debug)]
@@ -53,6 +67,49 @@ pub struct OnlineUser {
     pub client_id: String,
 }
 
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct MicrosoftUser {
+    pub email: String,
+    pub username: String,
+    pub uuid: String,
+    /// the Minecraft services access token
+    pub access_id: String,
+    /// the Microsoft refresh token
+    pub refresh_token: String,
+    /// unix time (seconds) at which `access_id` expires
+    pub expires_at: u64,
+    pub last_checked: u64,
+}
+
+impl MicrosoftUser {
+    fn new(email: &str, auth: MicrosoftAuth) -> Self {
+        let now = time();
+        Self {
+            email: email.to_string(),
+            username: auth.username,
+            uuid: auth.uuid.to_string(),
+            access_id: auth.access_token,
+            refresh_token: auth.refresh_token,
+            expires_at: now + auth.expires_in,
+            last_checked: now,
+        }
+    }
+
+    /// The [`OnlineUser`] used to join servers. Microsoft accounts have no
+    /// password or client token.
+    fn online(&self) -> OnlineUser {
+        OnlineUser {
+            email: self.email.clone(),
+            username: self.username.clone(),
+            password: String::new(),
+            last_checked: self.last_checked,
+            uuid: self.uuid.clone(),
+            access_id: self.access_id.clone(),
+            client_id: String::new(),
+        }
+    }
+}
+
 impl OnlineUser {
     pub fn uuid(&self) -> UUID {
         UUID::from(&self.uuid)
@@ -62,9 +119,110 @@ impl OnlineUser {
 impl User {
     const fn email(&self) -> &String {
         match self {
-            Self::Invalid(InvalidUser { email, .. }) | Self::Valid(OnlineUser { email, .. }) => {
-                email
-            }
+            Self::Invalid(InvalidUser { email, .. })
+            | Self::Valid(OnlineUser { email, .. })
+            | Self::Microsoft(MicrosoftUser { email, .. }) => email,
+        }
+    }
+}
+
+/// The status of a cached user
+#[derive(Serialize, Deserialize, clap::ValueEnum, Copy, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+    /// a valid Mojang (Yggdrasil) account
+    Valid,
+    /// a valid Microsoft account
+    Microsoft,
+    /// an account that could not be authenticated
+    Invalid,
+}
+
+/// A flat view of a cached user. This is what is listed, exported and
+/// imported.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CachedUser {
+    pub email: String,
+    pub status: UserStatus,
+    pub password: String,
+    pub username: String,
+    pub uuid: String,
+    pub access_id: String,
+    pub client_id: String,
+    pub refresh_token: String,
+    pub expires_at: u64,
+    pub last_checked: u64,
+}
+
+impl From<&User> for CachedUser {
+    fn from(user: &User) -> Self {
+        let empty = Self {
+            email: user.email().clone(),
+            status: UserStatus::Invalid,
+            password: String::new(),
+            username: String::new(),
+            uuid: String::new(),
+            access_id: String::new(),
+            client_id: String::new(),
+            refresh_token: String::new(),
+            expires_at: 0,
+            last_checked: 0,
+        };
+
+        match user {
+            User::Valid(user) => Self {
+                status: UserStatus::Valid,
+                password: user.password.clone(),
+                username: user.username.clone(),
+                uuid: user.uuid.clone(),
+                access_id: user.access_id.clone(),
+                client_id: user.client_id.clone(),
+                last_checked: user.last_checked,
+                ..empty
+            },
+            User::Microsoft(user) => Self {
+                status: UserStatus::Microsoft,
+                username: user.username.clone(),
+                uuid: user.uuid.clone(),
+                access_id: user.access_id.clone(),
+                refresh_token: user.refresh_token.clone(),
+                expires_at: user.expires_at,
+                last_checked: user.last_checked,
+                ..empty
+            },
+            User::Invalid(user) => Self {
+                password: user.password.clone(),
+                ..empty
+            },
+        }
+    }
+}
+
+impl From<CachedUser> for User {
+    fn from(user: CachedUser) -> Self {
+        match user.status {
+            UserStatus::Valid => Self::Valid(OnlineUser {
+                email: user.email,
+                username: user.username,
+                password: user.password,
+                last_checked: user.last_checked,
+                uuid: user.uuid,
+                access_id: user.access_id,
+                client_id: user.client_id,
+            }),
+            UserStatus::Microsoft => Self::Microsoft(MicrosoftUser {
+                email: user.email,
+                username: user.username,
+                uuid: user.uuid,
+                access_id: user.access_id,
+                refresh_token: user.refresh_token,
+                expires_at: user.expires_at,
+                last_checked: user.last_checked,
+            }),
+            UserStatus::Invalid => Self::Invalid(InvalidUser {
+                email: user.email,
+                password: user.password,
+            }),
         }
     }
 }
@@ -72,6 +230,9 @@ impl User {
 pub struct UserCache {
     file_path: PathBuf,
     cache: HashMap<String, User>,
+    /// the key the cache is encrypted with. If [`None`] the cache is stored as
+    /// plain text.
+    key: Option<CacheKey>,
 }
 
 #[derive(Debug)]
@@ -98,6 +259,13 @@ impl BotData {
     }
 }
 
+/// How users are authenticated and against which servers
+#[derive(Clone, Debug, Default)]
+pub struct Auth {
+    pub mode: AuthMode,
+    pub endpoints: AuthEndpoints,
+}
+
 /// A bot data holds the "Mojang" object used in cache to verify that the user
 /// is valid along with data about what the proxy address is and the valid user
 /// information
@@ -105,92 +273,332 @@ impl BotData {
 pub struct BotConnectionData {
     pub bot: BotData,
     pub proxy: Option<Proxy>,
+    pub config: BotConfig,
+
+    /// the server the bot joins
+    pub server: Address,
 }
 
 impl BotConnectionData {
-    pub fn offline_random() -> impl Stream<Item = Self> {
-        let mut idx = 0;
-        futures::stream::repeat_with(move || {
+    /// Offline bots for each group. Bots are numbered across groups.
+    pub fn offline_random(groups: Vec<BotGroup>) -> impl Stream<Item = Self> {
+        let bots = groups
+            .into_iter()
+            .flat_map(|group| std::iter::repeat((group.server, group.config)).take(group.count));
+
+        futures::stream::iter(bots.enumerate().map(|(idx, (server, config))| {
             let username = format!("Bot{idx:0>4}");
             let res = Self {
                 bot: BotData::Offline {
                     user: OfflineUser { username },
                 },
                 proxy: None,
+                config,
+                server,
             };
 
-            idx += 1;
-
-            println!("generated offline {res:?}");
+            debug!("generated offline {res:?}");
 
             res
-        })
+        }))
     }
+
     pub fn load_from_files(
-        users_file: &str,
-        proxies_file: &str,
-        proxy: bool,
-        count: usize,
+        groups: Vec<BotGroup>,
+        auth: Auth,
+        encrypt_cache: bool,
     ) -> anyhow::Result<Receiver<Self>> {
-        let csv_file = File::open(users_file).with_context(|| {
-            format!(
-                "could not open users file {users_file}. \
-            This is specified by the --users-file flag. You can avoid specifying this file by using the --offline flag to use offline mode."
-            )
-        })?;
-
-        let csv_users =
-            bootstrap::csv::read_users(csv_file).context("could not open users file")?;
-
-        let proxies = if proxy {
-            let proxies_file = File::open(proxies_file)
-                .with_context(|| format!("could not open proxies file {proxies_file}"))?;
-            bootstrap::csv::read_proxies(proxies_file)
-                .context("could not open proxies file")?
-                .into_iter()
-                .map(Some)
-                .collect()
-        } else {
-            vec![None]
-        };
+        let groups = groups
+            .into_iter()
+            .filter(|group| group.count > 0)
+            .map(|group| {
+                let users_file = &group.users_file;
+                let csv_file = File::open(users_file).with_context(|| {
+                    format!(
+                        "could not open users file {users_file}. \
+                    This is specified by the --users-file flag. You can avoid specifying this file by using the --offline flag to use offline mode."
+                    )
+                })?;
+
+                let users =
+                    bootstrap::csv::read_users(csv_file).context("could not open users file")?;
+
+                Ok(GroupUsers {
+                    server: group.server,
+                    count: group.count,
+                    users,
+                    proxies: load_proxies(&group.proxies_file, group.proxy)?,
+                    config: group.config,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let cache = UserCache::load(CACHE_FILE.into(), encrypt_cache)?;
+
+        Ok(cache.obtain_users(groups, auth))
+    }
+}
 
-        let cache = UserCache::load("cache.db".into())?;
+/// The users and proxies of a [`BotGroup`]
+pub struct GroupUsers {
+    pub server: Address,
+    pub count: usize,
+    pub users: Vec<CSVUser>,
+    pub proxies: Box<dyn Iterator<Item = Option<Proxy>>>,
+    pub config: BotConfig,
+}
 
-        Ok(cache.obtain_users(count, csv_users, proxies))
+/// The proxies assigned to successive bots according to `policy`
+pub fn load_proxies(
+    proxies_file: &str,
+    policy: ProxyPolicy,
+) -> anyhow::Result<Box<dyn Iterator<Item = Option<Proxy>>>> {
+    if policy == ProxyPolicy::None {
+        return Ok(policy.assign(Vec::new()));
     }
+
+    let proxies_file = File::open(proxies_file)
+        .with_context(|| format!("could not open proxies file {proxies_file}"))?;
+
+    let proxies =
+        bootstrap::csv::read_proxies(proxies_file).context("could not open proxies file")?;
+
+    Ok(policy.assign(proxies))
 }
 
-fn time() -> u64 {
+pub fn time() -> u64 {
     let start = SystemTime::now();
     let since_the_epoch = start.duration_since(UNIX_EPOCH).unwrap_or_default();
     since_the_epoch.as_secs()
 }
 
+/// get new tokens for `valid` after they failed validation. They are refreshed
+/// if possible and authenticated again otherwise.
+async fn renew(mojang: &MojangClient, valid: &mut OnlineUser) -> anyhow::Result<()> {
+    let auth = match mojang.refresh(&valid.access_id, &valid.client_id).await {
+        Ok(auth) => auth,
+        Err(e) if !e.is::<Rejected>() => return Err(e),
+
+        // we could not refresh -> try to authenticate
+        Err(e) => {
+            warn!("failed refreshing {} .. {}", valid.email, e);
+            mojang.authenticate(&valid.email, &valid.password).await?
+        }
+    };
+
+    valid.access_id = auth.access_token;
+    valid.username = auth.username;
+    valid.uuid = auth.uuid.to_string();
+    valid.client_id = auth.client_token;
+    valid.last_checked = time();
+    Ok(())
+}
+
 impl UserCache {
-    pub fn load(file_path: PathBuf) -> anyhow::Result<Self> {
+    /// Load the cache at `file_path`. An encrypted cache asks for its
+    /// passphrase. If `encrypt` is set, an unencrypted cache is encrypted the
+    /// next time it is saved.
+    pub fn load(file_path: PathBuf, encrypt: bool) -> anyhow::Result<Self> {
         let exists = file_path
             .try_exists()
             .with_context(|| format!("cannot load user from: {file_path:?} (DNE)"))?;
-        let res = if exists {
+
+        let bytes = if exists {
             let file = File::open(&file_path).context("could not load file")?;
             let bytes: Result<Vec<_>, _> = file.bytes().collect();
-            let bytes = bytes.context("could not load bytes")?;
+            bytes.context("could not load bytes")?
+        } else {
+            Vec::new()
+        };
+
+        let (key, bytes) = if cache_key::is_encrypted(&bytes) {
+            let passphrase = cache_key::passphrase(false)?;
+            let key = CacheKey::for_file(&passphrase, &bytes)?;
+            let bytes = key.decrypt(&bytes)?;
+            (Some(key), bytes)
+        } else if encrypt {
+            if exists {
+                warn!("{file_path:?} is not encrypted. It will be encrypted when saved");
+            }
+            let passphrase = cache_key::passphrase(true)?;
+            (Some(CacheKey::new(&passphrase)?), bytes)
+        } else {
+            (None, bytes)
+        };
+
+        let cache = if exists {
             let config = bincode::config::standard();
             let (Root { users }, _) = bincode::decode_from_slice(&bytes, config)
                 .context("could not decode from slice")?;
 
-            let cache: HashMap<_, _> = users
+            users
                 .into_iter()
                 .map(|user| (user.email().clone(), user))
-                .collect();
-            Self { file_path, cache }
+                .collect()
         } else {
-            Self {
-                file_path,
-                cache: HashMap::new(),
+            HashMap::new()
+        };
+
+        Ok(Self {
+            file_path,
+            cache,
+            key,
+        })
+    }
+
+    /// Write the cache back to its file. Once a cache is encrypted it is never
+    /// written as plain text again.
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        let users = self.cache.drain().map(|(_, v)| v).collect();
+
+        let root = Root { users };
+
+        let data = bincode::encode_to_vec(root, bincode::config::standard())?;
+
+        let data = match &self.key {
+            Some(key) => key.encrypt(&data)?,
+            None => {
+                let existing = std::fs::read(&self.file_path).unwrap_or_default();
+                if cache_key::is_encrypted(&existing) {
+                    bail!(
+                        "refusing to overwrite encrypted {:?} with plain text",
+                        self.file_path
+                    );
+                }
+                data
             }
         };
-        Ok(res)
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .append(false)
+            .write(true)
+            .open(&self.file_path)
+            .context("could not open cache file")?;
+
+        file.write_all(&data)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// All cached users sorted by email
+    pub fn users(&self) -> Vec<CachedUser> {
+        let mut users: Vec<_> = self.cache.values().map(CachedUser::from).collect();
+        users.sort_by(|a, b| a.email.cmp(&b.email));
+        users
+    }
+
+    /// Insert a user, replacing any cached user with the same email
+    pub fn insert(&mut self, user: CachedUser) {
+        self.cache.insert(user.email.clone(), User::from(user));
+    }
+
+    /// Remove a user. Returns false if the user was not cached.
+    pub fn remove(&mut self, email: &str) -> bool {
+        self.cache.remove(email).is_some()
+    }
+
+    /// Mark a user as valid or invalid. Returns false if the user was not
+    /// cached.
+    ///
+    /// A user marked valid keeps its password but has no tokens, so it is
+    /// authenticated again the next time it is used.
+    pub fn mark(&mut self, email: &str, valid: bool) -> bool {
+        let Some(user) = self.cache.get_mut(email) else {
+            return false;
+        };
+
+        let password = match user {
+            User::Valid(OnlineUser { password, .. })
+            | User::Invalid(InvalidUser { password, .. }) => password.clone(),
+            User::Microsoft(_) => String::new(),
+        };
+
+        *user = match (valid, &user) {
+            (true, User::Invalid(_)) => User::Valid(OnlineUser {
+                email: email.to_string(),
+                username: String::new(),
+                password,
+                last_checked: 0,
+                uuid: String::new(),
+                access_id: String::new(),
+                client_id: String::new(),
+            }),
+            (false, User::Valid(_) | User::Microsoft(_)) => User::Invalid(InvalidUser {
+                email: email.to_string(),
+                password,
+            }),
+            _ => return true,
+        };
+
+        true
+    }
+
+    /// Authenticate a cached user again regardless of when it was last
+    /// checked. Microsoft users go through the Microsoft chain, Mojang users
+    /// through Yggdrasil and invalid users through `auth.mode`. Returns if
+    /// the user is now valid, or an error if the auth server could not tell.
+    pub async fn revalidate(
+        &mut self,
+        email: &str,
+        auth: &Auth,
+        proxies: &mut impl Iterator<Item = Option<Proxy>>,
+    ) -> anyhow::Result<bool> {
+        let user = self
+            .cache
+            .get_mut(email)
+            .with_context(|| format!("{email} is not cached"))?;
+
+        let (mode, password) = match user {
+            User::Valid(user) => {
+                user.last_checked = 0;
+                (AuthMode::Mojang, user.password.clone())
+            }
+            User::Microsoft(user) => {
+                user.expires_at = 0;
+                (AuthMode::Microsoft, String::new())
+            }
+            User::Invalid(user) => (auth.mode, user.password.clone()),
+        };
+
+        // invalid users are authenticated as if they were never cached. They
+        // are put back if the attempt leaves no entry (i.e., it failed for
+        // some other reason than the account).
+        let invalid = matches!(user, User::Invalid(_))
+            .then(|| self.cache.remove(email))
+            .flatten();
+
+        let csv_user = CSVUser {
+            email: email.to_string(),
+            password,
+        };
+
+        let res = match mode {
+            AuthMode::Mojang => Ok(self.get_or_put(&csv_user, proxies, &auth.endpoints).await),
+            AuthMode::Microsoft => {
+                self.get_or_put_microsoft(&csv_user, proxies, &auth.endpoints)
+                    .await
+            }
+        };
+
+        // the user is skipped without being marked invalid if the auth server
+        // could not be reached
+        let rejected = matches!(self.cache.get(email), Some(User::Invalid(_)));
+
+        if let Some(invalid) = invalid {
+            self.cache.entry(email.to_string()).or_insert(invalid);
+        }
+
+        if res?.is_some() {
+            return Ok(true);
+        }
+
+        if !rejected {
+            bail!("could not reach the auth server (see the log)");
+        }
+        Ok(false)
     }
 
     /// Takes a [`CSVUser`] and returns the user's data along with the proxy
@@ -199,11 +607,12 @@ impl UserCache {
         &mut self,
         user: &CSVUser,
         iter: &mut impl Iterator<Item = Option<Proxy>>,
+        endpoints: &AuthEndpoints,
     ) -> Option<(MojangClient, Option<Proxy>, OnlineUser)> {
         match self.cache.get_mut(&user.email) {
             None => {
                 let proxy = iter.next().unwrap();
-                let mojang = MojangClient::try_from(proxy.as_ref()).unwrap();
+                let mojang = MojangClient::new(proxy.as_ref(), endpoints.clone()).unwrap();
                 match mojang.authenticate(&user.email, &user.password).await {
                     Ok(res) => {
                         let valid_user = OnlineUser {
@@ -220,9 +629,15 @@ impl UserCache {
                         Some((mojang, proxy, valid_user))
                     }
 
+                    // skip without caching if the server cannot be reached
+                    Err(e) if !e.is::<Rejected>() => {
+                        warn!("could not authenticate {} .. {:#}", user.email, e);
+                        None
+                    }
+
                     // we cannot do anything more -> change to invalid
                     Err(e) => {
-                        println!("failed authentication for {} .. {}", user.email, e);
+                        warn!("failed authentication for {} .. {}", user.email, e);
                         let invalid = InvalidUser {
                             email: user.email.clone(),
                             password: user.password.clone(),
@@ -237,117 +652,217 @@ impl UserCache {
                 match cached {
                     User::Valid(valid) => {
                         let proxy = iter.next().unwrap();
-                        let mojang = MojangClient::try_from(proxy.as_ref()).unwrap();
+                        let mojang = MojangClient::new(proxy.as_ref(), endpoints.clone()).unwrap();
 
                         // if verified in last day don't even check to verify
                         if time() - valid.last_checked < 3600 * 24 {
                             return Some((mojang, proxy, valid.clone()));
                         }
 
-                        println!("refreshing auth tokens for {} due to time", user.email);
+                        info!("refreshing auth tokens for {} due to time", user.email);
 
+                        // skip without touching the cache if the server cannot
+                        // be reached (it might work next time)
                         let is_valid = mojang
                             .validate(&valid.access_id, &valid.client_id)
                             .await
-                            .unwrap();
+                            .map_err(|e| warn!("could not validate {} .. {:#}", user.email, e))
+                            .ok()?;
 
                         if is_valid {
+                            valid.last_checked = time();
                             return Some((mojang, proxy, valid.clone()));
                         }
-                        println!("failed validating {}", user.email);
-                        match mojang.refresh(&valid.access_id, &valid.client_id).await {
-                            Ok(auth) => {
-                                valid.access_id = auth.access_token;
-                                valid.username = auth.username;
-                                valid.uuid = auth.uuid.to_string();
-                                valid.client_id = auth.client_token;
-                                valid.last_checked = time();
-                                return Some((mojang, proxy, valid.clone()));
+                        warn!("failed validating {}", user.email);
+                        match renew(&mojang, valid).await {
+                            Ok(()) => return Some((mojang, proxy, valid.clone())),
+
+                            // skip without touching the cache if the server
+                            // cannot be reached
+                            Err(e) if !e.is::<Rejected>() => {
+                                warn!("could not renew {} .. {:#}", user.email, e);
+                                return None;
                             }
 
-                            // we could not refresh -> try to authenticate
+                            // we cannot do anything more -> change to invalid
                             Err(e) => {
-                                println!("failed refreshing {} .. {}", user.email, e);
-                                match mojang.authenticate(&valid.email, &valid.password).await {
-                                    Ok(auth) => {
-                                        valid.access_id = auth.access_token;
-                                        valid.username = auth.username;
-                                        valid.uuid = auth.uuid.to_string();
-                                        valid.client_id = auth.client_token;
-                                        valid.last_checked = time();
-                                        return Some((mojang, proxy, valid.clone()));
-                                    }
-
-                                    // we cannot do anything more -> change to invalid
-                                    Err(e) => {
-                                        println!("failed authenticating {} .. {}", user.email, e);
-                                        *cached = User::Invalid(InvalidUser {
-                                            email: valid.email.clone(),
-                                            password: valid.password.clone(),
-                                        });
-                                    }
-                                }
+                                warn!("failed authenticating {} .. {}", user.email, e);
+                                *cached = User::Invalid(InvalidUser {
+                                    email: valid.email.clone(),
+                                    password: valid.password.clone(),
+                                });
                             }
                         }
                     }
                     User::Invalid(_invalid) => {}
+                    User::Microsoft(_) => {
+                        info!(
+                            "user {} is a Microsoft account. Use --auth microsoft to log in",
+                            user.email
+                        );
+                        return None;
+                    }
                 }
 
-                println!("user {} is cached as invalid. If this user **is** valid, delete cache.db and re-run", user.email);
+                warn!("user {0} is cached as invalid. If this user **is** valid, run `swarm-bot cache mark valid {0}`", user.email);
                 None
             }
         }
     }
 
+    /// Takes a [`CSVUser`] and returns the user's data along with the proxy
+    /// associated with it, going through the Microsoft token chain. A cached
+    /// Minecraft token is reused until it is about to expire, after which the
+    /// stored refresh token is used. If that fails too, the user is asked to
+    /// log in with a device code.
+    ///
+    /// The user is only cached as invalid if the auth server [`Rejected`] it.
+    /// Configuration errors are returned.
+    async fn get_or_put_microsoft(
+        &mut self,
+        user: &CSVUser,
+        iter: &mut impl Iterator<Item = Option<Proxy>>,
+        endpoints: &AuthEndpoints,
+    ) -> anyhow::Result<Option<(MojangClient, Option<Proxy>, OnlineUser)>> {
+        if let Some(User::Invalid(_)) = self.cache.get(&user.email) {
+            warn!("user {0} is cached as invalid. If this user **is** valid, run `swarm-bot cache mark valid {0}`", user.email);
+            return Ok(None);
+        }
+
+        let proxy = iter.next().unwrap();
+        let mojang = MojangClient::new(proxy.as_ref(), endpoints.clone())?;
+        mojang.client_id()?;
+
+        if let Some(User::Microsoft(cached)) = self.cache.get(&user.email) {
+            // keep using the token if it is valid for at least another hour
+            if cached.expires_at > time() + 3600 {
+                return Ok(Some((mojang, proxy, cached.online())));
+            }
+
+            info!("refreshing auth tokens for {} due to time", user.email);
+
+            match mojang.refresh_microsoft_chain(&cached.refresh_token).await {
+                Ok(auth) => {
+                    let valid = MicrosoftUser::new(&user.email, auth);
+                    let online = valid.online();
+                    self.cache
+                        .insert(valid.email.clone(), User::Microsoft(valid));
+                    return Ok(Some((mojang, proxy, online)));
+                }
+                Err(e) => warn!("failed refreshing {} .. {:#}", user.email, e),
+            }
+        }
+
+        match mojang.login_microsoft(&user.email).await {
+            Ok(auth) => {
+                let valid = MicrosoftUser::new(&user.email, auth);
+                let online = valid.online();
+                self.cache
+                    .insert(valid.email.clone(), User::Microsoft(valid));
+                Ok(Some((mojang, proxy, online)))
+            }
+
+            // the account itself is bad -> change to invalid
+            Err(e) if e.is::<Rejected>() => {
+                warn!("failed authentication for {} .. {:#}", user.email, e);
+                let invalid = InvalidUser {
+                    email: user.email.clone(),
+                    password: user.password.clone(),
+                };
+                self.cache
+                    .insert(invalid.email.clone(), User::Invalid(invalid));
+                Ok(None)
+            }
+
+            // it might work next time, so leave the cache as it is
+            Err(e) => {
+                warn!(
+                    "could not authenticate {} (skipping) .. {:#}",
+                    user.email, e
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Obtain `count` valid users for each group. A user is only used once
+    /// even if it is in the users file of several groups.
     pub fn obtain_users(
         mut self,
-        count: usize,
-        users: Vec<CSVUser>,
-        proxies: Vec<Option<Proxy>>,
+        groups: Vec<GroupUsers>,
+        auth: Auth,
     ) -> Receiver<BotConnectionData> {
-        let mut proxies = proxies.into_iter().cycle();
-
         let (tx, rx) = tokio::sync::mpsc::channel(32);
 
         // spawn the receiver that will yield players
         tokio::task::spawn_local(async move {
-            let mut local_count = 0;
-
-            'user_loop: for csv_user in users {
-                if let Some((mojang, proxy, user)) = self.get_or_put(&csv_user, &mut proxies).await
-                {
-                    local_count += 1;
-                    println!("valid user {}", user.email);
-                    tx.send(BotConnectionData {
-                        bot: BotData::Online { user, mojang },
-                        proxy,
-                    })
-                    .await
-                    .unwrap();
-                } else {
-                    println!("invalid user {}", csv_user.email);
+            let mut used = HashSet::new();
+
+            'group_loop: for group in groups {
+                let GroupUsers {
+                    server,
+                    count,
+                    users,
+                    mut proxies,
+                    config,
+                } = group;
+
+                let mut local_count = 0;
+
+                'user_loop: for csv_user in users {
+                    if local_count >= count {
+                        break 'user_loop;
+                    }
+
+                    if !used.insert(csv_user.email.clone()) {
+                        continue;
+                    }
+
+                    let obtained = match auth.mode {
+                        AuthMode::Mojang => {
+                            self.get_or_put(&csv_user, &mut proxies, &auth.endpoints)
+                                .await
+                        }
+                        AuthMode::Microsoft => {
+                            match self
+                                .get_or_put_microsoft(&csv_user, &mut proxies, &auth.endpoints)
+                                .await
+                            {
+                                Ok(obtained) => obtained,
+
+                                // no other user is going to work either
+                                Err(e) => {
+                                    error!("could not obtain users .. {e:#}");
+                                    break 'group_loop;
+                                }
+                            }
+                        }
+                    };
+
+                    if let Some((mojang, proxy, user)) = obtained {
+                        local_count += 1;
+                        info!("valid user {}", user.email);
+                        tx.send(BotConnectionData {
+                            bot: BotData::Online { user, mojang },
+                            proxy,
+                            config: config.clone(),
+                            server: server.clone(),
+                        })
+                        .await
+                        .unwrap();
+                    } else {
+                        warn!("invalid user {}", csv_user.email);
+                    }
                 }
 
-                if local_count >= count {
-                    break 'user_loop;
+                if local_count < count {
+                    warn!("only {local_count} of {count} users are valid");
                 }
             }
 
-            let mut file = OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .append(false)
-                .write(true)
-                .open(&self.file_path)
-                .unwrap();
-
-            let users = self.cache.drain().map(|(_, v)| v).collect();
-
-            let root = Root { users };
-
-            let data = bincode::encode_to_vec(root, bincode::config::standard()).unwrap();
-            file.write_all(&data).unwrap();
-            file.flush().unwrap();
+            if let Err(e) = self.save() {
+                error!("could not save user cache .. {e:#}");
+            }
         });
 
         rx
@@ -1,40 +1,267 @@
 use std::time::Instant;
 
 use anyhow::bail;
-use crossterm::style::Stylize;
 use float_ord::FloatOrd;
-use interfaces::types::BlockLocation;
+use interfaces::{query::TaskInfo, types::BlockLocation, Outcome};
 use itertools::Itertools;
+use tracing::{debug, debug_span, info, Span};
 
 use crate::{
     client::{
+        commands::CommandId,
         state::{global::GlobalState, local::LocalState},
         tasks::{
-            compound::CompoundTask, eat::EatTask, fall_bucket::FallBucketTask, mine::MineTask,
-            navigate::BlockTravelTask, Task,
+            behavior::{self, BehaviorTree},
+            compound::CompoundTask,
+            eat::EatTask,
+            fall_bucket::FallBucketTask,
+            mine::MineTask,
+            navigate::BlockTravelTask,
+            script,
+            script::ScriptTask,
+            Status, Task,
         },
     },
+    metrics::short_type_name,
     protocol::{EventQueue, Face, InterfaceOut},
     types::Displacement,
 };
 
-/// The current [`Task`] we are trying to achieve. If we are not aiming at
-/// achieving a task, this is [`None`].
+/// how often the progress of the running task is logged (in ticks)
+const PROGRESS_LOG_TICKS: usize = 100;
+
+/// How urgent a task is. A task interrupts less urgent ones, which continue
+/// once it is done.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// the configured [`BehaviorTree`], which runs when the bot has nothing
+    /// else to do and is kept when it dies
+    Behavior,
+    /// background work (i.e., mining a region)
+    Low,
+    /// commands from chat, the dashboard and websocket clients
+    #[default]
+    Normal,
+    /// tasks which keep the bot alive (i.e., eating)
+    Urgent,
+}
+
+/// A scheduled [`Task`]
+struct Entry {
+    task: Box<dyn Task>,
+    priority: Priority,
+
+    /// the websocket command the task is for
+    command: Option<CommandId>,
+
+    /// a more urgent task ran since this one was scheduled
+    interrupted: bool,
+
+    /// the task stopped running for a more urgent one and has not been told
+    /// yet (see [`Task::cancel`])
+    pause: bool,
+}
+
+impl Entry {
+    fn stopped(&self, outcome: Outcome) -> Stopped {
+        Stopped {
+            task: self.task.name(),
+            command: self.command,
+            outcome,
+        }
+    }
+}
+
+/// The [`Task`]s we are trying to achieve. Only the most urgent one runs. If we
+/// are not aiming at achieving a task, there are none.
 #[derive(Default)]
 pub struct ActionState {
-    /// the task. In the future, this might change to be a priority queue of
-    /// tasks
-    task: Option<Box<dyn Task>>,
+    /// the tasks from least to most urgent. There is at most one task per
+    /// [`Priority`] and the last one is running.
+    tasks: Vec<Entry>,
+
+    /// tasks which stopped since they were last taken
+    stopped: Vec<Stopped>,
+
+    /// tasks which were stopped before they were done and have not been told
+    /// yet (see [`Task::cancel`])
+    cancelled: Vec<Box<dyn Task>>,
+}
+
+/// A task which stopped
+pub struct Stopped {
+    /// the type name of the task
+    pub task: &'static str,
+
+    /// the websocket command the task was for
+    pub command: Option<CommandId>,
+
+    pub outcome: Outcome,
 }
 
 impl ActionState {
-    /// schedule a task
+    /// schedule a task with [`Priority::Normal`]
     pub fn schedule(&mut self, task: impl Task + 'static) {
-        self.task = Some(Box::new(task));
+        self.push(Box::new(task), Priority::Normal, None);
+    }
+
+    /// schedule a task which replaces the task with the same `priority` (if
+    /// any) and interrupts less urgent ones
+    pub fn schedule_with(&mut self, task: impl Task + 'static, priority: Priority) {
+        self.push(Box::new(task), priority, None);
+    }
+
+    /// schedule a task for a websocket command
+    pub fn schedule_command(
+        &mut self,
+        task: impl Task + 'static,
+        priority: Priority,
+        command: CommandId,
+    ) {
+        self.push(Box::new(task), priority, Some(command));
+    }
+
+    fn push(&mut self, task: Box<dyn Task>, priority: Priority, command: Option<CommandId>) {
+        if let Some(idx) = self.tasks.iter().position(|e| e.priority == priority) {
+            let replaced = self.tasks.remove(idx);
+            self.cancel(replaced);
+        }
+
+        let idx = self.tasks.partition_point(|e| e.priority < priority);
+        for below in &mut self.tasks[..idx] {
+            below.interrupted = true;
+        }
+
+        // the running task is interrupted
+        if idx == self.tasks.len() {
+            if let Some(running) = self.tasks.last_mut() {
+                running.pause = true;
+            }
+        }
+
+        self.tasks.insert(
+            idx,
+            Entry {
+                task,
+                priority,
+                command,
+                interrupted: false,
+                pause: false,
+            },
+        );
     }
+
+    /// a task stopped before it was done
+    fn cancel(&mut self, entry: Entry) {
+        self.stopped.push(entry.stopped(Outcome::Cancelled));
+        self.cancelled.push(entry.task);
+    }
+
     /// clear the task list
     pub fn clear(&mut self) {
-        self.task = None;
+        for entry in std::mem::take(&mut self.tasks) {
+            self.cancel(entry);
+        }
+    }
+
+    /// clear the task list except for tasks with `priority`, which start
+    /// what they were doing over (i.e., after the bot died)
+    pub fn clear_except(&mut self, priority: Priority) {
+        let (kept, cleared): (Vec<_>, Vec<_>) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|entry| entry.priority == priority);
+
+        for entry in cleared {
+            self.cancel(entry);
+        }
+
+        self.tasks = kept;
+        for entry in &mut self.tasks {
+            entry.interrupted = true;
+            entry.pause = true;
+        }
+    }
+
+    /// let tasks which stopped running (because they were cancelled or
+    /// interrupted) leave the bot in a neutral state. This is done before the
+    /// next task runs.
+    fn cancel_stopped(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        for mut task in self.cancelled.drain(..) {
+            debug!(task = %short_type_name(task.name()), "cancelling task");
+            task.cancel(out, local, global);
+        }
+
+        for entry in &mut self.tasks {
+            if entry.pause {
+                entry.pause = false;
+                entry.task.cancel(out, local, global);
+            }
+        }
+    }
+
+    /// stop the task for a websocket command. Returns false if no task is for
+    /// it.
+    pub fn cancel_command(&mut self, command: CommandId) -> bool {
+        let Some(idx) = self.tasks.iter().position(|e| e.command == Some(command)) else {
+            return false;
+        };
+        let entry = self.tasks.remove(idx);
+        self.cancel(entry);
+        true
+    }
+
+    /// the running task is done
+    fn finish(&mut self, outcome: Outcome) {
+        if let Some(entry) = self.tasks.pop() {
+            self.stopped.push(entry.stopped(outcome));
+        }
+    }
+
+    /// the running task. It is told to resume first if it was interrupted.
+    fn running(&mut self, local: &mut LocalState, global: &GlobalState) -> Option<&mut dyn Task> {
+        let entry = self.tasks.last_mut()?;
+        if entry.interrupted {
+            debug!("resuming task");
+            entry.interrupted = false;
+            entry.task.resume(local, global);
+        }
+        Some(entry.task.as_mut())
+    }
+
+    /// the websocket command the bot is working on (even if its task is
+    /// interrupted)
+    pub fn command(&self) -> Option<CommandId> {
+        self.tasks.iter().rev().find_map(|entry| entry.command)
+    }
+
+    /// the tasks which stopped since this was last called
+    pub fn take_stopped(&mut self) -> Vec<Stopped> {
+        std::mem::take(&mut self.stopped)
+    }
+
+    /// if the bot has no task
+    pub fn is_idle(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// if a task with `priority` is scheduled
+    pub fn has(&self, priority: Priority) -> bool {
+        self.tasks.iter().any(|entry| entry.priority == priority)
+    }
+
+    /// the type name of the running task
+    pub fn task_name(&self) -> Option<&'static str> {
+        self.tasks.last().map(|entry| entry.task.name())
+    }
+
+    /// what the running task is doing
+    pub fn describe(&self) -> Option<TaskInfo> {
+        self.tasks.last().map(|entry| entry.task.describe())
     }
 }
 
@@ -50,15 +277,31 @@ pub struct Bot<Queue: EventQueue, Out: InterfaceOut> {
     pub queue: Queue,
     /// The [`InterfaceOut`] we use to communicate with the world
     pub out: Out,
+
+    /// the `bot` span with the id and username of the bot. Everything the bot
+    /// does is logged inside it
+    pub span: Span,
 }
 
 impl<Queue: EventQueue, Out: InterfaceOut> Bot<Queue, Out> {
     pub fn run_sync(&mut self, global: &mut GlobalState) {
-        match self.actions.task.as_mut() {
-            None => {}
-            Some(task) => {
-                if task.tick(&mut self.out, &mut self.state, global) {
-                    self.actions.task = None;
+        self.actions
+            .cancel_stopped(&mut self.out, &mut self.state, global);
+
+        if let Some(task) = self.actions.running(&mut self.state, global) {
+            let _span = debug_span!("task", name = %short_type_name(task.name())).entered();
+            if self.state.ticks % PROGRESS_LOG_TICKS == 0 {
+                debug!(activity = %task.describe(), "task progress");
+            }
+            match task.tick(&mut self.out, &mut self.state, global) {
+                Status::Running => {}
+                Status::Success => {
+                    debug!("finished task");
+                    self.actions.finish(Outcome::Finished);
+                }
+                Status::Failure => {
+                    debug!("task failed");
+                    self.actions.finish(Outcome::Failed);
                 }
             }
         }
@@ -105,13 +348,10 @@ pub fn process_command(
     out: &mut dyn InterfaceOut,
 ) -> anyhow::Result<()> {
     macro_rules! msg {
-        () => {{
-            println!();
-        }};
         ($($msg: expr),*) => {{
-            let to_print_raw = format!($($msg),*);
-            let to_print = to_print_raw.bold().to_string();
-            println!("{}", to_print);
+            let msg = format!($($msg),*);
+            info!("{msg}");
+            local.record_chat(msg);
         }};
     }
 
@@ -143,7 +383,7 @@ pub fn process_command(
         //     local.physics.jump();
         // }
         "health" => {
-            println!("Health: {}, Food: {}", local.health, local.food);
+            msg!("Health: {}, Food: {}", local.health, local.food);
         }
         "follow" => {
             local.follow_closest = true;
@@ -153,7 +393,7 @@ pub fn process_command(
         // }
         "eat" => {
             let eat_task = EatTask::default();
-            actions.task = Some(Box::new(eat_task));
+            actions.schedule(eat_task);
         }
         "slot" => {
             if let [number] = args {
@@ -175,15 +415,20 @@ pub fn process_command(
         }
         "goto" => {
             if let [a, b, c] = args {
-                let x = a.parse()?;
-                let y = b.parse()?;
-                let z = c.parse()?;
-                let dest = BlockLocation::new(x, y, z);
+                let dest = BlockLocation::new(a.parse()?, b.parse()?, c.parse()?);
                 actions.schedule(BlockTravelTask::new(dest, local));
             }
         }
+        "script" => {
+            if let [name] = args {
+                let ast = script::load(name)?;
+                actions.schedule(ScriptTask::new(ast));
+            }
+        }
+        "behave" => behave(args, actions)?,
+        "set" => set(args, local)?,
         "stop" => {
-            actions.task = None;
+            actions.clear();
         }
         "loc" => {
             msg!(
@@ -201,16 +446,14 @@ pub fn process_command(
                         BlockLocation::from(local.physics.location() - Displacement::EPSILON_Y);
                     msg!("below kind {:?}", global.blocks.get_block_kind(below_loc));
                     msg!("inventory slots {:?}", local.inventory.hotbar());
+                    let task = actions.describe().map(|activity| activity.to_string());
+                    msg!("task {}", task.as_deref().unwrap_or("none"));
                 }
             }
         }
         "get" => {
             if let [a, b, c] = args {
-                let x = a.parse()?;
-                let y = b.parse()?;
-                let z = c.parse()?;
-                let location = BlockLocation::new(x, y, z);
-
+                let location = BlockLocation::new(a.parse()?, b.parse()?, c.parse()?);
                 msg!("The block is {:?}", global.blocks.get_block(location));
             }
         }
@@ -252,6 +495,24 @@ pub fn process_command(
     Ok(())
 }
 
+/// run the behavior tree `name` (i.e., `behave autonomous`)
+fn behave(args: &[&str], actions: &mut ActionState) -> anyhow::Result<()> {
+    if let [name] = args {
+        let tree = BehaviorTree::new(behavior::load(name)?);
+        actions.schedule_with(tree, Priority::Behavior);
+    }
+    Ok(())
+}
+
+/// put a location on the blackboard (i.e., `set bed 10 64 -3`)
+fn set(args: &[&str], local: &mut LocalState) -> anyhow::Result<()> {
+    if let [key, a, b, c] = args {
+        let location = BlockLocation::new(a.parse()?, b.parse()?, c.parse()?);
+        local.blackboard.set(key, location);
+    }
+    Ok(())
+}
+
 pub fn run_threaded(
     _: &rayon::Scope,
     local: &mut LocalState,
@@ -259,7 +520,158 @@ pub fn run_threaded(
     global: &GlobalState,
     end_by: Instant,
 ) {
-    if let Some(task) = actions.task.as_mut() {
+    if let Some(task) = actions.running(local, global) {
+        let _span = debug_span!("task", name = %short_type_name(task.name())).entered();
         task.expensive(end_by, local, global);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use interfaces::{types::BlockLocation, Outcome};
+
+    use crate::{
+        client::{
+            bot::{ActionState, Priority},
+            processor::{InterfaceIn, SimpleInterfaceIn},
+            state::{global::GlobalState, local::LocalState},
+            tasks::{
+                behavior::{self, BehaviorTree},
+                delay::DelayTask,
+                eat::EatTask,
+                lazy_stream::LazyStream,
+                mine::MineTask,
+                mine_region::MineRegion,
+                Status, Task,
+            },
+        },
+        protocol::{InterfaceOut, Mine, MockOut},
+        storage::blocks::WorldBlocks,
+    };
+
+    /// never finishes and counts how often it was resumed
+    struct Resumable(Arc<AtomicUsize>);
+
+    impl Task for Resumable {
+        fn tick(
+            &mut self,
+            _: &mut dyn InterfaceOut,
+            _: &mut LocalState,
+            _: &mut GlobalState,
+        ) -> Status {
+            Status::Running
+        }
+
+        fn resume(&mut self, _: &mut LocalState, _: &GlobalState) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_priority() {
+        let mut local = LocalState::mock();
+        let global = GlobalState::init();
+        let resumed = Arc::new(AtomicUsize::new(0));
+        let name = |actions: &ActionState| actions.task_name().unwrap().rsplit("::").next();
+
+        let mut actions = ActionState::default();
+        actions.schedule_with(Resumable(resumed.clone()), Priority::Low);
+        actions.schedule(DelayTask::new(0));
+        assert_eq!(name(&actions), Some("DelayTask"));
+
+        // the interrupted task resumes once the more urgent one is done
+        actions.running(&mut local, &global).unwrap();
+        actions.finish(Outcome::Finished);
+        assert_eq!(name(&actions), Some("Resumable"));
+        actions.running(&mut local, &global).unwrap();
+        actions.running(&mut local, &global).unwrap();
+        assert_eq!(resumed.load(Ordering::Relaxed), 1);
+
+        // less urgent tasks wait behind more urgent ones
+        actions.schedule_with(EatTask::default(), Priority::Urgent);
+        actions.schedule(DelayTask::new(5));
+        assert_eq!(name(&actions), Some("EatTask"));
+        assert!(actions.has(Priority::Normal));
+
+        // only the task which was running is told it stopped
+        let paused: Vec<_> = actions.tasks.iter().map(|entry| entry.pause).collect();
+        assert_eq!(paused, [true, false, false]);
+
+        // a task replaces the one with the same priority
+        actions.schedule(DelayTask::new(1));
+        let stopped = actions.take_stopped();
+        assert_eq!(stopped.len(), 2);
+        assert!(stopped[1].task.ends_with("DelayTask"));
+        assert_eq!(stopped[1].outcome, Outcome::Cancelled);
+        assert_eq!(actions.cancelled.len(), 1);
+
+        actions.clear();
+        assert!(actions.is_idle());
+        assert_eq!(actions.take_stopped().len(), 3);
+        assert_eq!(actions.cancelled.len(), 4);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut out = MockOut::default();
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+        global.blocks = WorldBlocks::flat();
+
+        let location = BlockLocation::new(0, 0, 0);
+        let mut actions = ActionState::default();
+        actions.schedule(MineTask::new(location, &mut out, &mut local, &global));
+
+        let task = actions.running(&mut local, &global).unwrap();
+        assert_eq!(
+            task.tick(&mut out, &mut local, &mut global),
+            Status::Running
+        );
+        assert_eq!(out.mined, [(location, Mine::Start)]);
+
+        // the replaced task stops digging before the next one runs
+        actions.schedule(DelayTask::new(5));
+        actions.cancel_stopped(&mut out, &mut local, &mut global);
+        assert_eq!(
+            out.mined,
+            [(location, Mine::Start), (location, Mine::Cancel)]
+        );
+    }
+
+    #[test]
+    fn test_behavior() {
+        let mut out = MockOut::default();
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+        let behaving = |actions: &ActionState| {
+            actions
+                .task_name()
+                .is_some_and(|name| name.ends_with("BehaviorTree"))
+        };
+
+        let mut actions = ActionState::default();
+        let tree = BehaviorTree::new(behavior::autonomous());
+        actions.schedule_with(tree, Priority::Behavior);
+        actions.schedule(DelayTask::new(5));
+
+        // dying stops commands but not the behavior
+        SimpleInterfaceIn::new(&mut local, &mut actions, &mut global, &mut out).on_death();
+        assert!(behaving(&actions));
+        assert_eq!(actions.take_stopped().len(), 1);
+
+        // mining (as the websocket `mine` command does) interrupts the
+        // behavior rather than replacing it
+        actions.schedule_with(LazyStream::from(MineRegion::default()), Priority::Low);
+        assert!(actions.has(Priority::Behavior));
+        assert!(actions.take_stopped().is_empty());
+
+        actions.finish(Outcome::Finished);
+        assert!(behaving(&actions));
+        assert!(actions.running(&mut local, &global).is_some());
+    }
+}
@@ -1,85 +1,525 @@
-use std::sync::mpsc::{Receiver, Sender};
+//! The websocket command protocol. Clients send [`Command`]s with an id they
+//! choose. Each command is acknowledged ([`Ack`]) or rejected ([`Error`]).
+//! Every bot working on it then reports [`Progress`] when it stops, and the
+//! command ends with [`Finished`] or [`Cancelled`].
+//!
+//! Clients authenticate with a token (`Authorization: Bearer {token}`) when
+//! the swarm was launched with tokens. Control tokens allow any command while
+//! read tokens only allow queries and subscriptions.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    net::SocketAddr,
+    path::PathBuf,
+    rc::Rc,
+    sync::mpsc::{Receiver, Sender},
+};
 
 use anyhow::{bail, Context};
-use futures::StreamExt;
+use float_ord::FloatOrd;
+use futures::{SinkExt, StreamExt};
+use interfaces::{
+    types::Location, Ack, Cancelled, Command, Error, ErrorKind, Finished, Id, Nearest, Outcome,
+    Progress, Response, Selector,
+};
 use serde_json::Value;
-use tokio::net::{TcpListener, TcpStream};
-use tokio_tungstenite::WebSocketStream;
-use tracing::error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+};
+use tokio_native_tls::TlsAcceptor;
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::server::{ErrorResponse, Request},
+        http::{header::AUTHORIZATION, StatusCode},
+        Message,
+    },
+    WebSocketStream,
+};
+use tracing::{debug, info, warn};
 
 /// commands received over websocket (typically forge mod)
 pub struct CommandReceiver {
-    pub pending: Receiver<TaggedValue>,
+    pub pending: Receiver<Incoming>,
 }
 
-struct Processor {
-    value: Value,
+/// How the websocket server is reached and who may use it
+pub struct WsOptions {
+    /// the address to listen on
+    pub address: SocketAddr,
+
+    /// tokens of clients which may run any command. Clients do not need a
+    /// token if there are neither control nor read tokens
+    pub control_tokens: Vec<String>,
+
+    /// tokens of clients which may only query and subscribe
+    pub read_tokens: Vec<String>,
+
+    /// serve `wss://` instead of `ws://`
+    pub tls: Option<Tls>,
 }
 
-pub struct TaggedValue {
-    pub path: String,
-    pub value: Value,
+/// The certificate websocket clients are served with
+pub struct Tls {
+    /// the certificate chain (PEM)
+    pub cert: PathBuf,
+    /// the private key (PKCS #8 PEM)
+    pub key: PathBuf,
 }
 
-impl TaggedValue {
-    pub fn parse<T>(self) -> Result<T, serde_json::Error>
-    where
-        T: serde::de::DeserializeOwned,
-    {
-        serde_json::from_value(self.value)
+impl Tls {
+    fn acceptor(&self) -> anyhow::Result<TlsAcceptor> {
+        let read = |path: &PathBuf| {
+            std::fs::read(path).with_context(|| format!("could not read {}", path.display()))
+        };
+
+        let identity = native_tls::Identity::from_pkcs8(&read(&self.cert)?, &read(&self.key)?)
+            .context("invalid websocket certificate or key")?;
+        let acceptor = native_tls::TlsAcceptor::new(identity)?;
+        Ok(acceptor.into())
     }
 }
 
-async fn command_receiver(
-    tx: Sender<TaggedValue>,
-    mut ws: WebSocketStream<TcpStream>,
-) -> anyhow::Result<()> {
-    while let Some(msg) = ws.next().await {
-        let msg = msg.context("error reading next web socket message (websocket disconnect?)")?;
+/// What a client may do
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// only queries and subscriptions
+    Read,
+    /// any command
+    Control,
+}
 
-        let text = msg.into_text().unwrap();
+/// compare tokens in constant time so they cannot be guessed by timing
+fn token_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
 
-        let v: Value = match serde_json::from_str(&text) {
-            Ok(v) => v,
-            Err(e) => {
-                error!("invalid json {e}");
-                continue;
-            }
-        };
+struct Tokens {
+    control: Vec<String>,
+    read: Vec<String>,
+}
+
+impl Tokens {
+    /// if clients do not need a token
+    fn is_open(&self) -> bool {
+        self.control.is_empty() && self.read.is_empty()
+    }
+
+    /// the role of a client which sent `token` or [`None`] if it may not
+    /// connect
+    fn role(&self, token: Option<&str>) -> Option<Role> {
+        if self.is_open() {
+            return Some(Role::Control);
+        }
+
+        let token = token?;
+        let any = |tokens: &[String]| tokens.iter().any(|t| token_eq(t, token));
+
+        if any(&self.control) {
+            Some(Role::Control)
+        } else if any(&self.read) {
+            Some(Role::Read)
+        } else {
+            None
+        }
+    }
+}
+
+/// the token of `Authorization: Bearer {token}`
+fn bearer(request: &Request) -> Option<&str> {
+    let value = request.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    value.strip_prefix("Bearer ")
+}
+
+/// A connected websocket client
+#[derive(Clone)]
+pub struct Client {
+    /// unique per connection
+    id: u32,
+    role: Role,
+    tx: UnboundedSender<Response>,
+}
+
+impl Client {
+    pub const fn new(id: u32, role: Role, tx: UnboundedSender<Response>) -> Self {
+        Self { id, role, tx }
+    }
+
+    /// if the client may run commands which change the swarm
+    pub fn can_control(&self) -> bool {
+        self.role == Role::Control
+    }
+
+    /// send a response. Responses to clients which disconnected are dropped.
+    pub fn send(&self, response: Response) {
+        let _ = self.tx.send(response);
+    }
+
+    /// if the client is still connected
+    pub fn is_connected(&self) -> bool {
+        !self.tx.is_closed()
+    }
+
+    /// reject the command with `id`
+    pub fn error(&self, id: Option<Id>, err: CommandError) {
+        self.send(Response::Error(Error {
+            id,
+            kind: err.kind,
+            message: err.message,
+        }));
+    }
+}
+
+/// A command and the client which sent it
+pub struct Incoming {
+    pub client: Client,
+    pub command: Command,
+}
+
+/// Identifies a command across all clients (ids are only unique per client)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CommandId {
+    client: u32,
+    id: Id,
+}
+
+impl Incoming {
+    pub const fn id(&self) -> CommandId {
+        CommandId {
+            client: self.client.id,
+            id: self.command.id,
+        }
+    }
+
+    /// the command with `id` from the same client
+    pub const fn other(&self, id: Id) -> CommandId {
+        CommandId {
+            client: self.client.id,
+            id,
+        }
+    }
+}
+
+impl CommandId {
+    /// the id the client chose
+    pub const fn id(self) -> Id {
+        self.id
+    }
+
+    /// the id the client chose if the command is from `client`
+    pub const fn id_for(self, client: &Client) -> Option<Id> {
+        if self.client == client.id {
+            Some(self.id)
+        } else {
+            None
+        }
+    }
+}
+
+/// Why a command was rejected
+#[derive(Debug)]
+pub struct CommandError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl CommandError {
+    pub fn new(kind: ErrorKind, message: impl Display) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for CommandError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::new(ErrorKind::InvalidData, err)
+    }
+}
+
+/// A command the bots are working on
+struct ActiveCommand {
+    client: Client,
+
+    /// the bots which have not stopped working on it
+    bots: HashSet<u32>,
+
+    /// if any bot was cancelled
+    cancelled: bool,
+}
+
+/// Keeps track of which bots are working on which command so the client can
+/// be told when they stop
+#[derive(Default)]
+pub struct CommandTracker {
+    active: HashMap<CommandId, ActiveCommand>,
+}
+
+impl CommandTracker {
+    /// `bots` started working on the command
+    pub fn start(&mut self, incoming: &Incoming, bots: Vec<u32>) {
+        let id = incoming.command.id;
+        let client = incoming.client.clone();
+
+        client.send(Response::Ack(Ack {
+            id,
+            bots: bots.clone(),
+        }));
+
+        if bots.is_empty() {
+            client.send(Response::Finished(Finished { id }));
+            return;
+        }
+
+        self.active.insert(
+            incoming.id(),
+            ActiveCommand {
+                client,
+                bots: bots.into_iter().collect(),
+                cancelled: false,
+            },
+        );
+    }
 
-        let Value::Object(mut map) = v else {
-            bail!("expected object")
+    /// `bot` stopped working on `command`
+    pub fn stopped(&mut self, command: CommandId, bot: u32, outcome: Outcome) {
+        let Some(active) = self.active.get_mut(&command) else {
+            return;
         };
 
-        let Value::String(path) = map.remove("path").expect("no path elem") else {
-            bail!("invalid path")
+        if !active.bots.remove(&bot) {
+            return;
+        }
+
+        active.cancelled |= outcome != Outcome::Finished;
+        active.client.send(Response::Progress(Progress {
+            id: command.id,
+            bot,
+            outcome,
+            remaining: active.bots.len(),
+        }));
+
+        if !active.bots.is_empty() {
+            return;
+        }
+
+        let id = command.id;
+        let response = if active.cancelled {
+            Response::Cancelled(Cancelled { id })
+        } else {
+            Response::Finished(Finished { id })
         };
+        active.client.send(response);
+        self.active.remove(&command);
+    }
+
+    /// if bots are still working on `command`
+    pub fn is_active(&self, command: CommandId) -> bool {
+        self.active.contains_key(&command)
+    }
+
+    /// the bots working on `command`
+    pub fn bots(&self, command: CommandId) -> Option<&HashSet<u32>> {
+        self.active.get(&command).map(|active| &active.bots)
+    }
+}
+
+/// What a [`Selector`] is matched against
+pub struct Candidate<'a> {
+    pub id: u32,
+    pub username: &'a str,
+    pub group: Option<&'a str>,
+    pub idle: bool,
+    pub location: Location,
+}
+
+/// the ids of the bots `selector` selects out of `candidates`
+pub fn select<'a>(
+    selector: &Selector,
+    candidates: impl IntoIterator<Item = Candidate<'a>>,
+) -> HashSet<u32> {
+    let Selector {
+        ids,
+        names,
+        groups,
+        idle,
+        nearest,
+    } = selector;
+
+    let any = ids.is_empty() && names.is_empty() && groups.is_empty();
+
+    let mut selected: Vec<_> = candidates
+        .into_iter()
+        .filter(|bot| {
+            any || ids.contains(&bot.id)
+                || names.iter().any(|name| name == bot.username)
+                || bot
+                    .group
+                    .is_some_and(|group| groups.iter().any(|g| g == group))
+        })
+        .filter(|bot| !idle || bot.idle)
+        .collect();
+
+    if let Some(Nearest { location, count }) = nearest {
+        let location = location.center_bottom();
+        selected.sort_by_key(|bot| FloatOrd(bot.location.dist2(location)));
+        selected.truncate(*count);
+    }
+
+    selected.into_iter().map(|bot| bot.id).collect()
+}
+
+/// the id of a message which is not a valid [`Command`] (if it has one)
+fn message_id(text: &str) -> Option<Id> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    value.get("id")?.as_u64()
+}
+
+/// handle a connection until it is closed
+async fn command_receiver<S: AsyncRead + AsyncWrite + Unpin>(
+    tx: Sender<Incoming>,
+    ws: WebSocketStream<S>,
+    client: Client,
+    mut responses: UnboundedReceiver<Response>,
+) -> anyhow::Result<()> {
+    let (mut sink, mut stream) = ws.split();
 
-        let value = Value::Object(map);
+    loop {
+        tokio::select! {
+            msg = stream.next() => {
+                let Some(msg) = msg else {
+                    return Ok(());
+                };
 
-        let elem = TaggedValue { path, value };
+                let text = match msg? {
+                    Message::Text(text) => text,
+                    Message::Close(_) => return Ok(()),
+                    _ => continue,
+                };
 
-        tx.send(elem).unwrap();
+                match serde_json::from_str::<Command>(&text) {
+                    Ok(command) => {
+                        debug!("received command {} with id {}", command.path, command.id);
+                        let client = client.clone();
+                        if tx.send(Incoming { client, command }).is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Err(err) => client.error(
+                        message_id(&text),
+                        CommandError::new(ErrorKind::InvalidMessage, err),
+                    ),
+                }
+            }
+            response = responses.recv() => {
+                // the client is never dropped as we hold a copy
+                let Some(response) = response else {
+                    return Ok(());
+                };
+                let text = serde_json::to_string(&response)?;
+                sink.send(Message::Text(text)).await?;
+            }
+        }
     }
-    Ok(())
+}
+
+/// authenticate a client and handle it until it disconnects
+async fn connection<S: AsyncRead + AsyncWrite + Unpin>(
+    id: u32,
+    stream: S,
+    tokens: &Tokens,
+    tx: Sender<Incoming>,
+) -> anyhow::Result<()> {
+    let mut role = None;
+
+    let ws = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response| {
+        role = tokens.role(bearer(request));
+        if role.is_some() {
+            return Ok(response);
+        }
+
+        let mut error = ErrorResponse::new(Some("invalid token".to_string()));
+        *error.status_mut() = StatusCode::UNAUTHORIZED;
+        Err(error)
+    })
+    .await
+    .context("websocket handshake failed")?;
+
+    let role = role.context("no role after handshake")?;
+
+    info!("websocket client {id} connected ({role:?})");
+    let (responses_tx, responses) = tokio::sync::mpsc::unbounded_channel();
+    let client = Client::new(id, role, responses_tx);
+
+    let result = command_receiver(tx, ws, client, responses).await;
+    info!("websocket client {id} disconnected");
+    result
 }
 
 impl CommandReceiver {
-    pub async fn init(port: u16) -> anyhow::Result<Self> {
+    pub async fn init(options: WsOptions) -> anyhow::Result<Self> {
+        let WsOptions {
+            address,
+            control_tokens,
+            read_tokens,
+            tls,
+        } = options;
+
+        let tokens = Tokens {
+            control: control_tokens,
+            read: read_tokens,
+        };
+
+        // anyone on the network could control the bots
+        if tokens.is_open() && !address.ip().is_loopback() {
+            bail!("the websocket needs a token (--ws-token) to listen on {address}");
+        }
+
+        let tls = tls.as_ref().map(Tls::acceptor).transpose()?;
+
         let (tx, rx) = std::sync::mpsc::channel();
 
-        let server = TcpListener::bind(format!("127.0.0.1:{port}")).await?;
+        let server = TcpListener::bind(address)
+            .await
+            .with_context(|| format!("could not listen for websocket clients on {address}"))?;
+
+        let scheme = if tls.is_some() { "wss" } else { "ws" };
+        info!("listening for websocket clients on {scheme}://{address}");
+
+        let tokens = Rc::new(tokens);
 
         tokio::task::spawn_local(async move {
-            loop {
-                let (stream, _) = server.accept().await.unwrap();
-                let ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            for id in 0.. {
+                let stream = match server.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("error accepting websocket connection: {e}");
+                        continue;
+                    }
+                };
 
                 let tx = tx.clone();
+                let tokens = tokens.clone();
+                let tls = tls.clone();
 
                 tokio::task::spawn_local(async move {
-                    if let Err(e) = command_receiver(tx, ws).await {
-                        println!("error with websocket: {e}");
+                    let result = match tls {
+                        Some(tls) => match tls.accept(stream).await {
+                            Ok(stream) => connection(id, stream, &tokens, tx).await,
+                            Err(e) => Err(anyhow::Error::new(e).context("TLS handshake failed")),
+                        },
+                        None => connection(id, stream, &tokens, tx).await,
+                    };
+
+                    if let Err(e) = result {
+                        warn!("error with websocket client {id}: {e:#}");
                     }
                 });
             }
@@ -88,3 +528,130 @@ impl CommandReceiver {
         Ok(Self { pending: rx })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use interfaces::{
+        types::{BlockLocation, Location},
+        Ack, Cancelled, Command, Nearest, Outcome, Progress, Response, Selector,
+    };
+
+    use crate::client::commands::{
+        select, Candidate, Client, CommandTracker, Incoming, Role, Tokens,
+    };
+
+    #[test]
+    fn test_select() {
+        let candidates = || {
+            [
+                ("Alice", Some("miners"), false, 0.0),
+                ("Bob", Some("miners"), true, 10.0),
+                ("Carol", Some("guards"), true, 20.0),
+                ("Dave", None, true, 30.0),
+            ]
+            .into_iter()
+            .zip(0..)
+            .map(|((username, group, idle, x), id)| Candidate {
+                id,
+                username,
+                group,
+                idle,
+                location: Location::new(x, 0.0, 0.0),
+            })
+        };
+
+        let ids = |selector: Selector| select(&selector, candidates());
+
+        assert_eq!(ids(Selector::default()), HashSet::from([0, 1, 2, 3]));
+
+        let selector = Selector {
+            groups: vec!["miners".to_string()],
+            names: vec!["Dave".to_string()],
+            ..Selector::default()
+        };
+        assert_eq!(ids(selector), HashSet::from([0, 1, 3]));
+
+        let selector = Selector {
+            idle: true,
+            nearest: Some(Nearest {
+                location: BlockLocation::new(22, 0, 0),
+                count: 2,
+            }),
+            ..Selector::default()
+        };
+        assert_eq!(ids(selector), HashSet::from([2, 3]));
+    }
+
+    #[test]
+    fn test_tokens() {
+        let open = Tokens {
+            control: Vec::new(),
+            read: Vec::new(),
+        };
+        assert_eq!(open.role(None), Some(Role::Control));
+
+        let tokens = Tokens {
+            control: vec!["secret".to_string()],
+            read: vec!["viewer".to_string()],
+        };
+        assert_eq!(tokens.role(Some("secret")), Some(Role::Control));
+        assert_eq!(tokens.role(Some("viewer")), Some(Role::Read));
+        assert_eq!(tokens.role(Some("secreT")), None);
+        assert_eq!(tokens.role(Some("secret2")), None);
+        assert_eq!(tokens.role(None), None);
+    }
+
+    #[test]
+    fn test_tracker() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let incoming = Incoming {
+            client: Client::new(0, Role::Control, tx),
+            command: Command {
+                id: 7,
+                path: "goto".to_string(),
+                data: serde_json::Value::Null,
+            },
+        };
+
+        let mut tracker = CommandTracker::default();
+        tracker.start(&incoming, vec![1, 2]);
+        assert!(tracker.is_active(incoming.id()));
+        tracker.stopped(incoming.id(), 1, Outcome::Finished);
+
+        // bot 1 is no longer working on it
+        tracker.stopped(incoming.id(), 1, Outcome::Cancelled);
+        tracker.stopped(incoming.id(), 2, Outcome::Cancelled);
+
+        let mut responses = Vec::new();
+        while let Ok(response) = rx.try_recv() {
+            responses.push(response);
+        }
+
+        assert_eq!(
+            responses,
+            [
+                Response::Ack(Ack {
+                    id: 7,
+                    bots: vec![1, 2]
+                }),
+                Response::Progress(Progress {
+                    id: 7,
+                    bot: 1,
+                    outcome: Outcome::Finished,
+                    remaining: 1
+                }),
+                Response::Progress(Progress {
+                    id: 7,
+                    bot: 2,
+                    outcome: Outcome::Cancelled,
+                    remaining: 0
+                }),
+                Response::Cancelled(Cancelled { id: 7 }),
+            ]
+        );
+        assert!(tracker.bots(incoming.id()).is_none());
+        assert!(!tracker.is_active(incoming.id()));
+    }
+}
@@ -0,0 +1,413 @@
+//! An interactive terminal dashboard of all bots (`--tui`). The dashboard
+//! is drawn from the game loop and does not know about the runner. The runner
+//! gives it a [`BotRow`] per bot and executes the [`DashboardAction`]s it
+//! returns.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{PrintStyledContent, StyledContent, Stylize},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+/// how often the dashboard is drawn
+const DRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// the number of tick durations the timing is averaged over
+const TICK_HISTORY: usize = 100;
+
+/// A bot as shown in the dashboard
+#[derive(Debug)]
+pub struct BotRow {
+    pub id: u32,
+    pub username: String,
+    pub server: String,
+    pub health: f32,
+    pub food: u8,
+    pub dimension: String,
+    pub location: String,
+    pub task: Option<String>,
+    pub disconnected: bool,
+}
+
+/// The login progress of the swarm
+#[derive(Debug, Default)]
+pub struct Logins {
+    pub pending: i64,
+    pub failed: u64,
+    /// the device-code prompts of logins waiting for the user
+    pub prompts: Vec<String>,
+}
+
+/// What the runner should do after input
+#[derive(Debug, Eq, PartialEq)]
+pub enum DashboardAction {
+    /// the user wants to quit
+    Quit,
+    /// a command (as if sent in chat without `#`) for the bot with `bot_id`
+    Command { bot_id: u32, command: String },
+}
+
+pub struct Dashboard {
+    /// the id of the selected bot
+    selected: Option<u32>,
+
+    /// the command being typed. [`None`] if not typing
+    input: Option<String>,
+
+    /// the durations of the most recent ticks
+    ticks: VecDeque<Duration>,
+
+    last_draw: Option<Instant>,
+
+    /// if [`Dashboard::start`] took over the terminal
+    started: bool,
+}
+
+/// restore the terminal to how it was before the dashboard started
+fn restore() {
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+}
+
+impl Dashboard {
+    /// Take over the terminal
+    pub fn start() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+
+        // so a panic is readable
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            hook(info);
+        }));
+
+        let mut dashboard = Self::new();
+        dashboard.started = true;
+        Ok(dashboard)
+    }
+
+    const fn new() -> Self {
+        Self {
+            selected: None,
+            input: None,
+            ticks: VecDeque::new(),
+            last_draw: None,
+            started: false,
+        }
+    }
+
+    /// the id of the selected bot
+    pub const fn selected(&self) -> Option<u32> {
+        self.selected
+    }
+
+    /// a game loop iteration took `duration`
+    pub fn record_tick(&mut self, duration: Duration) {
+        if self.ticks.len() == TICK_HISTORY {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back(duration);
+    }
+
+    /// Handle all key presses since the last call
+    pub fn handle_input(&mut self, rows: &[BotRow]) -> io::Result<Vec<DashboardAction>> {
+        let mut actions = Vec::new();
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                actions.extend(self.handle_key(key, rows));
+            }
+        }
+        Ok(actions)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, rows: &[BotRow]) -> Option<DashboardAction> {
+        if key.kind == KeyEventKind::Release {
+            return None;
+        }
+
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Some(DashboardAction::Quit);
+        }
+
+        if let Some(input) = &mut self.input {
+            match key.code {
+                KeyCode::Enter => {
+                    let command = std::mem::take(input);
+                    self.input = None;
+
+                    let command = command.trim().trim_start_matches('#').to_string();
+                    let bot_id = self.selected_idx(rows).map(|idx| rows[idx].id)?;
+                    if !command.is_empty() {
+                        return Some(DashboardAction::Command { bot_id, command });
+                    }
+                }
+                KeyCode::Esc => self.input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return Some(DashboardAction::Quit),
+            KeyCode::Up => self.select(rows, -1),
+            KeyCode::Down => self.select(rows, 1),
+            KeyCode::Enter | KeyCode::Char(':') => self.input = Some(String::new()),
+            _ => {}
+        }
+
+        None
+    }
+
+    /// the index of the selected bot in `rows`. The first bot is selected if
+    /// the selected bot is gone.
+    fn selected_idx(&self, rows: &[BotRow]) -> Option<usize> {
+        if rows.is_empty() {
+            return None;
+        }
+        let idx = self
+            .selected
+            .and_then(|id| rows.iter().position(|row| row.id == id));
+        Some(idx.unwrap_or(0))
+    }
+
+    /// move the selection by `by` rows
+    fn select(&mut self, rows: &[BotRow], by: isize) {
+        let Some(idx) = self.selected_idx(rows) else {
+            return;
+        };
+        let idx = idx.saturating_add_signed(by).min(rows.len() - 1);
+        self.selected = Some(rows[idx].id);
+    }
+
+    /// if enough time has passed since the dashboard was last drawn
+    pub fn should_draw(&self) -> bool {
+        self.last_draw
+            .map_or(true, |last| last.elapsed() >= DRAW_INTERVAL)
+    }
+
+    /// the average and maximum tick duration
+    fn tick_timing(&self) -> (Duration, Duration) {
+        let max = self.ticks.iter().max().copied().unwrap_or_default();
+        let count = u32::try_from(self.ticks.len()).unwrap_or(u32::MAX).max(1);
+        let avg = self.ticks.iter().sum::<Duration>() / count;
+        (avg, max)
+    }
+
+    /// Draw the dashboard. `chat` is the chat of the selected bot.
+    pub fn draw(
+        &mut self,
+        rows: &[BotRow],
+        chat: Option<&VecDeque<String>>,
+        logins: &Logins,
+    ) -> io::Result<()> {
+        self.last_draw = Some(Instant::now());
+
+        let (width, height) = terminal::size()?;
+        let height = usize::from(height);
+
+        let mut screen = Screen {
+            out: io::stdout().lock(),
+            width: usize::from(width),
+            y: 0,
+        };
+        queue!(screen.out, Clear(ClearType::All))?;
+
+        self.draw_header(&mut screen, rows, logins)?;
+
+        // the chat pane and footer take a third of the screen
+        let chat_height = (height / 3).max(4);
+        let table_height = height.saturating_sub(screen.y + chat_height + 2).max(1);
+
+        let selected = self.selected_idx(rows);
+        let start = selected
+            .unwrap_or(0)
+            .saturating_sub(table_height.saturating_sub(1));
+        for (idx, row) in rows.iter().enumerate().skip(start).take(table_height) {
+            let state = if row.disconnected {
+                "disconnected"
+            } else {
+                "online"
+            };
+            let text = format!(
+                "{} {:<5} {:<16} {:<22} {:>6.1} {:>4} {:<9} {:<28} {:<12} {}",
+                if Some(idx) == selected { ">" } else { " " },
+                row.id,
+                row.username,
+                row.server,
+                row.health,
+                row.food,
+                row.dimension,
+                row.location,
+                state,
+                row.task.as_deref().unwrap_or("-"),
+            );
+            if Some(idx) == selected {
+                screen.line(&text, Stylize::reverse)?;
+            } else {
+                screen.line(&text, Stylize::stylize)?;
+            }
+        }
+
+        screen.y = height.saturating_sub(chat_height + 2);
+        let name = selected.map_or("", |idx| rows[idx].username.as_str());
+        screen.line(&format!("chat ({name})"), Stylize::bold)?;
+        if let Some(chat) = chat {
+            let skip = chat.len().saturating_sub(chat_height);
+            for message in chat.iter().skip(skip) {
+                screen.line(message, Stylize::stylize)?;
+            }
+        }
+
+        screen.y = height.saturating_sub(1);
+        if let Some(input) = &self.input {
+            screen.line(&format!("{name}> {input}_"), Stylize::stylize)?;
+        } else {
+            screen.line(
+                "up/down select | enter send command | q quit",
+                Stylize::dark_grey,
+            )?;
+        }
+
+        screen.out.flush()
+    }
+
+    /// the swarm summary and the header of the bot table
+    fn draw_header(&self, screen: &mut Screen, rows: &[BotRow], logins: &Logins) -> io::Result<()> {
+        let servers = rows
+            .iter()
+            .map(|row| &row.server)
+            .collect::<HashSet<_>>()
+            .len();
+        let (avg, max) = self.tick_timing();
+        let last = self.ticks.back().copied().unwrap_or_default();
+
+        screen.line(
+            &format!(
+                "SwarmBot | {} bots on {servers} servers | tick {last:.1?} avg {avg:.1?} max {max:.1?}",
+                rows.len(),
+            ),
+            Stylize::bold,
+        )?;
+        screen.line(
+            &format!(
+                "logins: {} connected, {} pending, {} failed",
+                rows.len(),
+                logins.pending,
+                logins.failed
+            ),
+            Stylize::stylize,
+        )?;
+        for prompt in &logins.prompts {
+            screen.line(prompt, Stylize::yellow)?;
+        }
+        screen.line(
+            &format!(
+                "  {:<5} {:<16} {:<22} {:>6} {:>4} {:<9} {:<28} {:<12} {}",
+                "id", "name", "server", "health", "food", "dim", "location", "state", "task"
+            ),
+            Stylize::bold,
+        )
+    }
+}
+
+/// Writes lines to the terminal from top to bottom
+struct Screen {
+    out: io::StdoutLock<'static>,
+    width: usize,
+
+    /// the row the next line is written to
+    y: usize,
+}
+
+impl Screen {
+    /// write `text` truncated to the width of the terminal
+    fn line(
+        &mut self,
+        text: &str,
+        style: impl FnOnce(String) -> StyledContent<String>,
+    ) -> io::Result<()> {
+        let text: String = text.chars().take(self.width).collect();
+        let y = u16::try_from(self.y).unwrap_or(u16::MAX);
+        self.y += 1;
+        queue!(self.out, MoveTo(0, y), PrintStyledContent(style(text)))
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        if self.started {
+            restore();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::client::dashboard::{BotRow, Dashboard, DashboardAction};
+
+    fn row(id: u32) -> BotRow {
+        BotRow {
+            id,
+            username: format!("Bot{id:0>4}"),
+            server: "localhost:25565".to_string(),
+            health: 20.0,
+            food: 20,
+            dimension: "overworld".to_string(),
+            location: String::new(),
+            task: None,
+            disconnected: false,
+        }
+    }
+
+    fn press(dashboard: &mut Dashboard, code: KeyCode, rows: &[BotRow]) -> Option<DashboardAction> {
+        dashboard.handle_key(KeyEvent::new(code, KeyModifiers::NONE), rows)
+    }
+
+    #[test]
+    fn test_command() {
+        let rows = [row(3), row(5), row(8)];
+        let mut dashboard = Dashboard::new();
+
+        press(&mut dashboard, KeyCode::Down, &rows);
+        press(&mut dashboard, KeyCode::Down, &rows);
+        press(&mut dashboard, KeyCode::Down, &rows);
+        press(&mut dashboard, KeyCode::Up, &rows);
+        assert_eq!(dashboard.selected(), Some(5));
+
+        press(&mut dashboard, KeyCode::Enter, &rows);
+        for c in "#goto 1 2 3".chars() {
+            press(&mut dashboard, KeyCode::Char(c), &rows);
+        }
+
+        // q is typed and does not quit while typing
+        press(&mut dashboard, KeyCode::Char('q'), &rows);
+        press(&mut dashboard, KeyCode::Backspace, &rows);
+
+        assert_eq!(
+            press(&mut dashboard, KeyCode::Enter, &rows),
+            Some(DashboardAction::Command {
+                bot_id: 5,
+                command: "goto 1 2 3".to_string()
+            })
+        );
+        assert_eq!(
+            press(&mut dashboard, KeyCode::Char('q'), &rows),
+            Some(DashboardAction::Quit)
+        );
+    }
+}
@@ -1,8 +1,11 @@
 use std::collections::VecDeque;
 
+use interfaces::types::{BlockLocation, SimpleType};
+use tracing::debug;
+
 use crate::{
     client::{
-        pathfind::{context::MoveRecord, incremental::PathResult},
+        pathfind::{context::MoveRecord, incremental::PathResult, moves::CardinalDirection},
         physics::{speed::Speed, Line},
         state::{global::GlobalState, local::LocalState},
     },
@@ -53,6 +56,29 @@ pub enum Result {
     Finished,
 }
 
+/// Something in the way of the next node of a path which the path was planned
+/// to get past (see [`Follower::obstacle`])
+#[derive(Eq, PartialEq, Debug)]
+pub enum Obstacle {
+    /// a block which has to be mined
+    Block(BlockLocation),
+
+    /// there is nothing to walk onto in `direction`, so a block has to be
+    /// placed against the side of the block the bot is on (`against`)
+    Gap {
+        against: BlockLocation,
+        direction: CardinalDirection,
+    },
+
+    /// the next node is right above, so the bot has to jump and place a block
+    /// under itself until it stands at `y`
+    Pillar { y: i16 },
+
+    /// a closed door, trapdoor or fence gate which has to be right-clicked
+    /// open
+    Closed(BlockLocation),
+}
+
 /// Given a path the follower decides which moves (analogous to keys a real
 /// player would press) the bot should take. Currently the follower is totally
 /// legit---it interfaces with the [Physics] struct which only allows for moves
@@ -163,6 +189,78 @@ impl Follower {
         *self = other;
     }
 
+    /// the fraction of the path which has been followed. If the path is not
+    /// complete this is only up to where it was calculated.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn progress(&self) -> f32 {
+        1.0 - self.xs.len() as f32 / self.initial as f32
+    }
+
+    /// what has to be done before the bot can move to the next node (if
+    /// anything). Apart from opening doors this is only checked while the bot
+    /// is on the ground.
+    pub fn obstacle(&mut self, local: &LocalState, global: &GlobalState) -> Option<Obstacle> {
+        let to = BlockLocation::from(*self.xs.front()?);
+        let from = BlockLocation::from(local.physics.location());
+        if to == from {
+            return None;
+        }
+
+        let world = &global.blocks;
+        let closed = |loc: BlockLocation| {
+            world
+                .get_block_exact(loc)
+                .is_some_and(|state| state.is_closed())
+        };
+
+        // trapdoors can be opened while climbing a ladder
+        if let Some(&door) = [to.above(), to].iter().find(|&&loc| closed(loc)) {
+            self.ticks = 0;
+            return Some(Obstacle::Closed(door));
+        }
+
+        if !local.physics.on_ground() {
+            return None;
+        }
+
+        let solid = |loc: BlockLocation| {
+            world
+                .get_block_exact(loc)
+                .is_some_and(|state| state.simple_type() == SimpleType::Solid)
+        };
+        let passable =
+            |loc: BlockLocation| world.get_block_simple(loc) == Some(SimpleType::WalkThrough);
+
+        // the space we move through (including above our head if we jump)
+        let mut space = vec![to.above(), to];
+        if to.y > from.y {
+            space.insert(0, from.above().above());
+        }
+
+        let obstacle = if let Some(&block) = space.iter().find(|&&loc| solid(loc)) {
+            Obstacle::Block(block)
+        } else if to == from.above()
+            && passable(from)
+            && !world.is_climbable(from)
+            && solid(from.below())
+        {
+            Obstacle::Pillar { y: to.y }
+        } else if let Some(direction) = CardinalDirection::between(from, to)
+            .filter(|_| passable(to) && passable(to.below()) && solid(from.below()))
+        {
+            Obstacle::Gap {
+                against: from.below(),
+                direction,
+            }
+        } else {
+            return None;
+        };
+
+        // the bot is not stuck while it deals with the obstacle
+        self.ticks = 0;
+        Some(obstacle)
+    }
+
     /// go to the next point on the path
     fn next(&mut self) {
         self.xs.pop_front();
@@ -236,7 +334,7 @@ impl Follower {
 
         // more than 1.5 seconds on same block => failed
         if self.ticks >= MAX_PROGRESS_TICKS {
-            println!(
+            debug!(
                 "follower failed (time) for {} -> {:?}",
                 local.physics.location(),
                 self.xs.front()
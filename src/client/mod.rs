@@ -1,11 +1,14 @@
 //! Everything related to a client
 pub mod bot;
-mod commands;
+pub mod commands;
+mod dashboard;
 mod follow;
 pub mod pathfind;
 pub mod physics;
 pub mod processor;
+mod query;
 pub mod runner;
 pub mod state;
+mod subscriptions;
 mod tasks;
 mod timing;
@@ -1,12 +1,17 @@
 //! global context information that is applicable to all bots
 use std::hash::{Hash, Hasher};
 
-use interfaces::types::BlockLocation;
+use interfaces::types::{block_data::BlockData, BlockLocation};
+use serde::Deserialize;
 
-use crate::{client::pathfind::incremental::Node, storage::blocks::WorldBlocks};
+use crate::{
+    client::{pathfind::incremental::Node, physics::tools::Tool},
+    storage::blocks::WorldBlocks,
+};
 
 /// The costs of doing everything (used for pathfinding)
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Costs {
     /// cost to walk one block
     pub block_walk: f64,
@@ -34,9 +39,14 @@ pub struct Costs {
 
     /// the cost of falling (without taking damage)
     pub fall: f64,
+
+    /// cost to right-click a door, trapdoor or fence gate open
+    pub open: f64,
 }
 
 /// The configuration for finding paths
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PathConfig {
     /// The [`Costs`] config
     pub costs: Costs,
@@ -44,20 +54,27 @@ pub struct PathConfig {
     pub parkour: bool,
 }
 
+impl Default for Costs {
+    fn default() -> Self {
+        Self {
+            block_walk: 1.0,
+            block_parkour: 1.5,
+            mine_unrelated: 20.0,
+            ascend: 1.0,
+            no_breathe_mult: 3.0,
+            fall: 1.0,
+            place_unrelated: 20.0,
+            mine_required: 1.0,
+            place_required: 1.0,
+            open: 2.0,
+        }
+    }
+}
+
 impl Default for PathConfig {
     fn default() -> Self {
         Self {
-            costs: Costs {
-                block_walk: 1.0,
-                block_parkour: 1.5,
-                mine_unrelated: 20.0,
-                ascend: 1.0,
-                no_breathe_mult: 3.0,
-                fall: 1.0,
-                place_unrelated: 20.0,
-                mine_required: 1.0,
-                place_required: 1.0,
-            },
+            costs: Costs::default(),
             parkour: true,
         }
     }
@@ -71,6 +88,12 @@ pub struct GlobalContext<'a> {
 
     /// the state of the world blocks
     pub world: &'a WorldBlocks,
+
+    /// the block data (i.e., how long blocks take to mine)
+    pub block_data: &'a BlockData,
+
+    /// the tools in the hotbar of the bot (used for how long mining takes)
+    pub tools: &'a [Tool],
 }
 
 /// A node which represents a movement
@@ -81,6 +104,10 @@ pub struct MoveNode {
 
     /// The number of 'throwaway' blocks we have, i.e., for bridging
     pub throwaway_block_count: usize,
+
+    /// if we stand on a block placed on the way here (which is not in the
+    /// world yet)
+    pub on_placed: bool,
 }
 
 impl MoveNode {
@@ -89,6 +116,7 @@ impl MoveNode {
         Self {
             location,
             throwaway_block_count: 0,
+            on_placed: false,
         }
     }
 
@@ -103,6 +131,7 @@ impl Clone for MoveNode {
         Self {
             location: self.location,
             throwaway_block_count: self.throwaway_block_count,
+            on_placed: self.on_placed,
         }
     }
 }
@@ -114,12 +143,13 @@ impl Node for MoveNode {
         let &Self {
             location,
             throwaway_block_count,
-            ..
+            on_placed,
         } = self;
 
         let state = MoveState {
             location,
             throwaway_block_count,
+            on_placed,
         };
 
         Self::Record { state }
@@ -130,6 +160,7 @@ impl Node for MoveNode {
 pub struct MoveState {
     pub location: BlockLocation,
     pub throwaway_block_count: usize,
+    pub on_placed: bool,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -1,5 +1,7 @@
 use std::time::Instant;
 
+use interfaces::types::BlockLocation;
+
 use crate::client::{
     pathfind::{
         context::{GlobalContext, MoveNode, MoveRecord},
@@ -28,17 +30,23 @@ pub struct PlayerProblem<H: Heuristic<MoveNode>, G: GoalCheck<MoveNode>> {
     a_star: AStar<MoveNode>,
     heuristic: H,
     goal_checker: G,
+
+    /// where the search starts if it has not yet. The blocks the bot can place
+    /// are only known once it does.
+    start: Option<BlockLocation>,
 }
 
 impl<H: Heuristic<MoveNode> + Send + Sync, G: GoalCheck<MoveNode> + Send + Sync>
     PlayerProblem<H, G>
 {
     pub fn new(start: MoveNode, heuristic: H, goal_checker: G) -> Self {
+        let location = start.location;
         let a_star = AStar::new(start);
         Self {
             a_star,
             heuristic,
             goal_checker,
+            start: Some(location),
         }
     }
 
@@ -67,12 +75,21 @@ impl<H: Heuristic<MoveNode> + Send + Sync, G: GoalCheck<MoveNode> + Send + Sync>
     fn iterate_until(
         &mut self,
         end_at: Instant,
-        _: &mut LocalState,
+        local: &mut LocalState,
         global: &GlobalState,
     ) -> Increment<PathResult<MoveRecord>> {
+        if let Some(start) = self.start.take() {
+            let mut start = MoveNode::simple(start);
+            start.throwaway_block_count = local.inventory.throwaway_count();
+            self.a_star = AStar::new(start);
+        }
+
+        let tools = local.inventory.hotbar_tools();
         let ctx = GlobalContext {
-            path_config: &global.travel_config,
+            path_config: &local.config.path,
             world: &global.blocks,
+            block_data: &global.block_data,
+            tools: &tools,
         };
         let progressor = GenericProgressor { ctx };
         self.a_star
@@ -80,6 +97,6 @@ impl<H: Heuristic<MoveNode> + Send + Sync, G: GoalCheck<MoveNode> + Send + Sync>
     }
 
     fn recalc(&mut self, context: Self::Node) {
-        self.a_star = AStar::new(context);
+        self.start = Some(context.location);
     }
 }
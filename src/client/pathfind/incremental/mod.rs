@@ -9,6 +9,8 @@ use std::{
     time::Instant,
 };
 
+use tracing::debug;
+
 use crate::client::{
     pathfind::{
         traits::{GoalCheck, Heuristic, Progression, Progressor},
@@ -195,7 +197,7 @@ impl<T: Node> AStar<T> {
             }
             let g_score = state.g_scores[&id];
             if g_score > MIN_DIST {
-                println!("larger than min dist");
+                debug!("larger than min dist");
                 let path = reconstruct_path(&state.idx_to_record, id, &state.parent_map);
                 return Increment::Finished(PathResult::incomplete(path));
             }
@@ -222,7 +224,7 @@ impl<T: Node> AStar<T> {
                 let dur = &mut state.total_duration_ms;
                 *dur += iter_duration.as_millis();
                 return if *dur > state.max_duration_ms {
-                    println!("reached maxed duration");
+                    debug!("reached maxed duration");
                     return self.select_best();
                 } else {
                     Increment::InProgress
@@ -319,7 +321,7 @@ impl<T: Node> AStar<T> {
                 state.open_set.push(heap_node);
             }
         } else {
-            println!(
+            debug!(
                 "no more nodes iterated through {}",
                 state.idx_to_record.len()
             );
@@ -1,5 +1,5 @@
 use interfaces::types::{
-    BlockLocation, Change, SimpleType,
+    BlockKind, BlockLocation, BlockState, Change, SimpleType,
     SimpleType::{Avoid, Solid, WalkThrough, Water},
 };
 
@@ -14,6 +14,10 @@ use crate::{
 
 pub const MAX_FALL: i32 = 3;
 
+/// about how many ticks it takes to walk a block. Used to weigh the time it
+/// takes to mine a block against walking.
+const WALK_TICKS: f64 = 5.0;
+
 mod centered_arr;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -46,6 +50,7 @@ impl Movements {
             ($block_loc:expr) => {{
                 let mut node = MoveNode::from(&on);
                 node.location = $block_loc;
+                node.on_placed = false;
                 node
             }};
         }
@@ -128,9 +133,16 @@ impl Movements {
         }
 
         let above = get_block!(x, y + 2, z).unwrap();
-        let floor = get_block!(x, y - 1, z).unwrap();
+        let floor = if on.on_placed {
+            Solid
+        } else {
+            get_block!(x, y - 1, z).unwrap()
+        };
         let feet = get_block!(x, y, z).unwrap();
 
+        // in a ladder or vine
+        let climbing = w.is_climbable(on.location);
+
         if above == Water || head == Water && above == WalkThrough {
             res.push(Neighbor {
                 value: wrap!(BlockLocation::new(x, y + 1, z)),
@@ -145,7 +157,27 @@ impl Movements {
             });
         }
 
-        let can_micro_jump = above == WalkThrough && (floor == Solid || feet == Water);
+        // climbing up and down ladders and vines (opening trapdoors on the way)
+        if climbing {
+            let head_cost = open_cost(BlockLocation::new(x, y + 1, z), ctx);
+            let above_cost = open_cost(BlockLocation::new(x, y + 2, z), ctx);
+            if let (Some(head_cost), Some(above_cost)) = (head_cost, above_cost) {
+                res.push(Neighbor {
+                    value: wrap!(BlockLocation::new(x, y + 1, z)),
+                    cost: ctx.path_config.costs.ascend + head_cost + above_cost,
+                });
+            }
+        }
+
+        if w.is_climbable(BlockLocation::new(x, y - 1, z)) {
+            res.push(Neighbor {
+                value: wrap!(BlockLocation::new(x, y - 1, z)),
+                cost: ctx.path_config.costs.ascend,
+            });
+        }
+
+        // we can also get onto a ledge from the top of a ladder
+        let can_micro_jump = above == WalkThrough && (floor == Solid || feet == Water || climbing);
 
         if can_micro_jump {
             // ascending adjacent
@@ -171,8 +203,79 @@ impl Movements {
             }
         }
 
+        let costs = &ctx.path_config.costs;
+        let has_throwaway = on.throwaway_block_count > 0;
+
+        // mining or opening our way through adjacent blocks
+        for (idx, direction) in CardinalDirection::ALL.iter().enumerate() {
+            let Change { dx, dz, .. } = direction.unit_change();
+
+            if can_move_adj_noplace[idx] || get_block!(x + dx, y - 1, z + dz) != Some(Solid) {
+                continue;
+            }
+
+            let legs = BlockLocation::new(x + dx, y, z + dz);
+            let head = BlockLocation::new(x + dx, y + 1, z + dz);
+            let legs_cost = clear_cost(legs, ctx);
+            let head_cost = clear_cost(head, ctx);
+
+            if let (Some(legs_cost), Some(head_cost)) = (legs_cost, head_cost) {
+                res.push(Neighbor {
+                    value: wrap!(legs),
+                    cost: costs.block_walk.mul_add(multiplier, legs_cost + head_cost),
+                });
+            }
+        }
+
+        // bridging across a gap by placing a block against the one we are on
+        if has_throwaway && floor == Solid {
+            for (idx, direction) in CardinalDirection::ALL.iter().enumerate() {
+                let Change { dx, dz, .. } = direction.unit_change();
+
+                let passable = adj_legs[idx] == WalkThrough && adj_head[idx] == WalkThrough;
+                if passable && get_block!(x + dx, y - 1, z + dz) == Some(WalkThrough) {
+                    let mut node = wrap!(BlockLocation::new(x + dx, y, z + dz));
+                    node.throwaway_block_count -= 1;
+                    node.on_placed = true;
+                    res.push(Neighbor {
+                        value: node,
+                        cost: costs.block_walk.mul_add(multiplier, costs.place_unrelated),
+                    });
+                }
+            }
+        }
+
+        // pillaring up by placing a block where we stand
+        if has_throwaway
+            && floor == Solid
+            && feet == WalkThrough
+            && head == WalkThrough
+            && !climbing
+        {
+            if let Some(above_cost) = clear_cost(BlockLocation::new(x, y + 2, z), ctx) {
+                let mut node = wrap!(BlockLocation::new(x, y + 1, z));
+                node.throwaway_block_count -= 1;
+                node.on_placed = true;
+                res.push(Neighbor {
+                    value: node,
+                    cost: costs.ascend + costs.place_unrelated + above_cost,
+                });
+            }
+        }
+
+        // digging down into the block we are on
+        let below_floor = get_block!(x, y - 2, z);
+        if floor == Solid && below_floor == Some(Solid) && !on.on_placed {
+            if let Some(floor_cost) = mine_cost(BlockLocation::new(x, y - 1, z), ctx) {
+                res.push(Neighbor {
+                    value: wrap!(BlockLocation::new(x, y - 1, z)),
+                    cost: costs.fall + floor_cost,
+                });
+            }
+        }
+
         // can full multi-block jump (i.e., jumping on bedrock)
-        let can_jump = above == WalkThrough && floor != Water;
+        let can_jump = ctx.path_config.parkour && above == WalkThrough && floor != Water;
 
         if can_jump {
             // we can jump in a 3 block radius
@@ -281,6 +384,84 @@ impl Movements {
     }
 }
 
+/// the cost of making `location` passable: [`mine_cost`] if it is solid and
+/// [`open_cost`] otherwise. [`None`] if it cannot be done.
+fn clear_cost(location: BlockLocation, ctx: &GlobalContext) -> Option<f64> {
+    match ctx.world.get_block_simple(location)? {
+        Solid => mine_cost(location, ctx),
+        _ => open_cost(location, ctx),
+    }
+}
+
+/// the cost of walking through `location` without mining: nothing if it is
+/// passable and the cost of right-clicking it if it is a closed door, trapdoor
+/// or fence gate. [`None`] otherwise (this includes iron doors).
+fn open_cost(location: BlockLocation, ctx: &GlobalContext) -> Option<f64> {
+    match ctx.world.get_block_simple(location)? {
+        WalkThrough => Some(0.0),
+        Avoid => ctx
+            .world
+            .get_block_exact(location)
+            .filter(BlockState::is_closed)
+            .map(|_| ctx.path_config.costs.open),
+        Solid | Water => None,
+    }
+}
+
+/// the cost of mining the block at `location` with the best tool in the
+/// hotbar. [`None`] if it should not be mined, which is if it is not known,
+/// cannot be broken (i.e., bedrock), would fall (sand and gravel) or is next
+/// to something we do not want to let in (i.e., lava or water).
+#[allow(clippy::cast_precision_loss)]
+fn mine_cost(location: BlockLocation, ctx: &GlobalContext) -> Option<f64> {
+    let world = ctx.world;
+    let kind = world.get_block_kind(location)?;
+
+    let breakable = kind
+        .hardness(ctx.block_data)
+        .is_some_and(|hardness| hardness >= 0.0);
+    if !breakable || !kind.mineable(ctx.block_data) {
+        return None;
+    }
+
+    let falls =
+        |loc: BlockLocation| matches!(world.get_block_kind(loc), Some(kind) if is_falling(kind));
+    if falls(location) || falls(location.above()) {
+        return None;
+    }
+
+    let BlockLocation { x, y, z } = location;
+    let adjacent = [
+        BlockLocation::new(x + 1, y, z),
+        BlockLocation::new(x - 1, y, z),
+        BlockLocation::new(x, y + 1, z),
+        BlockLocation::new(x, y - 1, z),
+        BlockLocation::new(x, y, z + 1),
+        BlockLocation::new(x, y, z - 1),
+    ];
+    let flows_in = adjacent
+        .into_iter()
+        .any(|loc| matches!(world.get_block_simple(loc), Some(Water | Avoid) | None));
+    if flows_in {
+        return None;
+    }
+
+    let ticks = ctx
+        .tools
+        .iter()
+        .map(|tool| tool.wait_time(kind, false, true, ctx.block_data))
+        .min()?;
+
+    let costs = &ctx.path_config.costs;
+    Some((ticks as f64 / WALK_TICKS).mul_add(costs.block_walk, costs.mine_unrelated))
+}
+
+/// if the block falls when there is nothing under it
+const fn is_falling(kind: BlockKind) -> bool {
+    // sand and gravel
+    matches!(kind.0, 12 | 13)
+}
+
 fn drop_y(start: BlockLocation, world: &WorldBlocks) -> Option<i16> {
     let BlockLocation { x, y: init_y, z } = start;
 
@@ -312,7 +493,7 @@ fn drop_y(start: BlockLocation, world: &WorldBlocks) -> Option<i16> {
     None
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum CardinalDirection {
     North,
     South,
@@ -328,6 +509,14 @@ impl CardinalDirection {
 }
 
 impl CardinalDirection {
+    /// the direction from `from` to `to` if `to` is next to it at the same
+    /// height
+    pub fn between(from: BlockLocation, to: BlockLocation) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|&direction| from + BlockLocation::from(direction.unit_change()) == to)
+    }
+
     pub fn unit_change(self) -> Change {
         match self {
             Self::North => Change::new(1, 0, 0),
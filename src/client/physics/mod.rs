@@ -4,6 +4,7 @@ use float_ord::FloatOrd;
 use interfaces::types::{BlockApprox, BlockKind, BlockLocation, BlockState, SimpleType};
 use itertools::Itertools;
 use num::traits::Pow;
+use tracing::{debug, warn};
 
 use crate::{
     client::{physics::speed::Speed, state::local::inventory::PlayerInventory},
@@ -26,6 +27,14 @@ const PLAYER_WIDTH_2: f64 = 0.6 / 2.0; // + 0.001;
 const PLAYER_HEIGHT: f64 = 1.79999;
 const PLAYER_HEIGHT_Y: Displacement = Displacement::new(0., PLAYER_HEIGHT, 0.);
 
+/// the fastest a player moves horizontally or down while climbing (i.e., a
+/// ladder)
+const CLIMB_SPEED: f64 = 0.15;
+
+/// how fast a player climbs up. The server sets the velocity to 0.2 and then
+/// gravity and drag are applied.
+const CLIMB_UP_SPEED: f64 = (0.2 - ACC_G) * 0.98;
+
 const UNIT_Y: Displacement = Displacement::new(0., 1., 0.);
 const EPSILON_Y: Displacement = Displacement::new(0., 0.001, 0.);
 
@@ -77,6 +86,8 @@ struct MovementState {
     y_vel: f64,
     slip: f64,
     falling: bool,
+    /// walked into a block (which climbs if the player is on a ladder)
+    against_block: bool,
 }
 
 impl Default for MovementState {
@@ -86,6 +97,7 @@ impl Default for MovementState {
             y_vel: 0.0,
             slip: BlockKind::DEFAULT_SLIP,
             falling: false,
+            against_block: false,
         }
     }
 }
@@ -282,15 +294,23 @@ impl Physics {
             let against = place.location;
             let actual_loc = against + place.face.unit_location();
 
-            if let Some(current) = inventory.current() {
-                world.set_block(
-                    actual_loc,
-                    BlockState::from(current.kind.id(), current.damage),
-                );
-            } else {
-                eprintln!("tried to place air");
-                self.pending.place = None;
-            };
+            // right-clicking a door, trapdoor or fence gate opens it, so
+            // nothing is placed (and nothing has to be in the hand)
+            let opens = world
+                .get_block_exact(against)
+                .is_some_and(|state| state.kind().openable());
+
+            if !opens {
+                if let Some(current) = inventory.current() {
+                    world.set_block(
+                        actual_loc,
+                        BlockState::from(current.kind.id(), current.damage),
+                    );
+                } else {
+                    warn!("tried to place air");
+                    self.pending.place = None;
+                }
+            }
         }
 
         let in_block_loc = BlockLocation::from(self.location);
@@ -299,7 +319,7 @@ impl Physics {
 
         #[allow(clippy::use_debug)]
         if in_block {
-            println!(
+            debug!(
                 "was in block at {in_block_loc} of type {:?}",
                 world.get_block(in_block_loc)
             );
@@ -318,6 +338,8 @@ impl Physics {
         let mut falling =
             below_block_loc.y == current_block_loc.y || Self::cross_section_empty(below_loc, world);
 
+        let climbing = world.is_climbable(BlockLocation::from(self.location));
+
         let mut slip = match world.get_block(below_block_loc) {
             Some(BlockApprox::Realized(block)) => block.kind().slip(),
             // we might be on the edge of a block
@@ -394,6 +416,29 @@ impl Physics {
                 // we can't go down if there is a block below us.
                 res.max(0.0)
             }
+        } else if climbing {
+            // https://www.mcpk.wiki/wiki/Ladders_and_Vines
+            if falling {
+                slip = 1.0;
+            }
+
+            for i in 0..2 {
+                let speed = if falling {
+                    air_speed(prev_speeds[i], prev_slip, move_mults[i])
+                } else {
+                    ground_speed(prev_speeds[i], prev_slip, move_mults[i], effect_mult, slip)
+                };
+                speeds[i] = speed.clamp(-CLIMB_SPEED, CLIMB_SPEED);
+            }
+
+            if self.pending.jump || self.prev.against_block {
+                falling = true;
+                CLIMB_UP_SPEED
+            } else if falling {
+                ver_speed(self.prev.y_vel).max(-CLIMB_SPEED)
+            } else {
+                0.0
+            }
         } else if falling {
             // when falling the slip of air is 1.0
             slip = 1.0;
@@ -447,7 +492,7 @@ impl Physics {
 
         let prev_loc = self.location;
 
-        {
+        let against_block = {
             let mut new_loc = new_loc_first;
             new_loc.x += speeds[0];
             new_loc.z += speeds[1];
@@ -502,12 +547,8 @@ impl Physics {
                     leg_block == Some(SimpleType::Water) || head_block == Some(SimpleType::Water);
             }
 
-            // let leg_kind = world.get_block_kind(legs);
-            // if leg_kind == Some(BlockKind::LADDER) {
-            //     // yea this is jank
-            //     self.in_water = true;
-            // }
-        }
+            against_block
+        };
 
         new_loc_first.x += speeds[0];
         new_loc_first.z += speeds[1];
@@ -525,6 +566,7 @@ impl Physics {
             y_vel,
             slip,
             falling,
+            against_block,
         };
 
         actions
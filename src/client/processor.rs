@@ -1,13 +1,21 @@
-use interfaces::types::{BlockLocation, BlockState, ChunkLocation};
+use interfaces::{
+    events::Event,
+    types::{BlockApprox, BlockLocation, BlockState, ChunkLocation},
+};
+use tracing::{debug, info, warn};
 
 use crate::{
     client::{
-        bot::{process_command, ActionState},
+        bot::{process_command, ActionState, Priority},
+        query,
         state::{
             global::{world_players::Player, GlobalState},
             local::{inventory::ItemStack, LocalState},
         },
-        tasks::eat::EatTask,
+        tasks::{
+            behavior::{self, BehaviorTree},
+            eat::EatTask,
+        },
     },
     protocol::InterfaceOut,
     storage::{chunk::Column, entities::EntityKind},
@@ -21,6 +29,7 @@ pub trait InterfaceIn {
     fn on_death(&mut self);
     fn on_update_health(&mut self, health: f32, food: u8);
     fn on_dimension_change(&mut self, dimension: Dimension);
+    fn on_time_update(&mut self, time_of_day: i64);
     fn on_join(&mut self);
     fn on_move(&mut self, location: Location);
     fn on_recv_chunk(&mut self, location: ChunkLocation, column: Column, new: bool);
@@ -59,7 +68,7 @@ impl<'a, I: InterfaceOut> SimpleInterfaceIn<'a, I> {
 
 impl<'a, I: InterfaceOut> SimpleInterfaceIn<'a, I> {
     pub fn process_player_message(&mut self, msg: PlayerMessage) {
-        println!("processing player message {msg:?}");
+        debug!("processing player message {msg:?}");
         if let Some(cmd) = msg.into_cmd() {
             let name = cmd.command;
             let args_str: Vec<&str> = cmd.args.iter().map(String::as_str).collect();
@@ -71,7 +80,7 @@ impl<'a, I: InterfaceOut> SimpleInterfaceIn<'a, I> {
                 self.actions,
                 self.out,
             ) {
-                println!("could not process command. Reason: {err:?}");
+                warn!("could not process command. Reason: {err:?}");
             }
         }
     }
@@ -79,12 +88,19 @@ impl<'a, I: InterfaceOut> SimpleInterfaceIn<'a, I> {
 
 impl<'a, I: InterfaceOut> InterfaceIn for SimpleInterfaceIn<'a, I> {
     fn on_chat(&mut self, message: Chat) {
-        println!("{}", message.clone().colorize());
+        let text = message.plain();
+        if self.local.config.chat.print {
+            info!(target: "chat", "{text}");
+        }
+        self.local.record_chat(text.clone());
+        self.local.emit(Event::Chat { message: text });
 
-        if let Some(msg) = message.player_message() {
-            self.process_player_message(msg);
-        } else if let Some(msg) = message.player_dm() {
-            self.process_player_message(msg);
+        let msg = message.player_message().or_else(|| message.player_dm());
+
+        if let Some(msg) = msg {
+            if self.local.config.chat.allows(&msg.player) {
+                self.process_player_message(msg);
+            }
         }
     }
 
@@ -97,25 +113,34 @@ impl<'a, I: InterfaceOut> InterfaceIn for SimpleInterfaceIn<'a, I> {
     }
 
     fn on_death(&mut self) {
-        self.actions.clear();
+        self.local.emit(Event::Death);
+        // the behavior carries on after respawning
+        self.actions.clear_except(Priority::Behavior);
         self.out.respawn();
     }
 
     fn on_update_health(&mut self, health: f32, food: u8) {
+        #[allow(clippy::float_cmp)]
+        if health != self.local.health || food != self.local.food {
+            self.local.emit(Event::Health { health, food });
+        }
+
         self.local.health = health;
         self.local.food = food;
 
-        println!("updated health {health} food is {food}");
+        debug!("updated health {health} food is {food}");
 
-        // we should probably eat something
-        if food < 10 {
+        // we should probably eat something. Eating interrupts whatever the bot
+        // is doing until it is done.
+        if food < 10 && !self.actions.has(Priority::Urgent) {
             // if we could switch to food
             if self
                 .local
                 .inventory
                 .switch_food(&self.global.block_data, self.out)
             {
-                self.actions.schedule(EatTask::default());
+                self.actions
+                    .schedule_with(EatTask::default(), Priority::Urgent);
             }
         }
     }
@@ -124,13 +149,31 @@ impl<'a, I: InterfaceOut> InterfaceIn for SimpleInterfaceIn<'a, I> {
         self.local.dimension = dimension;
     }
 
+    fn on_time_update(&mut self, time_of_day: i64) {
+        // the time of day is negative if the daylight cycle is stopped
+        self.local.time_of_day = time_of_day.abs() % 24_000;
+    }
+
     fn on_join(&mut self) {
+        let username = self.local.info.username.clone();
+        self.local.emit(Event::Joined { username });
+
         // always start with slot 0
         self.out.change_slot(0);
+
+        // the behavior runs whenever the bot has nothing else to do
+        if let Some(name) = &self.local.config.behavior {
+            match behavior::load(name) {
+                Ok(root) => self
+                    .actions
+                    .schedule_with(BehaviorTree::new(root), Priority::Behavior),
+                Err(err) => warn!("could not start behavior {name}: {err:#}"),
+            }
+        }
     }
 
     fn on_move(&mut self, location: Location) {
-        println!("moved {} -> {}", self.local.physics.location(), location);
+        debug!("moved {} -> {}", self.local.physics.location(), location);
         self.local.physics.teleport(location);
     }
 
@@ -149,6 +192,11 @@ impl<'a, I: InterfaceOut> InterfaceIn for SimpleInterfaceIn<'a, I> {
     }
 
     fn on_block_change(&mut self, location: BlockLocation, state: BlockState) {
+        // every bot near the block is told about the change
+        if self.global.blocks.get_block_exact(location) != Some(state) {
+            let block = query::block_info(Some(BlockApprox::Realized(state)));
+            self.local.emit(Event::BlockChange { location, block });
+        }
         self.global.blocks.set_block(location, state);
     }
 
@@ -163,17 +211,50 @@ impl<'a, I: InterfaceOut> InterfaceIn for SimpleInterfaceIn<'a, I> {
     }
 
     fn on_player_join(&mut self, uuid: u128, name: String) {
+        // every bot on the server is told about the player
+        if self.global.players.by_uuid(uuid).is_some() {
+            return;
+        }
+
+        self.local.emit(Event::PlayerJoin {
+            name: name.clone(),
+            uuid: format!("{uuid:032x}"),
+        });
         self.global.players.add(Player { name, uuid });
     }
 
     fn on_player_leave(&mut self, uuid: u128) {
-        self.global.players.remove(uuid);
+        let name = self
+            .global
+            .players
+            .by_uuid(uuid)
+            .map(|player| player.name.clone());
+
+        if self.global.players.remove(uuid) {
+            self.local.emit(Event::PlayerLeave {
+                name,
+                uuid: format!("{uuid:032x}"),
+            });
+        }
     }
 
     fn on_disconnect(&mut self, reason: &str) {
-        println!("disconnecting because {reason}");
+        warn!("disconnecting because {reason}");
+        self.local.emit(Event::Disconnected {
+            reason: reason.to_string(),
+        });
         self.local.disconnected = true;
     }
 
-    fn on_socket_close(&mut self) {}
+    fn on_socket_close(&mut self) {
+        if self.local.disconnected {
+            return;
+        }
+
+        warn!("connection closed");
+        self.local.emit(Event::Disconnected {
+            reason: "connection closed".to_string(),
+        });
+        self.local.disconnected = true;
+    }
 }
@@ -0,0 +1,175 @@
+//! Answers to the read-only websocket queries in [`interfaces::query`]
+
+use interfaces::{
+    query::{BlockInfo, BlockRegion, BotInfo, EntityInfo, ItemInfo, PlayerInfo, MAX_BLOCKS},
+    types::{BlockApprox, BlockLocation, Location},
+    ErrorKind,
+};
+
+use crate::{
+    client::{
+        bot::ActionState,
+        commands::{Client, CommandError},
+        state::{global::GlobalState, local::LocalState},
+    },
+    metrics::short_type_name,
+    storage::entities::EntityKind,
+};
+
+/// the state of a bot. `client` is the client asking.
+pub fn bot(local: &LocalState, actions: &ActionState, server: String, client: &Client) -> BotInfo {
+    let inventory = local
+        .inventory
+        .items()
+        .map(|(slot, stack)| ItemInfo {
+            slot,
+            id: stack.kind.id(),
+            count: stack.count,
+            damage: stack.damage,
+        })
+        .collect();
+
+    BotInfo {
+        id: local.bot_id,
+        username: local.info.username.clone(),
+        server,
+        group: local.config.group.clone(),
+        health: local.health,
+        food: local.food,
+        dimension: local.dimension,
+        location: local.physics.location(),
+        on_ground: local.physics.on_ground(),
+        task: actions.task_name().map(short_type_name),
+        activity: actions.describe(),
+        command: actions.command().and_then(|command| command.id_for(client)),
+        selected_slot: local.inventory.selected(),
+        inventory,
+    }
+}
+
+/// the block at `location`
+pub fn block(global: &GlobalState, location: BlockLocation) -> BlockInfo {
+    block_info(global.blocks.get_block(location))
+}
+
+/// a block as far as the swarm knows it
+pub fn block_info(block: Option<BlockApprox>) -> BlockInfo {
+    match block {
+        None => BlockInfo::Unknown,
+        Some(BlockApprox::Realized(state)) => BlockInfo::Known {
+            id: state.id(),
+            metadata: state.metadata(),
+        },
+        Some(BlockApprox::Estimate(simple)) => BlockInfo::Estimate { simple },
+    }
+}
+
+/// the blocks between `from` and `to` (inclusive)
+pub fn blocks(
+    global: &GlobalState,
+    from: BlockLocation,
+    to: BlockLocation,
+) -> Result<BlockRegion, CommandError> {
+    let min = BlockLocation::new(from.x.min(to.x), from.y.min(to.y), from.z.min(to.z));
+    let max = BlockLocation::new(from.x.max(to.x), from.y.max(to.y), from.z.max(to.z));
+
+    let len = |min: i64, max: i64| usize::try_from(max - min + 1).unwrap_or(usize::MAX);
+    let volume = len(min.x.into(), max.x.into())
+        .saturating_mul(len(min.y.into(), max.y.into()))
+        .saturating_mul(len(min.z.into(), max.z.into()));
+
+    if volume > MAX_BLOCKS {
+        return Err(CommandError::new(
+            ErrorKind::InvalidData,
+            format!("{volume} blocks requested but at most {MAX_BLOCKS} can be queried at once"),
+        ));
+    }
+
+    let mut blocks = Vec::with_capacity(volume);
+    for y in min.y..=max.y {
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                blocks.push(block(global, BlockLocation::new(x, y, z)));
+            }
+        }
+    }
+
+    Ok(BlockRegion {
+        from: min,
+        to: max,
+        blocks,
+    })
+}
+
+/// the entities within `radius` of `location`
+pub fn entities(global: &GlobalState, location: Location, radius: f64) -> Vec<EntityInfo> {
+    global
+        .entities
+        .iter()
+        .filter(|(_, entity)| entity.location.dist2(location) <= radius * radius)
+        .map(|(&id, entity)| EntityInfo {
+            id,
+            location: entity.location,
+            player: match entity.kind {
+                EntityKind::Normal => None,
+                EntityKind::Player { uuid } => Some(format!("{uuid:032x}")),
+            },
+        })
+        .collect()
+}
+
+/// the players in the tab list
+pub fn players(global: &GlobalState) -> Vec<PlayerInfo> {
+    global
+        .players
+        .iter()
+        .map(|player| PlayerInfo {
+            name: player.name.clone(),
+            uuid: format!("{:032x}", player.uuid),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use interfaces::{query::BlockInfo, types::BlockLocation};
+
+    use crate::{
+        client::{query::blocks, state::global::GlobalState},
+        storage::blocks::WorldBlocks,
+    };
+
+    #[test]
+    fn test_blocks() {
+        let mut global = GlobalState::init();
+        global.blocks = WorldBlocks::flat();
+
+        let stone = BlockInfo::Known { id: 1, metadata: 0 };
+        let air = BlockInfo::Known { id: 0, metadata: 0 };
+
+        let region = blocks(
+            &global,
+            BlockLocation::new(1, 1, 0),
+            BlockLocation::new(0, 0, 0),
+        )
+        .unwrap();
+        assert_eq!(region.from, BlockLocation::new(0, 0, 0));
+        assert_eq!(region.blocks, [stone, stone, air, air]);
+
+        // outside of the loaded chunks
+        let region = blocks(
+            &global,
+            BlockLocation::new(500, 0, 500),
+            BlockLocation::new(500, 0, 500),
+        )
+        .unwrap();
+        assert_eq!(region.blocks, [BlockInfo::Unknown]);
+
+        let too_many = blocks(
+            &global,
+            BlockLocation::new(0, 0, 0),
+            BlockLocation::new(100, 100, 100),
+        );
+        assert!(too_many.is_err());
+    }
+}
@@ -1,31 +1,43 @@
 use std::{
     cell::RefCell,
+    collections::HashSet,
     rc::Rc,
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use anyhow::Context;
-use interfaces::types::Selection2D;
+use interfaces::{events::Event, types::Selection2D, Data, ErrorKind, Response, Selector};
+use serde_json::Value;
 use tokio::sync::Notify;
 use tokio_stream::{Stream, StreamExt};
+use tracing::{error, info, info_span, trace_span, warn, Instrument};
 
 use crate::{
-    bootstrap::BotConnection,
+    bootstrap::{mojang, Address, BotConnection},
     client::{
-        bot::{run_threaded, ActionState, Bot},
-        commands::{CommandReceiver, TaggedValue},
+        bot::{process_command, run_threaded, ActionState, Bot, Priority},
+        commands::{
+            select, Candidate, CommandError, CommandReceiver, CommandTracker, Incoming, WsOptions,
+        },
+        dashboard::{BotRow, Dashboard, DashboardAction, Logins as DashboardLogins},
         processor::SimpleInterfaceIn,
+        query,
         state::{
             global::{mine_alloc::MinePreference, GlobalState},
-            local::LocalState,
+            local::{config::BotConfig, LocalState},
         },
+        subscriptions::{Published, Subscriptions},
         tasks::{
-            attack_entity::AttackEntity, lazy_stream::LazyStream, mine_region::MineRegion,
+            attack_entity::AttackEntity,
+            lazy_stream::LazyStream,
+            mine_region::MineRegion,
             navigate::BlockTravelTask,
+            script::{self, ScriptTask},
         },
     },
     default,
+    metrics::{short_type_name, ServerSnapshot, Snapshot, METRICS},
     protocol::{EventQueue, Login, Minecraft},
 };
 
@@ -60,31 +72,63 @@ unsafe impl Sync for SyncLocal {}
 /// TODO
 unsafe impl Send for SyncLocal {}
 
-pub type Logins<T> = Rc<RefCell<Vec<Login<<T as Minecraft>::Queue, <T as Minecraft>::Interface>>>>;
+/// A finished login which is turned into a [`Bot`] by the [`Runner`]
+pub struct PendingLogin<T: Minecraft> {
+    login: Login<T::Queue, T::Interface>,
+
+    /// the config of the bot
+    config: BotConfig,
+
+    /// the server the bot logged into
+    server: Address,
+}
+
+pub type Logins<T> = Rc<RefCell<Vec<PendingLogin<T>>>>;
+
+/// A server the runner has bots on. Bots on different servers do not share
+/// any state.
+struct Server<T: Minecraft> {
+    /// the address of the server
+    address: Address,
+
+    /// the global state of the server containing chunks and global config
+    global_state: GlobalState,
+
+    /// the bots on the server
+    bots: Vec<Bot<T::Queue, T::Interface>>,
+}
 
 /// Runs the game loop and holds all bots.
 pub struct Runner<T: Minecraft> {
     /// logins that are about to be established
     pending_logins: Logins<T>,
 
-    /// the global state of the program containing chunks and global config
-    global_state: GlobalState,
+    /// the servers with at least one bot on them
+    servers: Vec<Server<T>>,
 
     command_receiver: CommandReceiver,
 
-    /// the bots created by pending logins
-    bots: Vec<Bot<T::Queue, T::Interface>>,
+    /// the websocket commands bots are working on
+    commands: CommandTracker,
+
+    /// the websocket clients subscribed to events
+    subscriptions: Subscriptions,
 
     /// An id counter that increases for each bot. Used as a unique identifier.
     id_on: u32,
+
+    /// the terminal dashboard if launched with `--tui`
+    dashboard: Option<Dashboard>,
 }
 
 /// Runner launch options
 pub struct RunnerOptions {
     /// The amount of milliseconds to wait between logging in successive users
     pub delay_ms: u64,
-    /// the websocket port
-    pub ws_port: u16,
+    /// the websocket server
+    pub ws: WsOptions,
+    /// if the terminal dashboard is shown
+    pub tui: bool,
 }
 
 impl<T: Minecraft + 'static> Runner<T> {
@@ -104,19 +148,27 @@ impl<T: Minecraft + 'static> Runner<T> {
         connections: impl Stream<Item = BotConnection> + 'static,
         opts: RunnerOptions,
     ) -> anyhow::Result<Self> {
-        let RunnerOptions { delay_ms, ws_port } = opts;
+        let RunnerOptions { delay_ms, ws, tui } = opts;
 
         // commands received over websocket (typically forge mod)
-        let commands = CommandReceiver::init(ws_port).await?;
+        let commands = CommandReceiver::init(ws).await?;
 
         let pending_logins = Self::login_all(connections, delay_ms);
 
+        let dashboard = if tui {
+            Some(Dashboard::start().context("could not start the dashboard")?)
+        } else {
+            None
+        };
+
         Ok(Self {
             pending_logins,
-            global_state: GlobalState::init(),
+            servers: Vec::new(),
             command_receiver: commands,
-            bots: Vec::new(),
+            commands: CommandTracker::default(),
+            subscriptions: Subscriptions::default(),
             id_on: 0,
+            dashboard,
         })
     }
 
@@ -138,22 +190,39 @@ impl<T: Minecraft + 'static> Runner<T> {
                 while let Some(connection) = connections.next().await {
                     let logins = pending_logins.clone();
 
+                    // the same span name as a running bot so `--log-bot` keeps
+                    // its login logs
+                    let span = info_span!(
+                        "bot",
+                        username = connection.bot.username(),
+                        server = %String::from(&connection.server_address)
+                    );
+
                     // login task for an individual user
-                    tokio::task::spawn_local(async move {
-                        println!("Starting login of {}", connection.bot.username());
-                        let username = connection.bot.username().to_string();
+                    let login = async move {
+                        info!("starting login");
+                        METRICS.login_started();
+                        let config = connection.config.clone();
+                        let server = connection.server_address.clone();
                         let login = match T::login(connection).await {
                             Ok(res) => {
-                                println!("Finished logging in {username}");
+                                info!("finished logging in");
+                                METRICS.login_finished(true);
                                 res
                             }
                             Err(err) => {
-                                println!("Error logging in {username} -- {err}");
+                                error!("error logging in: {err:#}");
+                                METRICS.login_finished(false);
                                 return;
                             }
                         };
-                        logins.borrow_mut().push(login);
-                    });
+                        logins.borrow_mut().push(PendingLogin {
+                            login,
+                            config,
+                            server,
+                        });
+                    };
+                    tokio::task::spawn_local(login.instrument(span));
 
                     // if we want a delay between logging in
                     tokio::time::sleep(Duration::from_millis(delay_millis)).await;
@@ -164,13 +233,38 @@ impl<T: Minecraft + 'static> Runner<T> {
         pending_logins
     }
 
+    /// run the game loop until the dashboard asks to quit
     pub async fn game_loop(&mut self) {
         let mut previous_goal = Instant::now();
 
         // a game loop repeating every 50 ms
-        loop {
+        for tick in 0_u64.. {
             let end_by = previous_goal + Duration::from_millis(50);
-            self.game_iter(end_by).await;
+            let start = Instant::now();
+            self.game_iter(end_by)
+                .instrument(trace_span!("tick", tick))
+                .await;
+
+            let finished = Instant::now();
+            METRICS.tick(finished - start, finished.saturating_duration_since(end_by));
+
+            match self.update_dashboard(finished - start) {
+                Ok(true) => {
+                    info!("quit from the dashboard");
+                    return;
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    self.dashboard = None;
+                    error!("dashboard stopped: {err}");
+                }
+            }
+
+            // about once a second
+            if tick % 20 == 0 {
+                self.publish_metrics();
+            }
+
             tokio::time::sleep_until(tokio::time::Instant::from_std(end_by)).await;
             let now = Instant::now();
             let difference = now - end_by;
@@ -178,26 +272,174 @@ impl<T: Minecraft + 'static> Runner<T> {
 
             // log if we are wayyyy off
             if millis_off > 100 {
-                println!("off by {millis_off}ms");
+                warn!("off by {millis_off}ms");
             }
 
             previous_goal = end_by;
         }
     }
 
+    /// publish the state of all servers to [`METRICS`]
+    fn publish_metrics(&self) {
+        let mut snapshot = Snapshot::default();
+
+        for server in &self.servers {
+            let global = &server.global_state;
+            snapshot.servers.push(ServerSnapshot {
+                address: String::from(&server.address),
+                bots: server.bots.len(),
+                chunks: global.blocks.chunk_count(),
+                entities: global.entities.count(),
+                memory: global.blocks.memory_estimate(),
+            });
+
+            for name in server.bots.iter().filter_map(|bot| bot.actions.task_name()) {
+                *snapshot.tasks.entry(name).or_default() += 1;
+            }
+        }
+
+        METRICS.publish(snapshot);
+    }
+
+    /// handle input to the dashboard and redraw it (if there is one). Returns
+    /// if the dashboard asked to quit.
+    fn update_dashboard(&mut self, tick: Duration) -> std::io::Result<bool> {
+        if self.dashboard.is_none() {
+            return Ok(false);
+        }
+        let rows = self.dashboard_rows();
+
+        let Some(dashboard) = &mut self.dashboard else {
+            return Ok(false);
+        };
+        dashboard.record_tick(tick);
+        let actions = dashboard.handle_input(&rows)?;
+        let redraw = !actions.is_empty() || dashboard.should_draw();
+
+        for action in actions {
+            match action {
+                DashboardAction::Quit => {
+                    // restore the terminal before returning
+                    self.dashboard = None;
+                    return Ok(true);
+                }
+                DashboardAction::Command { bot_id, command } => {
+                    self.dashboard_command(bot_id, &command);
+                }
+            }
+        }
+
+        if !redraw {
+            return Ok(false);
+        }
+
+        let Some(dashboard) = &mut self.dashboard else {
+            return Ok(false);
+        };
+        let chat = dashboard.selected().map_or_else(
+            || self.servers.first().and_then(|server| server.bots.first()),
+            |id| {
+                self.servers
+                    .iter()
+                    .flat_map(|server| &server.bots)
+                    .find(|bot| bot.state.bot_id == id)
+            },
+        );
+        let logins = DashboardLogins {
+            pending: METRICS.logins_pending(),
+            failed: METRICS.logins_failed(),
+            prompts: mojang::device_code_prompts(),
+        };
+        dashboard.draw(&rows, chat.map(|bot| &bot.state.chat), &logins)?;
+        Ok(false)
+    }
+
+    /// a row in the dashboard for every bot
+    fn dashboard_rows(&self) -> Vec<BotRow> {
+        self.servers
+            .iter()
+            .flat_map(|server| {
+                let address = String::from(&server.address);
+                server.bots.iter().map(move |bot| {
+                    let state = &bot.state;
+                    BotRow {
+                        id: state.bot_id,
+                        username: state.info.username.clone(),
+                        server: address.clone(),
+                        health: state.health,
+                        food: state.food,
+                        dimension: state.dimension.to_string(),
+                        location: state.physics.location().to_string(),
+                        task: bot.actions.task_name().map(short_type_name),
+                        disconnected: state.disconnected,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// run a command typed into the dashboard for the bot with `bot_id`
+    fn dashboard_command(&mut self, bot_id: u32, command: &str) {
+        for server in &mut self.servers {
+            let Some(bot) = server
+                .bots
+                .iter_mut()
+                .find(|bot| bot.state.bot_id == bot_id)
+            else {
+                continue;
+            };
+
+            let _span = bot.span.clone().entered();
+            let mut words = command.split_whitespace();
+            let Some(name) = words.next() else {
+                return;
+            };
+            let args: Vec<_> = words.collect();
+
+            info!("command from dashboard: {command}");
+            if let Err(err) = process_command(
+                name,
+                &args,
+                &mut bot.state,
+                &mut server.global_state,
+                &mut bot.actions,
+                &mut bot.out,
+            ) {
+                bot.state.record_chat(format!("error: {err}"));
+            }
+            return;
+        }
+    }
+
+    /// the bots with the given ids on any server
+    fn bots_mut<'a>(
+        &'a mut self,
+        ids: &'a HashSet<u32>,
+    ) -> impl Iterator<Item = &'a mut Bot<T::Queue, T::Interface>> + 'a {
+        self.servers
+            .iter_mut()
+            .flat_map(|server| &mut server.bots)
+            .filter(|bot| ids.contains(&bot.state.bot_id))
+    }
+
+    /// the number of bots on all servers
+    fn bot_count(&self) -> usize {
+        self.servers.iter().map(|server| server.bots.len()).sum()
+    }
+
     async fn game_iter(&mut self, end_by: Instant) {
-        let old_count = self.bots.len();
+        let old_count = self.bot_count();
         // first step: removing disconnected clients
         self.remove_disconnected();
 
         // second step: turning pending logins into clients
         self.pending_logins_to_client();
 
-        let new_count = self.bots.len();
+        let new_count = self.bot_count();
 
         // log clients if they have changed
         if new_count != old_count {
-            println!("{new_count} clients");
+            info!("{new_count} clients");
         }
 
         // process pending commands (from forge mod)
@@ -205,59 +447,192 @@ impl<T: Minecraft + 'static> Runner<T> {
 
         // fourth step: process packets from game loop
         self.process_incoming_minecraft_packets();
+        self.report_stopped();
 
         // fifth step: process packets from game loop
         self.run_expensive_tasks_multithreaded(end_by).await;
     }
 
-    /// remove disconnected clients
+    /// remove disconnected clients. Servers without bots are removed along
+    /// with their world state.
     fn remove_disconnected(&mut self) {
-        self.bots.retain(|client| !client.state.disconnected);
+        // disconnected bots stop working on their commands
+        for bot in self.servers.iter_mut().flat_map(|server| &mut server.bots) {
+            if bot.state.disconnected {
+                bot.actions.clear();
+            }
+        }
+        self.report_stopped();
+
+        for server in &mut self.servers {
+            server.bots.retain(|client| !client.state.disconnected);
+        }
+
+        self.servers.retain(|server| {
+            let empty = server.bots.is_empty();
+            if empty {
+                info!("no bots left on {}", String::from(&server.address));
+            }
+            !empty
+        });
     }
 
     /// turn pending logins into clients that are controller by the [`Runner`].
     fn pending_logins_to_client(&mut self) {
         let mut logins = self.pending_logins.borrow_mut();
 
-        for login in logins.drain(..) {
+        for pending in logins.drain(..) {
+            let PendingLogin {
+                login,
+                config,
+                server,
+            } = pending;
             let Login { queue, out, info } = login;
 
+            let span = info_span!(
+                "bot",
+                id = self.id_on,
+                username = info.username.as_str(),
+                group = config.group.as_deref()
+            );
+
             let client = Bot {
-                state: LocalState::new(self.id_on, info),
+                state: LocalState::new(self.id_on, info, config),
                 actions: default(),
                 queue,
                 out,
+                span,
             };
             self.id_on += 1;
-            self.bots.push(client);
+
+            let idx = if let Some(idx) = self.servers.iter().position(|s| s.address == server) {
+                idx
+            } else {
+                info!("first bot joined {}", String::from(&server));
+                self.servers.push(Server {
+                    address: server,
+                    global_state: GlobalState::init(),
+                    bots: Vec::new(),
+                });
+                self.servers.len() - 1
+            };
+
+            self.servers[idx].bots.push(client);
         }
     }
 
     /// process pending commands (generally from forge mod but more generally
     /// from a websocket)
     fn process_commands(&mut self) {
-        while let Ok(command) = self.command_receiver.pending.try_recv() {
-            if let Err(err) = self.process_command(command) {
-                println!("Error processing command: {err}");
+        while let Ok(incoming) = self.command_receiver.pending.try_recv() {
+            // queries are answered right away and do not involve bots
+            match self.query(&incoming) {
+                Ok(None) => {}
+                Ok(Some(data)) => {
+                    let id = incoming.command.id;
+                    incoming.client.send(Response::Data(Data { id, data }));
+                    continue;
+                }
+                Err(err) => {
+                    incoming.client.error(Some(incoming.command.id), err);
+                    continue;
+                }
+            }
+
+            // replacing an active command or subscription would leave it
+            // without `Finished` or `Cancelled`
+            let id = incoming.id();
+            if self.commands.is_active(id) || self.subscriptions.is_active(id) {
+                let err = CommandError::new(
+                    ErrorKind::InvalidData,
+                    format!("command id {} is already in use", incoming.command.id),
+                );
+                incoming.client.error(Some(incoming.command.id), err);
+                continue;
+            }
+
+            match self.subscriptions.command(&incoming) {
+                Ok(false) => {}
+                Ok(true) => continue,
+                Err(err) => {
+                    incoming.client.error(Some(incoming.command.id), err);
+                    continue;
+                }
+            }
+
+            if !incoming.client.can_control() {
+                let err = CommandError::new(
+                    ErrorKind::Forbidden,
+                    format!("{} needs a control token", incoming.command.path),
+                );
+                incoming.client.error(Some(incoming.command.id), err);
+                continue;
+            }
+
+            match self.process_command(&incoming) {
+                Ok(bots) => self.commands.start(&incoming, bots),
+                Err(err) => {
+                    warn!("error processing command: {}", err.message);
+                    incoming.client.error(Some(incoming.command.id), err);
+                }
             }
         }
     }
 
-    fn process_incoming_minecraft_packets(&mut self) {
-        for bot in &mut self.bots {
-            let mut processor = SimpleInterfaceIn::new(
-                &mut bot.state,
-                &mut bot.actions,
-                &mut self.global_state,
-                &mut bot.out,
-            );
+    /// tell websocket clients which bots stopped working on their commands
+    /// and publish the events of all bots
+    fn report_stopped(&mut self) {
+        let mut events = Vec::new();
+
+        for server in &mut self.servers {
+            let address = String::from(&server.address);
+
+            for bot in &mut server.bots {
+                let bot_id = bot.state.bot_id;
+
+                for stopped in bot.actions.take_stopped() {
+                    if let Some(command) = stopped.command {
+                        self.commands.stopped(command, bot_id, stopped.outcome);
+                    }
+
+                    bot.state.emit(Event::Task {
+                        task: short_type_name(stopped.task),
+                        outcome: stopped.outcome,
+                    });
+                }
+
+                events.extend(bot.state.events.drain(..).map(|event| Published {
+                    bot: bot_id,
+                    server: address.clone(),
+                    event,
+                }));
+            }
+        }
 
-            // protocol-specific logic. Translates input packets and sends to processor
-            bot.queue.flush(&mut processor);
+        let servers = &self.servers;
+        self.subscriptions
+            .publish(&events, |selector| Self::select_in(servers, selector));
+    }
 
-            // fifth step: general sync logic that isn't dependent on protocol
-            // implementation
-            bot.run_sync(&mut self.global_state);
+    fn process_incoming_minecraft_packets(&mut self) {
+        for server in &mut self.servers {
+            for bot in &mut server.bots {
+                let _span = bot.span.clone().entered();
+
+                let mut processor = SimpleInterfaceIn::new(
+                    &mut bot.state,
+                    &mut bot.actions,
+                    &mut server.global_state,
+                    &mut bot.out,
+                );
+
+                // protocol-specific logic. Translates input packets and sends to processor
+                trace_span!("packets").in_scope(|| bot.queue.flush(&mut processor));
+
+                // fifth step: general sync logic that isn't dependent on protocol
+                // implementation
+                bot.run_sync(&mut server.global_state);
+            }
         }
     }
 
@@ -289,29 +664,44 @@ impl<T: Minecraft + 'static> Runner<T> {
             // lifetimes of &GlobalState and &mut LocalState to be 'static. This is overall
             // pretty safe as it still requires the states to be Send+Sync, so
             // it is hard to make errors.
-            let global_state_sync = SyncGlobal(&self.global_state);
-            let states_sync: Vec<_> = self
-                .bots
+            //
+            // Each bot only sees the global state of its own server.
+            let servers_sync: Vec<_> = self
+                .servers
                 .iter_mut()
-                .map(|bot| (&mut bot.state, &mut bot.actions))
-                .map(|(state, actions)| (state as *mut LocalState, actions as *mut ActionState))
-                .map(SyncLocal)
+                .map(|server| {
+                    let global_state_sync = SyncGlobal(&server.global_state);
+                    let states_sync: Vec<_> = server
+                        .bots
+                        .iter_mut()
+                        .map(|bot| {
+                            let state = &mut bot.state as *mut LocalState;
+                            let actions = &mut bot.actions as *mut ActionState;
+                            (SyncLocal((state, actions)), bot.span.clone())
+                        })
+                        .collect();
+                    (global_state_sync, states_sync)
+                })
                 .collect();
 
             rayon::spawn(move || {
-                // Safety:
-                // TODO
-                let global_state = unsafe { global_state_sync.state() };
                 rayon::scope(|s| {
-                    for state_sync in states_sync {
-                        let (state, actions) = state_sync.0;
+                    for (global_state_sync, states_sync) in &servers_sync {
                         // Safety:
                         // TODO
-                        let (state, actions) = unsafe { (&mut *state, &mut *actions) };
-
-                        s.spawn(move |inner_scope| {
-                            run_threaded(inner_scope, state, actions, global_state, end_by);
-                        });
+                        let global_state = unsafe { global_state_sync.state() };
+
+                        for (state_sync, span) in states_sync {
+                            let (state, actions) = state_sync.0;
+                            // Safety:
+                            // TODO
+                            let (state, actions) = unsafe { (&mut *state, &mut *actions) };
+
+                            s.spawn(move |inner_scope| {
+                                let _span = span.enter();
+                                run_threaded(inner_scope, state, actions, global_state, end_by);
+                            });
+                        }
                     }
                 });
 
@@ -325,51 +715,292 @@ impl<T: Minecraft + 'static> Runner<T> {
         thread_loop_end.notified().await;
     }
 
-    fn process_command(&mut self, command: TaggedValue) -> anyhow::Result<()> {
-        use interfaces::{Attack, GoTo, Mine, Tag};
+    /// the ids of the bots on any server `selector` selects
+    fn select(&self, selector: &Selector) -> HashSet<u32> {
+        Self::select_in(&self.servers, selector)
+    }
 
-        let global = &mut self.global_state;
-        let bots = &mut self.bots;
+    /// the ids of the bots on `servers` which `selector` selects
+    fn select_in(servers: &[Server<T>], selector: &Selector) -> HashSet<u32> {
+        let candidates = servers
+            .iter()
+            .flat_map(|server| &server.bots)
+            .map(|bot| Candidate {
+                id: bot.state.bot_id,
+                username: &bot.state.info.username,
+                group: bot.state.config.group.as_deref(),
+                idle: bot.actions.is_idle(),
+                location: bot.state.physics.location(),
+            });
+
+        select(selector, candidates)
+    }
 
-        match command.path.as_str() {
-            Mine::PATH => {
-                let Mine { sel } = command.parse()?;
+    /// the server with `address`. The address can be left out if there is
+    /// only one server.
+    fn server(&self, address: Option<&str>) -> Result<&Server<T>, CommandError> {
+        if let Some(address) = address {
+            return self
+                .servers
+                .iter()
+                .find(|server| String::from(&server.address) == address)
+                .ok_or_else(|| {
+                    CommandError::new(ErrorKind::NotFound, format!("no bots are on {address}"))
+                });
+        }
 
-                let Selection2D { from, to } = sel.normalize();
-                global.mine.mine(from, to, Some(MinePreference::FromDist));
+        match self.servers.as_slice() {
+            [server] => Ok(server),
+            [] => Err(CommandError::new(
+                ErrorKind::NotFound,
+                "no bots are on any server",
+            )),
+            _ => Err(CommandError::new(
+                ErrorKind::InvalidData,
+                "bots are on several servers so a server must be given",
+            )),
+        }
+    }
 
-                for bot in bots {
-                    bot.actions.schedule(LazyStream::from(MineRegion));
-                }
+    /// answer a read-only query. Returns [`None`] if the command is not a
+    /// query.
+    fn query(&self, incoming: &Incoming) -> Result<Option<Value>, CommandError> {
+        use interfaces::{
+            query::{GetBlock, GetBlocks, GetBots, GetEntities, GetPlayers},
+            Tag,
+        };
+
+        let command = &incoming.command;
+        let data = match command.path.as_str() {
+            GetBots::PATH => {
+                let GetBots { bots: selector } = command.parse()?;
+                let selected = self.select(&selector);
+                let bots: Vec<_> = self
+                    .servers
+                    .iter()
+                    .flat_map(|server| {
+                        let address = String::from(&server.address);
+                        server
+                            .bots
+                            .iter()
+                            .filter(|bot| selected.contains(&bot.state.bot_id))
+                            .map(move |bot| {
+                                query::bot(
+                                    &bot.state,
+                                    &bot.actions,
+                                    address.clone(),
+                                    &incoming.client,
+                                )
+                            })
+                    })
+                    .collect();
+                serde_json::to_value(bots)
             }
-            GoTo::PATH => {
-                let GoTo { location } = command.parse()?;
-                for bot in bots {
-                    bot.actions
-                        .schedule(BlockTravelTask::new(location, &bot.state));
-                }
+            GetBlock::PATH => {
+                let GetBlock { location, server } = command.parse()?;
+                let server = self.server(server.as_deref())?;
+                serde_json::to_value(query::block(&server.global_state, location))
+            }
+            GetBlocks::PATH => {
+                let GetBlocks { from, to, server } = command.parse()?;
+                let server = self.server(server.as_deref())?;
+                serde_json::to_value(query::blocks(&server.global_state, from, to)?)
+            }
+            GetEntities::PATH => {
+                let GetEntities {
+                    location,
+                    radius,
+                    server,
+                } = command.parse()?;
+                let server = self.server(server.as_deref())?;
+                serde_json::to_value(query::entities(&server.global_state, location, radius))
+            }
+            GetPlayers::PATH => {
+                let GetPlayers { server } = command.parse()?;
+                let server = self.server(server.as_deref())?;
+                serde_json::to_value(query::players(&server.global_state))
+            }
+            _ => return Ok(None),
+        };
+
+        data.map(Some)
+            .map_err(|err| CommandError::new(ErrorKind::Failed, err))
+    }
+
+    /// process a command for the selected bots on every server. Returns the
+    /// ids of the bots working on it.
+    fn process_command(&mut self, incoming: &Incoming) -> Result<Vec<u32>, CommandError> {
+        use interfaces::{Assign, Attack, Cancel, GoTo, Mine, Script, Tag};
+
+        match incoming.command.path.as_str() {
+            Mine::PATH => self.command_mine(incoming),
+            GoTo::PATH => self.command_goto(incoming),
+            Attack::PATH => self.command_attack(incoming),
+            Script::PATH => self.command_script(incoming),
+            Assign::PATH => self.command_assign(incoming),
+            Cancel::PATH => self.command_cancel(incoming),
+            path => Err(CommandError::new(
+                ErrorKind::UnknownPath,
+                format!("unknown path {path}"),
+            )),
+        }
+    }
+
+    /// mine a region with the selected bots on every server
+    fn command_mine(&mut self, incoming: &Incoming) -> Result<Vec<u32>, CommandError> {
+        let interfaces::Mine {
+            sel,
+            bots: selector,
+        } = incoming.command.parse()?;
+        let Selection2D { from, to } = sel.normalize();
+        let selected = self.select(&selector);
+        let mut bots = Vec::new();
+
+        for server in &mut self.servers {
+            let mut server_bots = server
+                .bots
+                .iter_mut()
+                .filter(|bot| selected.contains(&bot.state.bot_id))
+                .peekable();
+
+            // only allocate the region on servers with bots mining it
+            if server_bots.peek().is_none() {
+                continue;
+            }
+
+            server
+                .global_state
+                .mine
+                .mine(from, to, Some(MinePreference::FromDist));
+
+            // mining is background work. Other commands interrupt it
+            // and it continues once they are done.
+            for bot in server_bots {
+                let task = LazyStream::from(MineRegion::default());
+                bot.actions
+                    .schedule_command(task, Priority::Low, incoming.id());
+                bots.push(bot.state.bot_id);
             }
-            Attack::PATH => {
-                let Attack { name } = command.parse()?;
-                let player = self
-                    .global_state
-                    .players
-                    .by_name(&name)
-                    .context("player does not exist")?;
-                let entity_id = self
-                    .global_state
-                    .entities
-                    .by_player_uuid(player.uuid)
-                    .context("could not find entity id for player")?;
-
-                for bot in bots {
-                    let task = LazyStream::from(AttackEntity::new(entity_id));
-                    bot.actions.schedule(task);
+        }
+
+        Ok(bots)
+    }
+
+    fn command_goto(&mut self, incoming: &Incoming) -> Result<Vec<u32>, CommandError> {
+        let interfaces::GoTo {
+            location,
+            bots: selector,
+        } = incoming.command.parse()?;
+        let selected = self.select(&selector);
+        let mut bots = Vec::new();
+
+        for bot in self.bots_mut(&selected) {
+            let task = BlockTravelTask::new(location, &bot.state);
+            bot.actions
+                .schedule_command(task, Priority::Normal, incoming.id());
+            bots.push(bot.state.bot_id);
+        }
+
+        Ok(bots)
+    }
+
+    fn command_attack(&mut self, incoming: &Incoming) -> Result<Vec<u32>, CommandError> {
+        let interfaces::Attack {
+            name,
+            bots: selector,
+        } = incoming.command.parse()?;
+        let selected = self.select(&selector);
+
+        // entity ids are per server so the player is looked up on each. All
+        // are resolved first so nothing is scheduled on an error.
+        let mut targets = Vec::new();
+        for (idx, server) in self.servers.iter_mut().enumerate() {
+            let global = &mut server.global_state;
+            let Some(player) = global.players.by_name(&name) else {
+                continue;
+            };
+            let entity_id = global.entities.by_player_uuid(player.uuid).ok_or_else(|| {
+                CommandError::new(
+                    ErrorKind::NotFound,
+                    format!("could not find entity id for {name}"),
+                )
+            })?;
+            targets.push((idx, entity_id));
+        }
+
+        if targets.is_empty() {
+            return Err(CommandError::new(
+                ErrorKind::NotFound,
+                format!("player {name} does not exist"),
+            ));
+        }
+
+        let mut bots = Vec::new();
+        for (idx, entity_id) in targets {
+            for bot in &mut self.servers[idx].bots {
+                if !selected.contains(&bot.state.bot_id) {
+                    continue;
                 }
+                let task = LazyStream::from(AttackEntity::new(entity_id));
+                bot.actions
+                    .schedule_command(task, Priority::Normal, incoming.id());
+                bots.push(bot.state.bot_id);
             }
-            _ => {}
         }
 
-        Ok(())
+        Ok(bots)
+    }
+
+    fn command_script(&mut self, incoming: &Incoming) -> Result<Vec<u32>, CommandError> {
+        let interfaces::Script {
+            source,
+            bots: selector,
+        } = incoming.command.parse()?;
+        let ast = script::compile(&source)
+            .map_err(|err| CommandError::new(ErrorKind::InvalidData, err.to_string()))?;
+        let selected = self.select(&selector);
+        let mut bots = Vec::new();
+
+        for bot in self.bots_mut(&selected) {
+            let task = ScriptTask::new(ast.clone());
+            bot.actions
+                .schedule_command(task, Priority::Normal, incoming.id());
+            bots.push(bot.state.bot_id);
+        }
+
+        Ok(bots)
+    }
+
+    /// assigning is done immediately so no bots are working on it
+    fn command_assign(&mut self, incoming: &Incoming) -> Result<Vec<u32>, CommandError> {
+        let interfaces::Assign {
+            bots: selector,
+            group,
+        } = incoming.command.parse()?;
+        let selected = self.select(&selector);
+
+        for bot in self.bots_mut(&selected) {
+            bot.state.config.group.clone_from(&group);
+        }
+
+        info!("assigned {} bots to {group:?}", selected.len());
+        Ok(Vec::new())
+    }
+
+    fn command_cancel(&mut self, incoming: &Incoming) -> Result<Vec<u32>, CommandError> {
+        let interfaces::Cancel { command } = incoming.command.parse()?;
+        let target = incoming.other(command);
+        if self.commands.bots(target).is_none() {
+            return Err(CommandError::new(
+                ErrorKind::NotFound,
+                format!("command {command} is not running"),
+            ));
+        }
+
+        for bot in self.servers.iter_mut().flat_map(|server| &mut server.bots) {
+            bot.actions.cancel_command(target);
+        }
+
+        Ok(Vec::new())
     }
 }
@@ -1,10 +1,7 @@
 use interfaces::types::block_data::BlockData;
 
 use crate::{
-    client::{
-        pathfind::context::PathConfig,
-        state::global::{mine_alloc::MineAlloc, world_players::WorldPlayers},
-    },
+    client::state::global::{mine_alloc::MineAlloc, world_players::WorldPlayers},
     storage::{blocks::WorldBlocks, entities::WorldEntities},
 };
 
@@ -30,9 +27,6 @@ pub struct GlobalState {
 
     /// ticks since the first bot has joined
     pub ticks: usize,
-
-    /// config for traveling
-    pub travel_config: PathConfig,
 }
 
 impl GlobalState {
@@ -13,11 +13,14 @@ impl WorldPlayers {
         self.players.push(player);
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &Player> + '_ {
+        self.players.iter()
+    }
+
     pub fn by_name(&mut self, name: &str) -> Option<&Player> {
         self.players.iter().find(|player| player.name == name)
     }
 
-    #[allow(unused)]
     pub fn by_uuid(&mut self, uuid: u128) -> Option<&Player> {
         self.players.iter().find(|player| player.uuid == uuid)
     }
@@ -1,13 +1,24 @@
+use std::collections::VecDeque;
+
+use interfaces::events::Event;
 use swarm_bot_packets::types::UUID;
 
 use crate::{
-    client::{physics::Physics, state::local::inventory::PlayerInventory},
+    client::{
+        physics::Physics,
+        state::local::{blackboard::Blackboard, config::BotConfig, inventory::PlayerInventory},
+    },
     protocol::ClientInfo,
     types::Dimension,
 };
 
+pub mod blackboard;
+pub mod config;
 pub mod inventory;
 
+/// the number of chat lines a bot remembers
+const CHAT_HISTORY: usize = 100;
+
 /// The local state which only each bot knows
 ///
 /// - this can be mutably modified by each bot at any time because it is the
@@ -25,6 +36,20 @@ pub struct LocalState {
     pub info: ClientInfo,
     pub alive: bool,
     pub dimension: Dimension,
+    pub config: BotConfig,
+
+    /// the time of day in ticks (0 is sunrise and a day is 24000 ticks)
+    pub time_of_day: i64,
+
+    /// what the behavior tree of the bot knows (see
+    /// [`crate::client::tasks::behavior`])
+    pub blackboard: Blackboard,
+
+    /// the most recent chat messages received and replies to commands
+    pub chat: VecDeque<String>,
+
+    /// events for websocket subscriptions since they were last taken
+    pub events: Vec<Event>,
 }
 
 impl LocalState {
@@ -37,12 +62,13 @@ impl LocalState {
                 uuid: UUID::default(),
                 entity_id: 0,
             },
+            BotConfig::default(),
         )
     }
 }
 
 impl LocalState {
-    pub fn new(bot_id: u32, info: ClientInfo) -> Self {
+    pub fn new(bot_id: u32, info: ClientInfo, config: BotConfig) -> Self {
         Self {
             ticks: 0,
             health: 0.0,
@@ -55,6 +81,25 @@ impl LocalState {
             alive: true,
             dimension: Dimension::Overworld,
             info,
+            time_of_day: 0,
+            blackboard: config.blackboard.clone(),
+            config,
+            chat: VecDeque::new(),
+            events: Vec::new(),
         }
     }
+
+    /// remember a chat line. The oldest line is forgotten once there are
+    /// [`CHAT_HISTORY`] lines
+    pub fn record_chat(&mut self, line: String) {
+        if self.chat.len() == CHAT_HISTORY {
+            self.chat.pop_front();
+        }
+        self.chat.push_back(line);
+    }
+
+    /// something happened that websocket clients can subscribe to
+    pub fn emit(&mut self, event: Event) {
+        self.events.push(event);
+    }
 }
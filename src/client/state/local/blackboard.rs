@@ -0,0 +1,29 @@
+//! Data a bot's behavior tree works with (i.e., where its bed is). Values are
+//! JSON so they can be given in the config file and set while the bot runs.
+
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Named values of a bot (see the [module docs](self))
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Blackboard(HashMap<String, serde_json::Value>);
+
+impl Blackboard {
+    /// the value of `key` if it is set and is a `T`
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.0.get(key)?.clone();
+        serde_json::from_value(value).ok()
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Serialize) {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.0.insert(key.to_string(), value);
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+}
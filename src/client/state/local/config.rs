@@ -0,0 +1,64 @@
+//! Per-bot configuration. Every bot starts with the `[bot]` section of the
+//! config file, which the section of its group can override.
+
+use serde::Deserialize;
+
+use crate::client::{pathfind::context::PathConfig, state::local::blackboard::Blackboard};
+
+/// How a bot deals with chat
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatConfig {
+    /// if chat messages the bot receives are printed
+    pub print: bool,
+
+    /// if the bot responds to `#command`s in chat and whispers
+    pub commands: bool,
+
+    /// the players whose commands the bot responds to. If empty, any player
+    /// can command the bot.
+    pub allowed_players: Vec<String>,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            print: true,
+            commands: true,
+            allowed_players: Vec::new(),
+        }
+    }
+}
+
+impl ChatConfig {
+    /// if `player` can command the bot
+    pub fn allows(&self, player: &str) -> bool {
+        self.commands
+            && (self.allowed_players.is_empty()
+                || self.allowed_players.iter().any(|allowed| allowed == player))
+    }
+}
+
+/// The configuration of a single bot
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotConfig {
+    /// the name of the group the bot was launched in. Bots can be assigned to
+    /// another group over the websocket
+    #[serde(skip)]
+    pub group: Option<String>,
+
+    /// path finding costs and moves
+    pub path: PathConfig,
+
+    /// chat settings
+    pub chat: ChatConfig,
+
+    /// the behavior tree the bot runs once it joins (see
+    /// [`crate::client::tasks::behavior::load`])
+    pub behavior: Option<String>,
+
+    /// what the behavior tree knows from the start (i.e., `home = { x = 0, y =
+    /// 64, z = 0 }`)
+    pub blackboard: Blackboard,
+}
@@ -43,6 +43,16 @@ impl ItemStack {
     }
 }
 
+/// if `stack` is loot, which is everything but tools, food, buckets and
+/// building blocks
+fn is_loot(stack: &ItemStack, data: &BlockData) -> bool {
+    let kind = stack.kind;
+    Tool::from(stack).material == ToolMat::Hand
+        && !data.is_food(kind.id())
+        && !matches!(kind.id(), 325 | 326)
+        && !kind.throw_away_block()
+}
+
 #[derive(Debug)]
 pub struct PlayerInventory {
     slots: [Option<ItemStack>; 46],
@@ -60,6 +70,19 @@ impl Default for PlayerInventory {
 }
 
 impl PlayerInventory {
+    /// all slots with an item in them
+    pub fn items(&self) -> impl Iterator<Item = (usize, &ItemStack)> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, slot)| slot.as_ref().map(|stack| (idx, stack)))
+    }
+
+    /// the selected hotbar slot
+    pub const fn selected(&self) -> u8 {
+        self.selected
+    }
+
     pub fn hotbar(&self) -> &[Option<ItemStack>] {
         &self.slots[36..45]
     }
@@ -81,6 +104,57 @@ impl PlayerInventory {
         }
     }
 
+    /// the tools in the hotbar (a hand for empty slots or items which are not
+    /// tools)
+    pub fn hotbar_tools(&self) -> Vec<Tool> {
+        self.hotbar()
+            .iter()
+            .map(|stack| stack.as_ref().map_or_else(Tool::default, Tool::from))
+            .collect()
+    }
+
+    /// the number of blocks in the hotbar which can be placed to get around
+    /// (see [`PlayerInventory::switch_block`])
+    pub fn throwaway_count(&self) -> usize {
+        self.hotbar()
+            .iter()
+            .flatten()
+            .filter(|stack| stack.kind.throw_away_block())
+            .map(|stack| usize::from(stack.count))
+            .sum()
+    }
+
+    /// if every slot of the main inventory and hotbar has something in it
+    pub fn is_full(&self) -> bool {
+        self.slots[9..45].iter().all(Option::is_some)
+    }
+
+    /// if something in the main inventory or hotbar is loot (see
+    /// [`is_loot`])
+    pub fn has_loot(&self, data: &BlockData) -> bool {
+        self.slots[9..45]
+            .iter()
+            .flatten()
+            .any(|stack| is_loot(stack, data))
+    }
+
+    /// drop a single stack of loot. Returns if there was none left.
+    pub fn drop_loot(&mut self, data: &BlockData, out: &mut dyn InterfaceOut) -> bool {
+        let idx = (9u16..45).find(|&idx| {
+            self.slots[usize::from(idx)]
+                .as_ref()
+                .is_some_and(|stack| is_loot(stack, data))
+        });
+
+        if let Some(idx) = idx {
+            self.slots[usize::from(idx)] = None;
+            out.inventory_action(InvAction::CtrlQ(idx));
+            false
+        } else {
+            true
+        }
+    }
+
     #[allow(unused)]
     pub fn current_tool(&self) -> Tool {
         match &self.hotbar()[self.selected as usize] {
@@ -0,0 +1,255 @@
+//! Websocket clients [`Subscribe`] to the events of the swarm. Events are
+//! emitted by bots while processing packets ([`LocalState::emit`]) and
+//! published by the runner at the end of every tick.
+//!
+//! [`LocalState::emit`]: crate::client::state::local::LocalState::emit
+
+use std::collections::{HashMap, HashSet};
+
+use interfaces::{
+    events::{Event, EventKind, EventMessage, Subscribe, Unsubscribe},
+    Ack, ErrorKind, Finished, Response, Selector, Tag,
+};
+
+use crate::client::commands::{Client, CommandError, CommandId, Incoming};
+
+/// An event on `server` which happened to (or was seen by) `bot`
+pub struct Published {
+    pub bot: u32,
+    pub server: String,
+    pub event: Event,
+}
+
+struct Subscription {
+    client: Client,
+    filter: Subscribe,
+}
+
+impl Subscription {
+    /// if the event passes the filters. `selected` are the bots selected by
+    /// the filter.
+    fn wants(&self, published: &Published, selected: impl FnOnce() -> bool) -> bool {
+        let Subscribe {
+            events,
+            server,
+            region,
+            ..
+        } = &self.filter;
+
+        let event = &published.event;
+
+        if !events.is_empty() && !events.contains(&event.kind()) {
+            return false;
+        }
+
+        if server
+            .as_ref()
+            .is_some_and(|server| *server != published.server)
+        {
+            return false;
+        }
+
+        if let Event::BlockChange { location, .. } = event {
+            return region.is_some_and(|region| region.contains(*location));
+        }
+
+        event.is_world() || selected()
+    }
+}
+
+#[derive(Default)]
+pub struct Subscriptions {
+    active: HashMap<CommandId, Subscription>,
+}
+
+impl Subscriptions {
+    /// if `id` is the id of a subscription
+    pub fn is_active(&self, id: CommandId) -> bool {
+        self.active.contains_key(&id)
+    }
+
+    /// handle [`Subscribe`] and [`Unsubscribe`]. Returns false if the command
+    /// is neither.
+    pub fn command(&mut self, incoming: &Incoming) -> Result<bool, CommandError> {
+        let command = &incoming.command;
+        let client = &incoming.client;
+        let id = command.id;
+
+        match command.path.as_str() {
+            Subscribe::PATH => {
+                let filter: Subscribe = command.parse()?;
+
+                if filter.events.contains(&EventKind::BlockChange) && filter.region.is_none() {
+                    return Err(CommandError::new(
+                        ErrorKind::InvalidData,
+                        "block changes need a region",
+                    ));
+                }
+
+                self.active.insert(
+                    incoming.id(),
+                    Subscription {
+                        client: client.clone(),
+                        filter,
+                    },
+                );
+
+                // the subscription is finished when unsubscribed
+                client.send(Response::Ack(Ack {
+                    id,
+                    bots: Vec::new(),
+                }));
+            }
+            Unsubscribe::PATH => {
+                let Unsubscribe { subscription } = command.parse()?;
+
+                if self.active.remove(&incoming.other(subscription)).is_none() {
+                    return Err(CommandError::new(
+                        ErrorKind::NotFound,
+                        format!("no subscription {subscription}"),
+                    ));
+                }
+
+                client.send(Response::Ack(Ack {
+                    id,
+                    bots: Vec::new(),
+                }));
+                client.send(Response::Finished(Finished { id }));
+                client.send(Response::Finished(Finished { id: subscription }));
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// send `events` to the subscriptions which want them. `select` returns the
+    /// bots a [`Selector`] selects.
+    pub fn publish(&mut self, events: &[Published], select: impl Fn(&Selector) -> HashSet<u32>) {
+        // subscriptions end with the connection
+        self.active
+            .retain(|_, subscription| subscription.client.is_connected());
+
+        if events.is_empty() {
+            return;
+        }
+
+        for (id, subscription) in &self.active {
+            // only select bots if there is an event about a bot
+            let mut selected = None;
+
+            for published in events {
+                let wants = subscription.wants(published, || {
+                    selected
+                        .get_or_insert_with(|| select(&subscription.filter.bots))
+                        .contains(&published.bot)
+                });
+
+                if !wants {
+                    continue;
+                }
+
+                subscription.client.send(Response::Event(EventMessage {
+                    subscription: id.id(),
+                    bot: published.bot,
+                    server: published.server.clone(),
+                    event: published.event.clone(),
+                }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use interfaces::{
+        events::{Event, EventKind, Region, Subscribe},
+        query::BlockInfo,
+        types::BlockLocation,
+        Command, Response, Selector, Tag,
+    };
+
+    use crate::client::{
+        commands::{Client, Incoming, Role},
+        subscriptions::{Published, Subscriptions},
+    };
+
+    #[test]
+    fn test_publish() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let client = Client::new(0, Role::Control, tx);
+
+        let subscribe = Subscribe {
+            events: vec![EventKind::Death, EventKind::BlockChange],
+            bots: Selector {
+                ids: vec![1],
+                ..Selector::default()
+            },
+            server: None,
+            region: Some(Region {
+                from: BlockLocation::new(0, 0, 0),
+                to: BlockLocation::new(10, 10, 10),
+            }),
+        };
+        let incoming = Incoming {
+            client,
+            command: subscribe.command(3),
+        };
+
+        let mut subscriptions = Subscriptions::default();
+        assert!(subscriptions.command(&incoming).unwrap());
+
+        // the id is taken by the active subscription
+        assert!(subscriptions.is_active(incoming.id()));
+
+        let not_subscription = Incoming {
+            client: incoming.client.clone(),
+            command: Command {
+                id: 4,
+                path: "goto".to_string(),
+                data: serde_json::Value::Null,
+            },
+        };
+        assert!(!subscriptions.command(&not_subscription).unwrap());
+
+        let published = |bot, event| Published {
+            bot,
+            server: "localhost:25565".to_string(),
+            event,
+        };
+        let block_change = |x| Event::BlockChange {
+            location: BlockLocation::new(x, 0, 0),
+            block: BlockInfo::Unknown,
+        };
+
+        let events = [
+            published(1, Event::Death),
+            // not selected
+            published(2, Event::Death),
+            // not subscribed to
+            published(
+                1,
+                Event::Chat {
+                    message: "hi".to_string(),
+                },
+            ),
+            // block changes are sent no matter which bot saw them
+            published(2, block_change(5)),
+            // outside of the region
+            published(1, block_change(50)),
+        ];
+        subscriptions.publish(&events, |_| HashSet::from([1]));
+
+        let mut received = Vec::new();
+        while let Ok(response) = rx.try_recv() {
+            if let Response::Event(message) = response {
+                assert_eq!(message.subscription, 3);
+                received.push((message.bot, message.event));
+            }
+        }
+
+        assert_eq!(received, [(1, Event::Death), (2, block_change(5))]);
+    }
+}
@@ -5,13 +5,16 @@ use crate::{
         pathfind::implementations::no_vehicle::TravelProblem,
         state::{global::GlobalState, local::LocalState},
         tasks::{
-            compound::CompoundTask, delay::DelayTask, hit_entity::HitEntityTask,
+            combinator::Race, compound::CompoundTask, delay::DelayTask, hit_entity::HitEntityTask,
             navigate::NavigateProblem, stream::TaskStream, Task,
         },
     },
     protocol::InterfaceOut,
 };
 
+/// how often to path to where the entity is now
+const REPATH_TICKS: u32 = 40;
+
 pub struct AttackEntity {
     id: u32,
 }
@@ -57,7 +60,7 @@ impl TaskStream for AttackEntity {
             );
             let task = NavigateProblem::from(travel);
 
-            Some(Box::new(task))
+            Some(Box::new(Race::new(task, DelayTask(REPATH_TICKS))))
         }
     }
 }
@@ -0,0 +1,255 @@
+//! The conditions and actions at the leaves of a behavior tree. Locations are
+//! read from the blackboard of the bot by key.
+
+use std::fmt::{Display, Formatter};
+
+use interfaces::types::{BlockLocation, BlockLocation2D};
+use serde::Deserialize;
+
+use crate::{
+    client::{
+        pathfind::implementations::no_vehicle::TravelProblem,
+        state::{global::GlobalState, local::LocalState},
+        tasks::{
+            combinator::{Branch, Parallel, Repeat, Retry, Timeout, Watch},
+            compound::CompoundTask,
+            delay::DelayTask,
+            eat::EatTask,
+            mine_region::MineRegion,
+            navigate::{BlockTravelTask, NavigateProblem},
+            MineRegionTask, Status, Task,
+        },
+    },
+    protocol::{Face, InterfaceOut},
+};
+
+/// when beds can be used (in ticks since sunrise)
+const NIGHT: std::ops::Range<i64> = 12_541..23_458;
+
+/// how often travelling is tried again after navigating failed
+const GO_TO_RETRIES: u32 = 2;
+
+/// the ticks travelling may take before it is tried again. A bot taking
+/// longer is most likely stuck.
+const GO_TO_TICKS: u32 = 20 * 60 * 5;
+
+/// Checks something about the bot. Succeeds if it holds and fails if not.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    FoodBelow(u8),
+    HealthBelow(f32),
+    /// beds can be used
+    Night,
+    /// no slot is free and some of it is loot (which [`Action::Deposit`]
+    /// drops)
+    InventoryFull,
+    /// the blackboard has the key
+    Known(String),
+}
+
+impl Condition {
+    pub fn check(&self, local: &LocalState, global: &GlobalState) -> bool {
+        match self {
+            Self::FoodBelow(food) => local.food < *food,
+            Self::HealthBelow(health) => local.health < *health,
+            Self::Night => NIGHT.contains(&local.time_of_day),
+            Self::InventoryFull => {
+                local.inventory.is_full() && local.inventory.has_loot(&global.block_data)
+            }
+            Self::Known(key) => local.blackboard.contains(key),
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FoodBelow(food) => write!(f, "food_below({food})"),
+            Self::HealthBelow(health) => write!(f, "health_below({health})"),
+            Self::Night => f.write_str("night"),
+            Self::InventoryFull => f.write_str("inventory_full"),
+            Self::Known(key) => write!(f, "known({key})"),
+        }
+    }
+}
+
+/// Starts a [`Task`] which runs until it is done
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// eat whatever food the bot has
+    Eat,
+    /// go to the location
+    GoTo(String),
+    /// go to the bed at the location and sleep until morning
+    Sleep(String),
+    /// mine the regions given to the bots with the `mine` command
+    MineRegion,
+    /// go to the location and drop everything but tools, food, buckets and
+    /// building blocks
+    Deposit(String),
+    /// wait for the given number of ticks
+    Wait(u32),
+    /// do the actions at the same time (i.e., eat while going home). Done
+    /// once all of them are done.
+    All(Vec<Action>),
+}
+
+impl Action {
+    /// the task doing the action. [`None`] if it cannot be done (i.e., the bot
+    /// has no food or the location is not known).
+    pub fn create(
+        &self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &GlobalState,
+    ) -> Option<Box<dyn Task>> {
+        let task: Box<dyn Task> = match self {
+            Self::Eat => {
+                if !local.inventory.switch_food(&global.block_data, out) {
+                    return None;
+                }
+                Box::new(EatTask::default())
+            }
+            Self::GoTo(key) => go_to(local.blackboard.get(key)?),
+            Self::Sleep(key) => {
+                let bed: BlockLocation = local.blackboard.get(key)?;
+                let start = local.physics.location().into();
+                let near = TravelProblem::navigate_near_block(
+                    start,
+                    BlockLocation2D::from(bed),
+                    2.0 * 2.0,
+                    false,
+                );
+                // there is no point in getting to the bed in the morning
+                let travel = Watch::new(NavigateProblem::from(near), NightTask);
+                let mut compound = CompoundTask::default();
+                compound.add(travel).add(SleepTask { bed, used: false });
+                Box::new(compound)
+            }
+            Self::MineRegion => Box::new(MineRegionTask::from(MineRegion::default())),
+            Self::Deposit(key) => {
+                // one stack a tick (more upsets anti cheat) until there is none
+                let drop = Repeat::new(
+                    |_: &mut LocalState, _: &GlobalState| -> Box<dyn Task> {
+                        Box::new(DropStackTask)
+                    },
+                    |local: &LocalState, global: &GlobalState| {
+                        !local.inventory.has_loot(&global.block_data)
+                    },
+                );
+                let mut compound = CompoundTask::default();
+                compound
+                    .add_boxed(go_to(local.blackboard.get(key)?))
+                    .add(drop);
+                Box::new(compound)
+            }
+            Self::Wait(ticks) => Box::new(DelayTask(*ticks)),
+            Self::All(actions) => {
+                let tasks = actions
+                    .iter()
+                    .map(|action| action.create(out, local, global))
+                    .collect::<Option<_>>()?;
+                Box::new(Parallel::new(tasks))
+            }
+        };
+
+        Some(task)
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Eat => f.write_str("eat"),
+            Self::GoTo(key) => write!(f, "go_to({key})"),
+            Self::Sleep(key) => write!(f, "sleep({key})"),
+            Self::MineRegion => f.write_str("mine_region"),
+            Self::Deposit(key) => write!(f, "deposit({key})"),
+            Self::Wait(ticks) => write!(f, "wait({ticks})"),
+            Self::All(actions) => {
+                f.write_str("all(")?;
+                for (idx, action) in actions.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{action}")?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// travel to `goal` (or do nothing if the bot is there)
+fn go_to(goal: BlockLocation) -> Box<dyn Task> {
+    // a retry starts from wherever the bot got stuck
+    let travel = move |local: &mut LocalState, _: &GlobalState| -> Box<dyn Task> {
+        Box::new(Timeout::new(BlockTravelTask::new(goal, local), GO_TO_TICKS))
+    };
+    let away = move |local: &LocalState, _: &GlobalState| {
+        BlockLocation::from(local.physics.location()) != goal
+    };
+    Box::new(Branch::new(away, Retry::new(travel, GO_TO_RETRIES)))
+}
+
+/// Uses the bed and lies in it until morning
+struct SleepTask {
+    bed: BlockLocation,
+    used: bool,
+}
+
+impl Task for SleepTask {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        _: &mut GlobalState,
+    ) -> Status {
+        if !self.used {
+            self.used = true;
+            local.physics.look_at(self.bed.center_bottom());
+            out.swing_arm();
+            out.place_block(self.bed, Face::PosY);
+        }
+
+        Status::from(!NIGHT.contains(&local.time_of_day))
+    }
+}
+
+/// Runs until the night is over and then fails
+struct NightTask;
+
+impl Task for NightTask {
+    fn tick(
+        &mut self,
+        _: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        _: &mut GlobalState,
+    ) -> Status {
+        if NIGHT.contains(&local.time_of_day) {
+            Status::Running
+        } else {
+            Status::Failure
+        }
+    }
+}
+
+/// Drops a single stack of loot. Fails if there is none.
+struct DropStackTask;
+
+impl Task for DropStackTask {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        if local.inventory.drop_loot(&global.block_data, out) {
+            Status::Failure
+        } else {
+            Status::Success
+        }
+    }
+}
@@ -0,0 +1,436 @@
+//! Behavior trees for bots which look after themselves.
+//!
+//! A [`BehaviorTree`] is a [`Task`] which never finishes. Every tick it runs
+//! its tree from the root, so a more important branch (i.e., eating) takes
+//! over as soon as its conditions hold and the action it replaces is
+//! cancelled. Trees are built in code (see [`autonomous`]) or read from
+//! `behaviors/{name}.toml`:
+//!
+//! ```toml
+//! selector = [
+//!     { sequence = [{ condition = { food_below = 10 } }, { action = "eat" }] },
+//!     { sequence = [{ condition = "night" }, { action = { sleep = "bed" } }] },
+//!     { action = "mine_region" },
+//! ]
+//! ```
+//!
+//! Each tick is logged at `trace` level as the nodes that ran with their
+//! status (i.e., `selector(sequence(food_below(10):failure):failure,
+//! mine_region:running):running`).
+
+use std::{fmt::Write, path::PathBuf, time::Instant};
+
+use anyhow::{bail, Context};
+use interfaces::query::TaskInfo;
+use serde::Deserialize;
+use tracing::{debug, trace};
+
+pub use crate::client::tasks::behavior::leaf::{Action, Condition};
+use crate::{
+    client::{
+        state::{global::GlobalState, local::LocalState},
+        tasks::{describe, Status, Task},
+    },
+    protocol::InterfaceOut,
+};
+
+mod leaf;
+
+/// the directory trees are loaded from by name (see [`load`])
+const BEHAVIORS_DIR: &str = "behaviors";
+
+/// A node of a behavior tree
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Node {
+    /// runs its children in order until one does not fail
+    Selector(Vec<Node>),
+    /// runs its children in order until one does not succeed. Children which
+    /// succeeded are skipped until the sequence is done or another branch
+    /// takes over.
+    Sequence(Vec<Node>),
+    /// succeeds if the child fails and the other way around
+    Invert(Box<Node>),
+    /// succeeds even if the child fails
+    Succeed(Box<Node>),
+    Condition(Condition),
+    Action(Action),
+}
+
+impl Node {
+    /// the number of nodes in the tree
+    fn size(&self) -> usize {
+        match self {
+            Self::Selector(children) | Self::Sequence(children) => {
+                1 + children.iter().map(Self::size).sum::<usize>()
+            }
+            Self::Invert(child) | Self::Succeed(child) => 1 + child.size(),
+            Self::Condition(_) | Self::Action(_) => 1,
+        }
+    }
+}
+
+/// Eat when hungry, go home when hurt, sleep at night and deposit loot when
+/// the inventory is full (if `home`, `bed` and `deposit` are on the
+/// blackboard) and mine otherwise.
+pub fn autonomous() -> Node {
+    use Node::{Action as Act, Condition as Cond, Selector, Sequence};
+
+    let known = |key: &str| Cond(Condition::Known(key.to_string()));
+
+    Selector(vec![
+        Sequence(vec![Cond(Condition::FoodBelow(10)), Act(Action::Eat)]),
+        Sequence(vec![
+            Cond(Condition::HealthBelow(8.0)),
+            known("home"),
+            Act(Action::GoTo("home".to_string())),
+        ]),
+        Sequence(vec![
+            Cond(Condition::Night),
+            known("bed"),
+            Act(Action::Sleep("bed".to_string())),
+        ]),
+        Sequence(vec![
+            Cond(Condition::InventoryFull),
+            known("deposit"),
+            Act(Action::Deposit("deposit".to_string())),
+        ]),
+        Act(Action::MineRegion),
+    ])
+}
+
+/// read the tree `name` in [`BEHAVIORS_DIR`] (i.e., `behaviors/farm.toml` for
+/// `farm`). `autonomous` is built in unless there is a file with that name.
+pub fn load(name: &str) -> anyhow::Result<Node> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    if name.is_empty() || !name.chars().all(valid) {
+        bail!("invalid behavior name {name}");
+    }
+
+    let path: PathBuf = [BEHAVIORS_DIR, &format!("{name}.toml")].iter().collect();
+    if name == "autonomous" && !path.exists() {
+        return Ok(autonomous());
+    }
+
+    let source = std::fs::read_to_string(&path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+
+    toml::from_str(&source).with_context(|| format!("invalid behavior tree {}", path.display()))
+}
+
+/// Runs a behavior tree (see the [module docs](self))
+pub struct BehaviorTree {
+    root: Node,
+
+    /// the child each sequence is on, by node id. Ids are the indices of
+    /// nodes in pre-order.
+    progress: Vec<usize>,
+
+    /// the running action and the id of its node
+    running: Option<(usize, Box<dyn Task>)>,
+
+    /// what the last tick did
+    trace: String,
+}
+
+impl BehaviorTree {
+    pub fn new(root: Node) -> Self {
+        Self {
+            progress: vec![0; root.size()],
+            root,
+            running: None,
+            trace: String::new(),
+        }
+    }
+}
+
+/// Everything a tick of a [`BehaviorTree`] works with
+struct Tick<'a> {
+    out: &'a mut dyn InterfaceOut,
+    local: &'a mut LocalState,
+    global: &'a mut GlobalState,
+    progress: &'a mut [usize],
+    running: &'a mut Option<(usize, Box<dyn Task>)>,
+
+    /// the nodes run this tick, by id
+    visited: Vec<bool>,
+    trace: String,
+}
+
+impl Tick<'_> {
+    fn run(&mut self, node: &Node, id: usize) -> Status {
+        self.visited[id] = true;
+
+        let status = match node {
+            Node::Selector(children) => {
+                self.trace.push_str("selector(");
+                let mut status = Status::Failure;
+                let mut child_id = id + 1;
+                for (idx, child) in children.iter().enumerate() {
+                    if idx > 0 {
+                        self.trace.push_str(", ");
+                    }
+                    status = self.run(child, child_id);
+                    if status != Status::Failure {
+                        break;
+                    }
+                    child_id += child.size();
+                }
+                self.trace.push(')');
+                status
+            }
+            Node::Sequence(children) => {
+                self.trace.push_str("sequence(");
+                let start = self.progress[id];
+                let mut child_id = id + 1 + children[..start].iter().map(Node::size).sum::<usize>();
+                let mut status = Status::Success;
+                for (idx, child) in children.iter().enumerate().skip(start) {
+                    if idx > start {
+                        self.trace.push_str(", ");
+                    }
+                    status = self.run(child, child_id);
+                    if status != Status::Success {
+                        break;
+                    }
+                    self.progress[id] = idx + 1;
+                    child_id += child.size();
+                }
+                if status.is_done() {
+                    self.progress[id] = 0;
+                }
+                self.trace.push(')');
+                status
+            }
+            Node::Invert(child) => {
+                self.trace.push_str("invert(");
+                let status = match self.run(child, id + 1) {
+                    Status::Success => Status::Failure,
+                    Status::Failure => Status::Success,
+                    Status::Running => Status::Running,
+                };
+                self.trace.push(')');
+                status
+            }
+            Node::Succeed(child) => {
+                self.trace.push_str("succeed(");
+                let status = match self.run(child, id + 1) {
+                    Status::Running => Status::Running,
+                    _ => Status::Success,
+                };
+                self.trace.push(')');
+                status
+            }
+            Node::Condition(condition) => {
+                let _ = write!(self.trace, "{condition}");
+                if condition.check(self.local, self.global) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            Node::Action(action) => {
+                let _ = write!(self.trace, "{action}");
+                self.act(action, id)
+            }
+        };
+
+        let _ = write!(self.trace, ":{status}");
+        status
+    }
+
+    fn act(&mut self, action: &Action, id: usize) -> Status {
+        let started = matches!(self.running, Some((running, _)) if *running == id);
+        if !started {
+            self.stop();
+            let Some(task) = action.create(self.out, self.local, self.global) else {
+                return Status::Failure;
+            };
+            debug!(%action, "starting action");
+            *self.running = Some((id, task));
+        }
+
+        let Some((_, task)) = self.running.as_mut() else {
+            return Status::Failure;
+        };
+
+        let status = task.tick(self.out, self.local, self.global);
+        if status.is_done() {
+            *self.running = None;
+        }
+        status
+    }
+
+    /// cancel the running action
+    fn stop(&mut self) {
+        if let Some((_, mut task)) = self.running.take() {
+            task.cancel(self.out, self.local, self.global);
+        }
+    }
+}
+
+impl Task for BehaviorTree {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        let visited = vec![false; self.progress.len()];
+        let mut tick = Tick {
+            out,
+            local,
+            global,
+            progress: &mut self.progress,
+            running: &mut self.running,
+            visited,
+            trace: String::new(),
+        };
+
+        tick.run(&self.root, 0);
+
+        // an action which was not reached was taken over by another branch
+        if let Some((id, _)) = tick.running {
+            if !tick.visited[*id] {
+                tick.stop();
+            }
+        }
+
+        // sequences which were not reached start over
+        for (progress, visited) in tick.progress.iter_mut().zip(&tick.visited) {
+            if !visited {
+                *progress = 0;
+            }
+        }
+
+        trace!(tree = %tick.trace, "behavior tick");
+        self.trace = tick.trace;
+
+        Status::Running
+    }
+
+    fn expensive(&mut self, end_by: Instant, local: &mut LocalState, global: &GlobalState) {
+        if let Some((_, task)) = self.running.as_mut() {
+            task.expensive(end_by, local, global);
+        }
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        // the action starts over if the tree runs again
+        if let Some((_, mut task)) = self.running.take() {
+            task.cancel(out, local, global);
+        }
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, self.running.as_ref().map(|(_, task)| task.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use interfaces::types::{BlockKind, BlockLocation};
+
+    use crate::{
+        client::{
+            state::{
+                global::GlobalState,
+                local::{inventory::ItemStack, LocalState},
+            },
+            tasks::{
+                behavior::{autonomous, load, BehaviorTree, Node},
+                Task,
+            },
+        },
+        protocol::MockOut,
+    };
+
+    #[test]
+    fn test_behavior_tree() {
+        let root: Node = toml::from_str(
+            r#"
+            selector = [
+                { sequence = [{ condition = { known = "alarm" } }, { action = { wait = 1 } }] },
+                { invert = { condition = { food_below = 10 } } },
+                { action = { wait = 100 } },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let mut tree = BehaviorTree::new(root);
+        let mut out = MockOut::default();
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+        local.food = 5;
+
+        tree.tick(&mut out, &mut local, &mut global);
+        assert_eq!(
+            tree.trace,
+            "selector(sequence(known(alarm):failure):failure, \
+             invert(food_below(10):success):failure, wait(100):running):running"
+        );
+
+        // the more important branch takes over
+        local.blackboard.set("alarm", true);
+        tree.tick(&mut out, &mut local, &mut global);
+        assert!(tree.trace.ends_with("wait(1):running):running):running"));
+        assert_eq!(tree.running.as_ref().unwrap().0, 3);
+
+        // the sequence remembers that the condition held
+        local.blackboard = LocalState::mock().blackboard;
+        tree.tick(&mut out, &mut local, &mut global);
+        assert_eq!(
+            tree.trace,
+            "selector(sequence(wait(1):success):success):success"
+        );
+
+        local.food = 20;
+        tree.tick(&mut out, &mut local, &mut global);
+        assert!(tree
+            .trace
+            .ends_with("invert(food_below(10):failure):success):success"));
+        assert!(tree.running.is_none());
+
+        // actions at the same time are done once all of them are
+        let all: Node = toml::from_str("action = { all = [{ wait = 0 }, { wait = 2 }] }").unwrap();
+        let mut tree = BehaviorTree::new(all);
+        tree.tick(&mut out, &mut local, &mut global);
+        assert_eq!(tree.trace, "all(wait(0), wait(2)):running");
+        tree.tick(&mut out, &mut local, &mut global);
+        tree.tick(&mut out, &mut local, &mut global);
+        assert_eq!(tree.trace, "all(wait(0), wait(2)):success");
+
+        assert!(load("autonomous").is_ok());
+        assert!(load("../secrets").is_err());
+    }
+
+    #[test]
+    fn test_inventory_full() {
+        let mut tree = BehaviorTree::new(autonomous());
+        let mut out = MockOut::default();
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+        local.food = 20;
+        local.health = 20.0;
+        local.blackboard.set("deposit", BlockLocation::new(0, 1, 0));
+
+        // nothing can be deposited, so the bot keeps mining
+        let cobblestone = || ItemStack::new(BlockKind(4), 64, 0, None);
+        for idx in 9..45 {
+            local.inventory.add(idx, cobblestone());
+        }
+        tree.tick(&mut out, &mut local, &mut global);
+        assert!(tree.trace.contains("inventory_full:failure"));
+        assert!(tree.trace.contains("mine_region"));
+
+        let gravel = ItemStack::new(BlockKind(13), 64, 0, None);
+        local.inventory.add(20, gravel);
+        tree.tick(&mut out, &mut local, &mut global);
+        assert!(tree.trace.contains("deposit(deposit):running"));
+    }
+}
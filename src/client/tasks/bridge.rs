@@ -5,7 +5,7 @@ use crate::{
         pathfind::moves::CardinalDirection,
         physics::{speed::Speed, Line},
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{Status, Task},
     },
     protocol::{Face, InterfaceOut},
     types::{Direction, Displacement},
@@ -35,7 +35,7 @@ impl Task for BridgeTask {
         _out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         _global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
         let displacement = Displacement::from(self.direction.unit_change());
 
         let direction = Direction::from(-displacement);
@@ -62,6 +62,6 @@ impl Task for BridgeTask {
             self.count -= 1;
         }
 
-        self.count == 0
+        Status::from(self.count == 0)
     }
 }
@@ -4,7 +4,7 @@ use crate::{
     client::{
         physics::{speed::Speed, Line},
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{Status, Task},
     },
     protocol::InterfaceOut,
 };
@@ -16,7 +16,7 @@ impl Task for CenterTask {
         _out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         _global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
         const THR: f64 = 0.05;
         const VEL_THR: f64 = 0.05;
         let loc = local.physics.location();
@@ -25,13 +25,13 @@ impl Task for CenterTask {
         center_loc.y = loc.y;
 
         if loc.dist2(center_loc) < THR * THR && vel.mag2() < VEL_THR * VEL_THR {
-            return true;
+            return Status::Success;
         }
 
         local.physics.look_at(center_loc);
         local.physics.line(Line::Forward);
         local.physics.speed(Speed::SNEAK);
 
-        false
+        Status::Running
     }
 }
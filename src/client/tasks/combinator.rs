@@ -0,0 +1,590 @@
+//! Tasks which run other tasks. [`CompoundTask`] runs tasks in sequence (and
+//! stops at the first failure); these add the rest.
+//!
+//! [`CompoundTask`]: crate::client::tasks::compound::CompoundTask
+
+use std::time::Instant;
+
+use interfaces::query::TaskInfo;
+
+use crate::{
+    client::{
+        state::{global::GlobalState, local::LocalState},
+        tasks::{describe, lazy::Lazy, Status, Task},
+    },
+    protocol::InterfaceOut,
+};
+
+/// Runs a task created by `create` and creates it again every time it fails,
+/// at most `retries` times
+pub struct Retry<T: Lazy> {
+    create: T,
+    retries: u32,
+    current: Option<Box<dyn Task>>,
+}
+
+impl<T: Lazy> Retry<T> {
+    pub const fn new(create: T, retries: u32) -> Self {
+        Self {
+            create,
+            retries,
+            current: None,
+        }
+    }
+
+    fn get(&mut self, local: &mut LocalState, global: &GlobalState) -> &mut dyn Task {
+        let create = &self.create;
+        self.current
+            .get_or_insert_with(|| create.create(local, global))
+            .as_mut()
+    }
+}
+
+impl<T: Lazy + Send> Task for Retry<T> {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        let status = self.get(local, global).tick(out, local, global);
+        if status == Status::Failure && self.retries > 0 {
+            self.retries -= 1;
+            self.current = None;
+            return Status::Running;
+        }
+        status
+    }
+
+    fn expensive(&mut self, end_by: Instant, local: &mut LocalState, global: &GlobalState) {
+        self.get(local, global).expensive(end_by, local, global);
+    }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        if let Some(current) = self.current.as_mut() {
+            current.resume(local, global);
+        }
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        if let Some(current) = self.current.as_mut() {
+            current.cancel(out, local, global);
+        }
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, self.current.as_deref())
+    }
+}
+
+/// Runs a task for at most `ticks` ticks. The task is cancelled and fails if
+/// it is not done by then.
+pub struct Timeout {
+    task: Box<dyn Task>,
+    ticks: u32,
+}
+
+impl Timeout {
+    pub fn new(task: impl Task + 'static, ticks: u32) -> Self {
+        Self {
+            task: Box::new(task),
+            ticks,
+        }
+    }
+}
+
+impl Task for Timeout {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        if self.ticks == 0 {
+            self.task.cancel(out, local, global);
+            return Status::Failure;
+        }
+
+        self.ticks -= 1;
+        self.task.tick(out, local, global)
+    }
+
+    fn expensive(&mut self, end_by: Instant, local: &mut LocalState, global: &GlobalState) {
+        self.task.expensive(end_by, local, global);
+    }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        self.task.resume(local, global);
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        self.task.cancel(out, local, global);
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, Some(self.task.as_ref()))
+    }
+}
+
+/// Runs tasks created by `create` one after another until `until` is true.
+/// `until` is checked before each task is created. Fails as soon as one of the
+/// tasks fails.
+pub struct Repeat<T: Lazy, C> {
+    create: T,
+    until: C,
+    current: Option<Box<dyn Task>>,
+}
+
+impl<T: Lazy, C: Fn(&LocalState, &GlobalState) -> bool> Repeat<T, C> {
+    pub const fn new(create: T, until: C) -> Self {
+        Self {
+            create,
+            until,
+            current: None,
+        }
+    }
+}
+
+impl<T, C> Task for Repeat<T, C>
+where
+    T: Lazy + Send,
+    C: Fn(&LocalState, &GlobalState) -> bool + Send,
+{
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        let task = match self.current.as_mut() {
+            Some(task) => task,
+            None if (self.until)(local, global) => return Status::Success,
+            None => self.current.insert(self.create.create(local, global)),
+        };
+
+        // the next task is only created next tick so tasks which are done
+        // right away cannot hold up the game loop
+        match task.tick(out, local, global) {
+            Status::Success => {
+                self.current = None;
+                Status::Running
+            }
+            status => status,
+        }
+    }
+
+    fn expensive(&mut self, end_by: Instant, local: &mut LocalState, global: &GlobalState) {
+        if let Some(current) = self.current.as_mut() {
+            current.expensive(end_by, local, global);
+        }
+    }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        if let Some(current) = self.current.as_mut() {
+            current.resume(local, global);
+        }
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        if let Some(current) = self.current.as_mut() {
+            current.cancel(out, local, global);
+        }
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, self.current.as_deref())
+    }
+}
+
+/// Runs two tasks at the same time. Whichever is done first decides the status
+/// and the other one is cancelled.
+pub struct Race {
+    first: Box<dyn Task>,
+    second: Box<dyn Task>,
+}
+
+impl Race {
+    pub fn new(first: impl Task + 'static, second: impl Task + 'static) -> Self {
+        Self {
+            first: Box::new(first),
+            second: Box::new(second),
+        }
+    }
+}
+
+impl Task for Race {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        let status = self.first.tick(out, local, global);
+        if status.is_done() {
+            self.second.cancel(out, local, global);
+            return status;
+        }
+
+        let status = self.second.tick(out, local, global);
+        if status.is_done() {
+            self.first.cancel(out, local, global);
+        }
+        status
+    }
+
+    fn expensive(&mut self, end_by: Instant, local: &mut LocalState, global: &GlobalState) {
+        self.first.expensive(end_by, local, global);
+        self.second.expensive(end_by, local, global);
+    }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        self.first.resume(local, global);
+        self.second.resume(local, global);
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        self.first.cancel(out, local, global);
+        self.second.cancel(out, local, global);
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, Some(self.first.as_ref()))
+    }
+}
+
+/// Runs tasks at the same time. Succeeds once all of them succeeded and fails
+/// as soon as one fails (the others are cancelled).
+pub struct Parallel {
+    /// the tasks which are not done yet
+    tasks: Vec<Box<dyn Task>>,
+}
+
+impl Parallel {
+    pub fn new(tasks: Vec<Box<dyn Task>>) -> Self {
+        Self { tasks }
+    }
+}
+
+impl Task for Parallel {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        let mut idx = 0;
+        while idx < self.tasks.len() {
+            match self.tasks[idx].tick(out, local, global) {
+                Status::Running => idx += 1,
+                Status::Success => {
+                    self.tasks.remove(idx);
+                }
+                Status::Failure => {
+                    self.tasks.remove(idx);
+                    self.cancel(out, local, global);
+                    return Status::Failure;
+                }
+            }
+        }
+
+        Status::from(self.tasks.is_empty())
+    }
+
+    fn expensive(&mut self, end_by: Instant, local: &mut LocalState, global: &GlobalState) {
+        for task in &mut self.tasks {
+            task.expensive(end_by, local, global);
+        }
+    }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        for task in &mut self.tasks {
+            task.resume(local, global);
+        }
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        for task in &mut self.tasks {
+            task.cancel(out, local, global);
+        }
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, self.tasks.first().map(AsRef::as_ref))
+    }
+}
+
+/// Runs `watcher` alongside `task` (i.e., to keep an eye on health). Done when
+/// `task` is done. If the watcher fails, `task` is cancelled and this fails
+/// too. A watcher which succeeds simply stops running.
+pub struct Watch {
+    task: Box<dyn Task>,
+    watcher: Option<Box<dyn Task>>,
+}
+
+impl Watch {
+    pub fn new(task: impl Task + 'static, watcher: impl Task + 'static) -> Self {
+        Self {
+            task: Box::new(task),
+            watcher: Some(Box::new(watcher)),
+        }
+    }
+}
+
+impl Task for Watch {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        if let Some(watcher) = self.watcher.as_mut() {
+            match watcher.tick(out, local, global) {
+                Status::Running => {}
+                Status::Success => self.watcher = None,
+                Status::Failure => {
+                    self.task.cancel(out, local, global);
+                    return Status::Failure;
+                }
+            }
+        }
+
+        let status = self.task.tick(out, local, global);
+        if status.is_done() {
+            if let Some(watcher) = self.watcher.as_mut() {
+                watcher.cancel(out, local, global);
+            }
+        }
+        status
+    }
+
+    fn expensive(&mut self, end_by: Instant, local: &mut LocalState, global: &GlobalState) {
+        self.task.expensive(end_by, local, global);
+        if let Some(watcher) = self.watcher.as_mut() {
+            watcher.expensive(end_by, local, global);
+        }
+    }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        self.task.resume(local, global);
+        if let Some(watcher) = self.watcher.as_mut() {
+            watcher.resume(local, global);
+        }
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        self.task.cancel(out, local, global);
+        if let Some(watcher) = self.watcher.as_mut() {
+            watcher.cancel(out, local, global);
+        }
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, Some(self.task.as_ref()))
+    }
+}
+
+/// Runs `then` if `condition` is true when the task starts and `otherwise`
+/// (if any) if it is not. Succeeds right away if there is nothing to run.
+pub struct Branch<C> {
+    condition: C,
+    then: Option<Box<dyn Task>>,
+    otherwise: Option<Box<dyn Task>>,
+    chosen: Option<Box<dyn Task>>,
+}
+
+impl<C: Fn(&LocalState, &GlobalState) -> bool> Branch<C> {
+    pub fn new(condition: C, then: impl Task + 'static) -> Self {
+        Self {
+            condition,
+            then: Some(Box::new(then)),
+            otherwise: None,
+            chosen: None,
+        }
+    }
+
+    /// the task to run if the condition is false
+    pub fn otherwise(mut self, task: impl Task + 'static) -> Self {
+        self.otherwise = Some(Box::new(task));
+        self
+    }
+}
+
+impl<C: Fn(&LocalState, &GlobalState) -> bool + Send> Task for Branch<C> {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        if self.chosen.is_none() {
+            self.chosen = if (self.condition)(local, global) {
+                self.then.take()
+            } else {
+                self.otherwise.take()
+            };
+        }
+
+        let Some(chosen) = self.chosen.as_mut() else {
+            return Status::Success;
+        };
+        chosen.tick(out, local, global)
+    }
+
+    fn expensive(&mut self, end_by: Instant, local: &mut LocalState, global: &GlobalState) {
+        if let Some(chosen) = self.chosen.as_mut() {
+            chosen.expensive(end_by, local, global);
+        }
+    }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        if let Some(chosen) = self.chosen.as_mut() {
+            chosen.resume(local, global);
+        }
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        if let Some(chosen) = self.chosen.as_mut() {
+            chosen.cancel(out, local, global);
+        }
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, self.chosen.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        client::{
+            state::{global::GlobalState, local::LocalState},
+            tasks::{
+                combinator::{Branch, Parallel, Race, Repeat, Retry, Timeout, Watch},
+                delay::DelayTask,
+                Status, Task,
+            },
+        },
+        protocol::{InterfaceOut, MockOut},
+    };
+
+    /// fails right away
+    struct Fail;
+
+    impl Task for Fail {
+        fn tick(
+            &mut self,
+            _: &mut dyn InterfaceOut,
+            _: &mut LocalState,
+            _: &mut GlobalState,
+        ) -> Status {
+            Status::Failure
+        }
+    }
+
+    /// ticks `task` until it is done (or 100 ticks passed) and returns the
+    /// status and the number of ticks it took
+    fn run(mut task: impl Task) -> (Status, usize) {
+        let mut out = MockOut::default();
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+
+        for ticks in 1..=100 {
+            let status = task.tick(&mut out, &mut local, &mut global);
+            if status.is_done() {
+                return (status, ticks);
+            }
+        }
+        (Status::Running, 100)
+    }
+
+    #[test]
+    fn test_combinators() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counted = attempts.clone();
+        let retry = Retry::new(
+            move |_: &mut LocalState, _: &GlobalState| -> Box<dyn Task> {
+                counted.fetch_add(1, Ordering::Relaxed);
+                Box::new(Fail)
+            },
+            2,
+        );
+        assert_eq!(run(retry).0, Status::Failure);
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        assert_eq!(run(Timeout::new(DelayTask(5), 3)), (Status::Failure, 4));
+        assert_eq!(run(Timeout::new(DelayTask(2), 3)), (Status::Success, 3));
+
+        let made = Arc::new(AtomicUsize::new(0));
+        let counted = made.clone();
+        let repeat = Repeat::new(
+            move |_: &mut LocalState, _: &GlobalState| -> Box<dyn Task> {
+                counted.fetch_add(1, Ordering::Relaxed);
+                Box::new(DelayTask(1))
+            },
+            move |_: &LocalState, _: &GlobalState| made.load(Ordering::Relaxed) == 3,
+        );
+        assert_eq!(run(repeat), (Status::Success, 7));
+
+        assert_eq!(
+            run(Race::new(DelayTask(5), DelayTask(1))),
+            (Status::Success, 2)
+        );
+        assert_eq!(run(Race::new(DelayTask(5), Fail)), (Status::Failure, 1));
+
+        let parallel = Parallel::new(vec![Box::new(DelayTask(1)), Box::new(DelayTask(3))]);
+        assert_eq!(run(parallel), (Status::Success, 4));
+        let parallel = Parallel::new(vec![Box::new(DelayTask(3)), Box::new(Fail)]);
+        assert_eq!(run(parallel), (Status::Failure, 1));
+
+        assert_eq!(
+            run(Watch::new(DelayTask(3), DelayTask(0))),
+            (Status::Success, 4)
+        );
+        assert_eq!(run(Watch::new(DelayTask(3), Fail)), (Status::Failure, 1));
+
+        let branch = Branch::new(|local: &LocalState, _: &GlobalState| local.ticks == 0, Fail);
+        assert_eq!(run(branch.otherwise(DelayTask(0))).0, Status::Failure);
+        let branch = Branch::new(|_: &LocalState, _: &GlobalState| false, Fail);
+        assert_eq!(run(branch), (Status::Success, 1));
+    }
+}
@@ -1,9 +1,11 @@
 use std::{collections::VecDeque, time::Instant};
 
+use interfaces::query::TaskInfo;
+
 use crate::{
     client::{
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{describe, Status, Task},
     },
     protocol::InterfaceOut,
 };
@@ -11,6 +13,9 @@ use crate::{
 #[derive(Default)]
 pub struct CompoundTask {
     tasks: VecDeque<Box<dyn Task>>,
+
+    /// the number of tasks which are done
+    done: usize,
 }
 
 impl CompoundTask {
@@ -19,6 +24,16 @@ impl CompoundTask {
         self
     }
 
+    pub fn add_boxed(&mut self, task: Box<dyn Task>) -> &mut Self {
+        self.tasks.push_back(task);
+        self
+    }
+
+    /// the task being run
+    pub fn current(&self) -> Option<&dyn Task> {
+        self.tasks.front().map(AsRef::as_ref)
+    }
+
     #[allow(unused)]
     pub fn prepend(&mut self, task: impl Task + 'static) {
         self.tasks.push_front(Box::new(task));
@@ -31,19 +46,19 @@ impl Task for CompoundTask {
         out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
         // this is so we can do multiple 0-tick tasks in a gametick
         while let Some(front) = self.tasks.front_mut() {
-            let finished = front.tick(out, local, global);
-
-            if finished {
-                self.tasks.pop_front();
-            } else {
-                return false;
+            match front.tick(out, local, global) {
+                Status::Success => {
+                    self.tasks.pop_front();
+                    self.done += 1;
+                }
+                status => return status,
             }
         }
 
-        true
+        Status::Success
     }
 
     fn expensive(&mut self, end_at: Instant, local: &mut LocalState, global: &GlobalState) {
@@ -52,4 +67,61 @@ impl Task for CompoundTask {
             Some(res) => res.expensive(end_at, local, global),
         };
     }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        if let Some(front) = self.tasks.front_mut() {
+            front.resume(local, global);
+        }
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        if let Some(front) = self.tasks.front_mut() {
+            front.cancel(out, local, global);
+        }
+    }
+
+    /// the fraction of tasks which are done
+    #[allow(clippy::cast_precision_loss)]
+    fn progress(&self) -> Option<f32> {
+        let total = self.done + self.tasks.len();
+        (total > 0).then(|| self.done as f32 / total as f32)
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, self.current())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        client::{
+            state::{global::GlobalState, local::LocalState},
+            tasks::{compound::CompoundTask, delay::DelayTask, eat::EatTask, Task},
+        },
+        protocol::MockOut,
+    };
+
+    #[test]
+    fn test_describe() {
+        let mut out = MockOut::default();
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+
+        let mut compound = CompoundTask::default();
+        compound.add(DelayTask(0)).add(EatTask::default());
+
+        for _ in 0..4 {
+            compound.tick(&mut out, &mut local, &mut global);
+        }
+
+        let activity = compound.describe();
+        assert_eq!(activity.progress, Some(0.5));
+        assert_eq!(activity.to_string(), "CompoundTask 50% > EatTask 10%");
+    }
 }
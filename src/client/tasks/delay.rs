@@ -1,7 +1,7 @@
 use crate::{
     client::{
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{Status, Task},
     },
     protocol::InterfaceOut,
 };
@@ -21,13 +21,13 @@ impl Task for DelayTask {
         _out: &mut dyn InterfaceOut,
         _local: &mut LocalState,
         _global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
         let ticks_left = self.0;
         if ticks_left == 0 {
-            true
+            Status::Success
         } else {
             self.0 -= 1;
-            false
+            Status::Running
         }
     }
 }
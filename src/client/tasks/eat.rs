@@ -1,7 +1,7 @@
 use crate::{
     client::{
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{Status, Task},
     },
     protocol::InterfaceOut,
 };
@@ -25,7 +25,7 @@ impl Task for EatTask {
         out: &mut dyn InterfaceOut,
         _: &mut LocalState,
         _: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
         // start eating
         if self.ticks == TICKS {
             out.use_item();
@@ -33,10 +33,27 @@ impl Task for EatTask {
 
         if self.ticks == 0 {
             out.finish_eating();
-            true
+            Status::Success
         } else {
             self.ticks -= 1;
-            false
+            Status::Running
         }
     }
+
+    fn resume(&mut self, _: &mut LocalState, _: &GlobalState) {
+        // eating was stopped so it has to start over
+        self.ticks = TICKS;
+    }
+
+    fn cancel(&mut self, out: &mut dyn InterfaceOut, _: &mut LocalState, _: &mut GlobalState) {
+        // release the use key
+        if self.ticks < TICKS {
+            out.finish_eating();
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn progress(&self) -> Option<f32> {
+        Some(1.0 - self.ticks as f32 / TICKS as f32)
+    }
 }
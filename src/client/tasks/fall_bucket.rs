@@ -3,7 +3,7 @@ use interfaces::types::{BlockLocation, BlockState, SimpleType};
 use crate::{
     client::{
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{Status, Task},
     },
     protocol::InterfaceOut,
 };
@@ -22,7 +22,7 @@ impl Task for FallBucketTask {
         out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
         const BUCKET_LEAVE_TICKS: u32 = 10;
 
         if self.placed {
@@ -41,10 +41,10 @@ impl Task for FallBucketTask {
             // this is so we don't have any conflicts with other tasks placing stuff and
             // potentially triggering anti-cheat
             if self.ticks_since_place > BUCKET_LEAVE_TICKS + 1 {
-                return true;
+                return Status::Success;
             }
 
-            return false;
+            return Status::Running;
         }
 
         let current_loc = local.physics.location();
@@ -57,7 +57,7 @@ impl Task for FallBucketTask {
                 } else {
                     let height = local.physics.location().y;
                     if height - (f64::from(location.y) + 1.0) < 3.0 {
-                        return true;
+                        return Status::Success;
                     }
                 }
 
@@ -66,7 +66,7 @@ impl Task for FallBucketTask {
                 if dy < 3.4 {
                     // we don't have to place when going into water
                     if global.blocks.get_block_simple(location) == Some(SimpleType::Water) {
-                        return true;
+                        return Status::Success;
                     }
 
                     out.use_item();
@@ -81,6 +81,6 @@ impl Task for FallBucketTask {
 
         self.iter = true;
 
-        false
+        Status::Running
     }
 }
@@ -1,7 +1,7 @@
 use crate::{
     client::{
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{Status, Task},
     },
     protocol::InterfaceOut,
     types::Displacement,
@@ -23,7 +23,7 @@ impl Task for HitEntityTask {
         out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
         let mut action = || {
             let entity_loc = global.entities.by_id(self.id)?.location;
 
@@ -38,7 +38,9 @@ impl Task for HitEntityTask {
             Some(())
         };
 
-        action();
-        true
+        match action() {
+            Some(()) => Status::Success,
+            None => Status::Failure,
+        }
     }
 }
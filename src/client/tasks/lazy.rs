@@ -1,9 +1,11 @@
 use std::time::Instant;
 
+use interfaces::query::TaskInfo;
+
 use crate::{
     client::{
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{task_name, Status, Task},
     },
     protocol::InterfaceOut,
 };
@@ -17,6 +19,12 @@ pub trait Lazy {
     fn create(&self, local: &mut LocalState, global: &GlobalState) -> Box<dyn Task>;
 }
 
+impl<F: Fn(&mut LocalState, &GlobalState) -> Box<dyn Task>> Lazy for F {
+    fn create(&self, local: &mut LocalState, global: &GlobalState) -> Box<dyn Task> {
+        self(local, global)
+    }
+}
+
 impl<T: Lazy> From<T> for LazyTask<T> {
     fn from(block: T) -> Self {
         Self {
@@ -44,7 +52,7 @@ impl<T: Lazy + Send> Task for LazyTask<T> {
         out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
         let task = self.get(local, global);
         task.tick(out, local, global)
     }
@@ -53,4 +61,33 @@ impl<T: Lazy + Send> Task for LazyTask<T> {
         let task = self.get(local, global);
         task.expensive(end_at, local, global);
     }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.resume(local, global);
+        }
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.cancel(out, local, global);
+        }
+    }
+
+    /// the task once it is created
+    fn describe(&self) -> TaskInfo {
+        self.inner.as_ref().map_or_else(
+            || TaskInfo {
+                name: task_name(std::any::type_name::<T>()),
+                progress: None,
+                current: None,
+            },
+            |inner| inner.describe(),
+        )
+    }
 }
@@ -1,9 +1,11 @@
 use std::time::Instant;
 
+use interfaces::query::TaskInfo;
+
 use crate::{
     client::{
         state::{global::GlobalState, local::LocalState},
-        tasks::{stream::TaskStream, Task},
+        tasks::{stream::TaskStream, task_name, Status, Task},
     },
     protocol::InterfaceOut,
 };
@@ -11,6 +13,9 @@ use crate::{
 pub struct LazyStream<T: TaskStream> {
     current: Option<Box<dyn Task>>,
     create_task: T,
+
+    /// the progress of the stream as of the last tick
+    progress: Option<f32>,
 }
 
 impl<T: TaskStream> From<T> for LazyStream<T> {
@@ -18,6 +23,7 @@ impl<T: TaskStream> From<T> for LazyStream<T> {
         Self {
             create_task,
             current: None,
+            progress: None,
         }
     }
 }
@@ -44,17 +50,17 @@ impl<T: TaskStream + Send> Task for LazyStream<T> {
         out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
+        self.progress = self.create_task.progress(local);
+
         while let Some(task) = self.get(out, local, global) {
-            let finished_subtask: bool = task.tick(out, local, global);
-            if finished_subtask {
-                self.current = None;
-            } else {
-                return false;
+            match task.tick(out, local, global) {
+                Status::Success => self.current = None,
+                status => return status,
             }
         }
 
-        true
+        Status::Success
     }
 
     fn expensive(&mut self, end_by: Instant, local: &mut LocalState, global: &GlobalState) {
@@ -63,4 +69,37 @@ impl<T: TaskStream + Send> Task for LazyStream<T> {
         };
         current.expensive(end_by, local, global);
     }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        if let Some(current) = self.current.as_mut() {
+            current.resume(local, global);
+        }
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        if let Some(current) = self.current.as_mut() {
+            current.cancel(out, local, global);
+        }
+    }
+
+    fn progress(&self) -> Option<f32> {
+        self.progress
+    }
+
+    /// named after the stream (i.e., `MineRegion`)
+    fn describe(&self) -> TaskInfo {
+        TaskInfo {
+            name: task_name(std::any::type_name::<T>()),
+            progress: self.progress,
+            current: self
+                .current
+                .as_ref()
+                .map(|current| Box::new(current.describe())),
+        }
+    }
 }
@@ -3,14 +3,18 @@ use interfaces::types::{BlockLocation, BlockState};
 use crate::{
     client::{
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{Status, Task},
     },
     protocol::{Face, InterfaceOut, Mine},
 };
 
 pub struct MineTask {
     ticks: usize,
+    /// the ticks it takes to mine the block
+    total: usize,
     first: bool,
+    /// another task ran since mining started
+    resumed: bool,
     location: BlockLocation,
     face: Face,
 }
@@ -30,9 +34,11 @@ impl MineTask {
 
         Self {
             ticks,
+            total: ticks,
             location,
             face: Face::PosY,
             first: true,
+            resumed: false,
         }
     }
 
@@ -47,7 +53,19 @@ impl Task for MineTask {
         out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
+        // the bot may hold something else now and has to start mining over
+        if self.resumed {
+            // the chunk may have been unloaded while another task ran
+            if global.blocks.get_block_kind(self.location).is_none() {
+                return Status::Failure;
+            }
+
+            let face = self.face;
+            *self = Self::new(self.location, out, local, global);
+            self.face = face;
+        }
+
         let look_loc = self.location.faces()[self.face as usize];
         local.physics.look_at(look_loc);
 
@@ -63,10 +81,63 @@ impl Task for MineTask {
         if self.ticks == 0 {
             out.mine(self.location, Mine::Finished, self.face);
             global.blocks.set_block(self.location, BlockState::AIR);
-            true
+            Status::Success
         } else {
             self.ticks -= 1;
-            false
+            Status::Running
         }
     }
+
+    fn resume(&mut self, _local: &mut LocalState, _global: &GlobalState) {
+        self.resumed = true;
+    }
+
+    fn cancel(&mut self, out: &mut dyn InterfaceOut, _: &mut LocalState, _: &mut GlobalState) {
+        // stop digging so the block does not stay half broken
+        if !self.first {
+            out.mine(self.location, Mine::Cancel, self.face);
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn progress(&self) -> Option<f32> {
+        Some(1.0 - self.ticks as f32 / self.total as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use interfaces::types::BlockLocation;
+
+    use crate::{
+        client::{
+            state::{global::GlobalState, local::LocalState},
+            tasks::{mine::MineTask, Status, Task},
+        },
+        protocol::MockOut,
+        storage::blocks::WorldBlocks,
+    };
+
+    #[test]
+    fn test_resume_unloaded() {
+        let mut out = MockOut::default();
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+        global.blocks = WorldBlocks::flat();
+
+        let location = BlockLocation::new(0, 0, 0);
+        let mut task = MineTask::new(location, &mut out, &mut local, &global);
+        assert_eq!(
+            task.tick(&mut out, &mut local, &mut global),
+            Status::Running
+        );
+
+        // the chunk is unloaded while another task runs
+        task.resume(&mut local, &global);
+        global.blocks = WorldBlocks::default();
+        assert_eq!(
+            task.tick(&mut out, &mut local, &mut global),
+            Status::Failure
+        );
+    }
 }
@@ -1,16 +1,30 @@
+use interfaces::types::{BlockLocation, BlockLocation2D};
+
 use crate::{
     client::{
         pathfind::implementations::no_vehicle::TravelProblem,
-        state::{global::GlobalState, local::LocalState},
+        state::{
+            global::{mine_alloc::MineAlloc, GlobalState},
+            local::LocalState,
+        },
         tasks::{
-            compound::CompoundTask, lazy::LazyTask, navigate::NavigateProblem,
-            safe_mine_coord::SafeMineRegion, stream::TaskStream, Task,
+            compound::CompoundTask, lazy::LazyTask, mine_column::MineColumn,
+            navigate::NavigateProblem, safe_mine_coord::SafeMineRegion, stream::TaskStream, Task,
         },
     },
     protocol::InterfaceOut,
 };
 
-pub struct MineRegion;
+/// Mines the regions allocated by [`MineAlloc`] one after another
+#[derive(Default)]
+pub struct MineRegion {
+    /// the center of the region being mined
+    center: Option<BlockLocation2D>,
+
+    /// the highest the bot has been in the region. Regions are mined top
+    /// down to [`MineColumn::MIN_MINE_LOC`].
+    top: i16,
+}
 
 impl TaskStream for MineRegion {
     fn poll(
@@ -22,6 +36,9 @@ impl TaskStream for MineRegion {
         let goal = global.mine.obtain_region()?;
         let start = local.physics.location();
 
+        self.center = Some(goal);
+        self.top = MineColumn::MIN_MINE_LOC;
+
         let mut compound = CompoundTask::default();
         let problem = TravelProblem::navigate_near_block(start.into(), goal, 0.0, false);
         let nav = NavigateProblem::from(problem);
@@ -30,4 +47,66 @@ impl TaskStream for MineRegion {
 
         Some(Box::new(compound))
     }
+
+    /// how far down the allocated region has been mined
+    fn progress(&mut self, local: &LocalState) -> Option<f32> {
+        let center = self.center?;
+        let location = BlockLocation::from(local.physics.location());
+
+        let in_region = (location.x - center.x).abs() <= MineAlloc::REGION_R
+            && (location.z - center.z).abs() <= MineAlloc::REGION_R;
+        if in_region {
+            self.top = self.top.max(location.y);
+        }
+
+        let depth = self.top - MineColumn::MIN_MINE_LOC;
+        if depth <= 0 {
+            return Some(0.0);
+        }
+
+        let mined = f32::from(self.top - location.y) / f32::from(depth);
+        Some(mined.clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use interfaces::types::{BlockLocation2D, Location};
+
+    use crate::{
+        client::{
+            state::{global::GlobalState, local::LocalState},
+            tasks::{mine_region::MineRegion, stream::TaskStream},
+        },
+        protocol::MockOut,
+    };
+
+    #[test]
+    fn test_progress() {
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+        let mut out = MockOut::default();
+
+        let mut stream = MineRegion::default();
+        assert_eq!(stream.progress(&local), None);
+
+        // the region is centered on (3, 3)
+        local.physics.teleport(Location::new(100.5, 70.0, 100.5));
+        global
+            .mine
+            .mine(BlockLocation2D::new(0, 0), BlockLocation2D::new(6, 6), None);
+        assert!(stream.poll(&mut out, &mut local, &mut global).is_some());
+
+        // travelling to the region
+        assert_eq!(stream.progress(&local), Some(0.0));
+
+        local.physics.teleport(Location::new(3.5, 61.0, 3.5));
+        assert_eq!(stream.progress(&local), Some(0.0));
+
+        local.physics.teleport(Location::new(2.5, 36.0, 4.5));
+        assert_eq!(stream.progress(&local), Some(0.5));
+
+        local.physics.teleport(Location::new(3.5, 11.0, 3.5));
+        assert_eq!(stream.progress(&local), Some(1.0));
+    }
 }
@@ -1,5 +1,9 @@
-use std::time::Instant;
+use std::{
+    fmt::{Display, Formatter},
+    time::Instant,
+};
 
+use interfaces::query::TaskInfo;
 use lazy::LazyTask;
 use mine_goto::GoMineTop;
 use mine_region::MineRegion;
@@ -11,12 +15,15 @@ use crate::{
             attack_entity::AttackEntity, lazy_stream::LazyStream, safe_mine_coord::SafeMineRegion,
         },
     },
+    metrics::short_type_name,
     protocol::InterfaceOut,
 };
 
 pub mod attack_entity;
+pub mod behavior;
 pub mod bridge;
 pub mod center;
+pub mod combinator;
 pub mod compound;
 pub mod delay;
 pub mod eat;
@@ -33,17 +40,56 @@ pub mod navigate;
 pub mod pillar;
 pub mod pillar_and_mine;
 pub mod safe_mine_coord;
+pub mod script;
 pub mod stream;
 
+/// What a [`Task`] did in a tick
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// the task is not done yet
+    Running,
+    /// the task is done
+    Success,
+    /// the task is done but could not do what it was meant to (i.e., the
+    /// entity to hit does not exist)
+    Failure,
+}
+
+impl Status {
+    pub const fn is_done(self) -> bool {
+        !matches!(self, Self::Running)
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Running => "running",
+            Self::Success => "success",
+            Self::Failure => "failure",
+        })
+    }
+}
+
+impl From<bool> for Status {
+    /// [`Status::Success`] if done
+    fn from(done: bool) -> Self {
+        if done {
+            Self::Success
+        } else {
+            Self::Running
+        }
+    }
+}
+
 /// Must be Send because expensive is called in a multi-threaded environment
 pub trait Task: Send {
-    /// return true if done
     fn tick(
         &mut self,
         out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         global: &mut GlobalState,
-    ) -> bool;
+    ) -> Status;
 
     /// Do an expensive part of the task. This is done in a multi-threaded
     /// environment. An example of This has a default implementation of
@@ -52,6 +98,61 @@ pub trait Task: Send {
     /// called every game loop cycle so if the task hasn't finished it by
     /// {`end_by`} it should instead until this function is called again.
     fn expensive(&mut self, _end_by: Instant, _local: &mut LocalState, _global: &GlobalState) {}
+
+    /// The task was interrupted by a more urgent one (see
+    /// [`crate::client::bot::Priority`]) and is about to run again. Tasks
+    /// which depend on where the bot was or what it held (i.e., a path or a
+    /// block being mined) should start over. This has a default
+    /// implementation of nothing.
+    fn resume(&mut self, _local: &mut LocalState, _global: &GlobalState) {}
+
+    /// The task stopped running before it was done because it was cancelled
+    /// or interrupted (in which case [`Task::resume`] is called before it runs
+    /// again). Tasks which started something that outlasts a tick (i.e.,
+    /// mining a block or eating) should stop it so the bot is left in a
+    /// neutral state. This has a default implementation of nothing.
+    fn cancel(
+        &mut self,
+        _out: &mut dyn InterfaceOut,
+        _local: &mut LocalState,
+        _global: &mut GlobalState,
+    ) {
+    }
+
+    /// How far the task is from 0 to 1 (i.e., how much of the path has been
+    /// walked). This has a default implementation of [`None`] for tasks which
+    /// do not know.
+    fn progress(&self) -> Option<f32> {
+        None
+    }
+
+    /// What the task is doing. Shown by the `state` command and the
+    /// `get_bots` query. Tasks which run other tasks should add the one they
+    /// are on with [`describe`].
+    fn describe(&self) -> TaskInfo {
+        describe(self, None)
+    }
+
+    /// The type name of the task. Used to count tasks by type in metrics.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// the [`TaskInfo`] of `task` which is on `current`
+pub fn describe(task: &(impl Task + ?Sized), current: Option<&dyn Task>) -> TaskInfo {
+    TaskInfo {
+        name: task_name(task.name()),
+        progress: task.progress(),
+        current: current.map(|current| Box::new(current.describe())),
+    }
+}
+
+/// the name of a task type without its path or generics (i.e., `LazyStream`
+/// for `LazyStream<MineRegion>`)
+pub fn task_name(type_name: &str) -> String {
+    let name = type_name.split('<').next().unwrap_or_default();
+    short_type_name(name)
 }
 
 pub type GoMineTopTask = LazyTask<GoMineTop>;
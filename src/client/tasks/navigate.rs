@@ -1,10 +1,13 @@
 use std::time::Instant;
 
-use interfaces::types::{BlockLocation, ChunkLocation};
+use float_ord::FloatOrd;
+use interfaces::types::{BlockKind, BlockLocation, BlockState, ChunkLocation};
+use itertools::Itertools;
+use tracing::{debug, debug_span};
 
 use crate::{
     client::{
-        follow::{Follower, Result},
+        follow::{Follower, Obstacle, Result},
         pathfind::{
             context::MoveNode,
             implementations::{
@@ -17,10 +20,11 @@ use crate::{
             traits::{GoalCheck, Heuristic},
         },
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{mine::MineTask, pillar::PillarTask, Status, Task},
         timing::Increment,
     },
-    protocol::InterfaceOut,
+    protocol::{Face, InterfaceOut},
+    types::{Displacement, Location},
 };
 
 pub type ChunkTravelTask = NavigateProblem<ChunkHeuristic, CenterChunkGoalCheck>;
@@ -48,14 +52,61 @@ pub struct NavigateProblem<H: Heuristic, G: GoalCheck> {
     calculate: bool,
     problem: Box<PlayerProblem<H, G>>,
     follower: Option<Follower>,
+
+    /// what the bot is doing to get past an [`Obstacle`] (mining or
+    /// pillaring). Doors are opened right away.
+    action: Option<Box<dyn Task>>,
+
+    /// how often following a path failed (see [`MAX_FAILURES`])
+    failures: u32,
 }
 
+/// how often the bot can get stuck or find no path before navigating fails
+const MAX_FAILURES: u32 = 5;
+
 impl<H: Heuristic, G: GoalCheck> From<PlayerProblem<H, G>> for NavigateProblem<H, G> {
     fn from(problem: PlayerProblem<H, G>) -> Self {
         Self {
             calculate: true,
             problem: problem.into(),
             follower: None,
+            action: None,
+            failures: 0,
+        }
+    }
+}
+
+/// the block placed to bridge gaps (cobblestone)
+const THROWAWAY: BlockState = BlockState(4 << 4);
+
+/// the face of `block` closest to `eye`
+fn nearest_face(block: BlockLocation, eye: Location) -> Face {
+    let idx = block
+        .faces()
+        .iter()
+        .position_min_by_key(|face| FloatOrd(face.dist2(eye)))
+        .unwrap_or_default();
+
+    // there are six faces
+    #[allow(clippy::cast_possible_truncation)]
+    Face::from(idx as u8)
+}
+
+impl<H: Heuristic + Send + Sync, G: GoalCheck + Send + Sync> NavigateProblem<H, G> {
+    /// find a path again after the last one could not be followed
+    fn replan_failed(&mut self, local: &LocalState) {
+        self.failures += 1;
+        self.problem
+            .recalc(MoveNode::simple(local.physics.location().into()));
+        self.calculate = true;
+    }
+
+    /// running until following a path failed too often
+    const fn status(&self) -> Status {
+        if self.failures > MAX_FAILURES {
+            Status::Failure
+        } else {
+            Status::Running
         }
     }
 }
@@ -63,16 +114,69 @@ impl<H: Heuristic, G: GoalCheck> From<PlayerProblem<H, G>> for NavigateProblem<H
 impl<H: Heuristic + Send + Sync, G: GoalCheck + Send + Sync> Task for NavigateProblem<H, G> {
     fn tick(
         &mut self,
-        _out: &mut dyn InterfaceOut,
+        out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
+        if let Some(action) = self.action.as_mut() {
+            if !action.tick(out, local, global).is_done() {
+                return Status::Running;
+            }
+            self.action = None;
+        }
+
         let Some(follower) = self.follower.as_mut() else {
-            return false;
+            return self.status();
         };
 
+        match follower.obstacle(local, global) {
+            None => {}
+            Some(Obstacle::Block(location)) => {
+                debug!(%location, "mining obstacle");
+                let eye = local.physics.location() + Displacement::EYE_HEIGHT;
+                let mut task = MineTask::new(location, out, local, global);
+                task.set_face(nearest_face(location, eye));
+                self.action = Some(Box::new(task));
+                return Status::Running;
+            }
+            Some(Obstacle::Pillar { y }) => {
+                debug!(y, "pillaring");
+                let y = u32::try_from(y).unwrap_or_default();
+                self.action = Some(Box::new(PillarTask::new(y)));
+                return Status::Running;
+            }
+            Some(Obstacle::Closed(location)) => {
+                debug!(%location, "opening");
+                local.physics.place_hand(location);
+
+                // assume it opened so we do not right-click it closed again
+                // before the server sends the new state
+                if let Some(state) = global.blocks.get_block_exact(location) {
+                    global.blocks.set_block(location, state.opened());
+                }
+            }
+            Some(Obstacle::Gap { against, direction }) => {
+                // the follower fails (and the path is found again without
+                // bridging) if there is nothing to place
+                if local
+                    .inventory
+                    .switch_selector(out, BlockKind::throw_away_block)
+                {
+                    debug!(%against, ?direction, "bridging");
+                    local
+                        .physics
+                        .place_hand_face(against, Face::from(direction));
+
+                    // the server sends the block back later. Until then we
+                    // assume it was placed so the follower walks onto it.
+                    let placed = against + BlockLocation::from(direction.unit_change());
+                    global.blocks.set_block(placed, THROWAWAY);
+                }
+            }
+        }
+
         if follower.should_recalc() {
-            println!("recalc");
+            debug!("recalc");
             self.problem
                 .recalc(MoveNode::simple(local.physics.location().into()));
             self.calculate = true;
@@ -80,17 +184,15 @@ impl<H: Heuristic + Send + Sync, G: GoalCheck + Send + Sync> Task for NavigatePr
 
         match follower.follow_iteration(local, global) {
             Result::Failed => {
-                println!("failed");
+                debug!("failed");
                 self.follower = None;
-                self.problem
-                    .recalc(MoveNode::simple(local.physics.location().into()));
-                self.calculate = true;
-                false
+                self.replan_failed(local);
+                self.status()
             }
-            Result::InProgress => false,
+            Result::InProgress => Status::Running,
             Result::Finished => {
-                println!("finished!");
-                true
+                debug!("finished!");
+                Status::Success
             }
         }
     }
@@ -100,12 +202,19 @@ impl<H: Heuristic + Send + Sync, G: GoalCheck + Send + Sync> Task for NavigatePr
             return;
         }
 
+        let _span = debug_span!("pathfind").entered();
         let res = self.problem.iterate_until(end_at, local, global);
         match res {
             Increment::Finished(res) => {
                 self.calculate = false;
                 match self.follower.as_mut() {
-                    None => self.follower = Follower::new(res),
+                    None => {
+                        self.follower = Follower::new(res);
+                        if self.follower.is_none() {
+                            debug!("no path");
+                            self.replan_failed(local);
+                        }
+                    }
                     Some(before) => before.merge(res),
                 };
             }
@@ -114,4 +223,287 @@ impl<H: Heuristic + Send + Sync, G: GoalCheck + Send + Sync> Task for NavigatePr
             Increment::InProgress => {}
         }
     }
+
+    fn resume(&mut self, local: &mut LocalState, _global: &GlobalState) {
+        // the bot may have left the path while it was doing something else
+        debug!("replanning");
+        self.follower = None;
+        self.action = None;
+        self.problem
+            .recalc(MoveNode::simple(local.physics.location().into()));
+        self.calculate = true;
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        if let Some(action) = self.action.as_mut() {
+            action.cancel(out, local, global);
+        }
+    }
+
+    /// [`None`] while there is no path yet
+    fn progress(&self) -> Option<f32> {
+        self.follower.as_ref().map(Follower::progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use interfaces::types::{BlockKind, BlockLocation, BlockState};
+
+    use crate::{
+        client::{
+            physics::BlockPlaced,
+            state::{
+                global::GlobalState,
+                local::{inventory::ItemStack, LocalState},
+            },
+            tasks::{
+                navigate::{BlockTravelTask, THROWAWAY},
+                Status, Task,
+            },
+        },
+        protocol::{Face, InterfaceOut, Mine, MockOut},
+        storage::blocks::WorldBlocks,
+    };
+
+    /// run `task` until it is done (or gives up after `ticks` ticks).
+    /// Right-clicks are sent like in `Bot::run_sync`.
+    fn run(
+        task: &mut impl Task,
+        out: &mut MockOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+        ticks: usize,
+    ) -> Status {
+        for _ in 0..ticks {
+            task.expensive(Instant::now() + Duration::from_millis(50), local, global);
+            let status = task.tick(out, local, global);
+            if status.is_done() {
+                return status;
+            }
+
+            let actions = local.physics.tick(&mut global.blocks, &local.inventory);
+            if let Some(BlockPlaced { location, face }) = actions.block_placed {
+                out.place_block(location, face);
+            }
+        }
+        Status::Running
+    }
+
+    /// navigate from (0, 1, 0) in a flat world to `goal` once `setup` changed
+    /// the world and the bot. Also returns what was sent and the state the
+    /// bot ended up in.
+    fn travel(
+        setup: impl FnOnce(&mut LocalState, &mut WorldBlocks),
+        goal: BlockLocation,
+    ) -> (Status, MockOut, LocalState, GlobalState) {
+        let mut out = MockOut::default();
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+        local.config.path.parkour = false;
+
+        let start = BlockLocation::new(0, 1, 0);
+        local.physics.teleport(start.center_bottom());
+
+        global.blocks = WorldBlocks::flat();
+        setup(&mut local, &mut global.blocks);
+
+        let mut task = BlockTravelTask::new(goal, &local);
+        let status = run(&mut task, &mut out, &mut local, &mut global, 1_000);
+        (status, out, local, global)
+    }
+
+    /// [`travel`] past a wall of `wall` blocks at x = 3 which is too high to
+    /// jump and too long to walk around
+    fn travel_through_wall(
+        wall: BlockState,
+        setup: impl FnOnce(&mut WorldBlocks),
+        goal: BlockLocation,
+    ) -> (Status, MockOut, LocalState, GlobalState) {
+        travel(
+            |_, blocks| {
+                for z in -100..=100 {
+                    for y in 1..=3 {
+                        blocks.set_block(BlockLocation::new(3, y, z), wall);
+                    }
+                }
+                setup(blocks);
+            },
+            goal,
+        )
+    }
+
+    /// a hotbar slot full of [`THROWAWAY`] blocks
+    fn give_blocks(local: &mut LocalState) {
+        let stack = ItemStack::new(THROWAWAY.kind(), 64, 0, None);
+        local.inventory.add(36, stack);
+    }
+
+    /// the blocks which were mined, in order
+    fn mined(out: &MockOut) -> Vec<BlockLocation> {
+        out.mined
+            .iter()
+            .filter(|(_, mine)| *mine == Mine::Finished)
+            .map(|(location, _)| *location)
+            .collect()
+    }
+
+    #[test]
+    fn test_mine_through() {
+        let dirt = BlockState::from(3, 0);
+        let goal = BlockLocation::new(6, 1, 0);
+        let (status, out, ..) = travel_through_wall(dirt, |_| {}, goal);
+
+        assert_eq!(status, Status::Success);
+        let through = [BlockLocation::new(3, 2, 0), BlockLocation::new(3, 1, 0)];
+        assert_eq!(mined(&out), through);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        // bedrock all around the start, which cannot be mined
+        let goal = BlockLocation::new(6, 1, 0);
+        let (status, ..) = travel(
+            |_, blocks| {
+                let bedrock = BlockState::from(7, 0);
+                for x in -1..=1 {
+                    for z in -1..=1 {
+                        for y in 0..=3 {
+                            if (x, y, z) != (0, 1, 0) && (x, y, z) != (0, 2, 0) {
+                                blocks.set_block(BlockLocation::new(x, y, z), bedrock);
+                            }
+                        }
+                    }
+                }
+            },
+            goal,
+        );
+
+        assert_eq!(status, Status::Failure);
+    }
+
+    #[test]
+    fn test_climb() {
+        // a ladder up one side of the wall
+        let goal = BlockLocation::new(3, 4, 0);
+        let (status, out, local, _) = travel_through_wall(
+            BlockState::STONE,
+            |blocks| {
+                for y in 1..=3 {
+                    let ladder = BlockState::from(BlockKind::LADDER.id(), 4);
+                    blocks.set_block(BlockLocation::new(2, y, 0), ladder);
+                }
+            },
+            goal,
+        );
+
+        assert_eq!(status, Status::Success);
+        assert!(out.mined.is_empty());
+        assert_eq!(BlockLocation::from(local.physics.location()), goal);
+    }
+
+    #[test]
+    fn test_open_door() {
+        // a closed oak door in the wall
+        let door = BlockLocation::new(3, 1, 0);
+        let goal = BlockLocation::new(6, 1, 0);
+        let (status, out, ..) = travel_through_wall(
+            BlockState::STONE,
+            |blocks| {
+                blocks.set_block(door, BlockState::from(64, 0));
+                blocks.set_block(door.above(), BlockState::from(64, 0b1000));
+            },
+            goal,
+        );
+
+        assert_eq!(status, Status::Success);
+        assert!(out.mined.is_empty());
+
+        // right-clicked once (not again before the server says it is open)
+        let clicked: Vec<_> = out.placed.iter().map(|(against, _)| *against).collect();
+        assert_eq!(clicked, [door]);
+    }
+
+    #[test]
+    fn test_bridge() {
+        // a trench with nothing below it
+        let goal = BlockLocation::new(6, 1, 0);
+        let (status, out, local, global) = travel(
+            |local, blocks| {
+                give_blocks(local);
+                for z in -100..=100 {
+                    blocks.set_block(BlockLocation::new(3, 0, z), BlockState::AIR);
+                }
+            },
+            goal,
+        );
+
+        assert_eq!(status, Status::Success);
+        assert_eq!(BlockLocation::from(local.physics.location()), goal);
+        assert!(out.mined.is_empty());
+        assert_eq!(out.placed, [(BlockLocation::new(2, 0, 0), Face::PosX)]);
+        let bridged = global.blocks.get_block_exact(BlockLocation::new(3, 0, 0));
+        assert_eq!(bridged, Some(THROWAWAY));
+    }
+
+    #[test]
+    fn test_pillar() {
+        // a bedrock ledge which cannot be mined into stairs
+        let goal = BlockLocation::new(3, 4, 0);
+        let (status, out, local, _) = travel(
+            |local, blocks| {
+                give_blocks(local);
+                for z in -100..=100 {
+                    for y in 1..=3 {
+                        let bedrock = BlockState::from(7, 0);
+                        blocks.set_block(BlockLocation::new(3, y, z), bedrock);
+                    }
+                }
+            },
+            goal,
+        );
+
+        assert_eq!(status, Status::Success);
+        assert_eq!(BlockLocation::from(local.physics.location()), goal);
+        assert!(out.mined.is_empty());
+
+        // two blocks up and a jump onto the ledge
+        let pillar = [BlockLocation::new(2, 0, 0), BlockLocation::new(2, 1, 0)];
+        assert_eq!(out.placed, pillar.map(|against| (against, Face::PosY)));
+    }
+
+    #[test]
+    fn test_dig_down() {
+        // the goal is buried in the ground. It counts as reached from a block
+        // above.
+        let goal = BlockLocation::new(0, 2, 0);
+        let (status, out, local, _) = travel(
+            |local, blocks| {
+                local
+                    .physics
+                    .teleport(BlockLocation::new(0, 6, 0).center_bottom());
+                for x in -100..=100 {
+                    for z in -100..=100 {
+                        for y in 1..=5 {
+                            blocks.set_block(BlockLocation::new(x, y, z), BlockState::STONE);
+                        }
+                    }
+                }
+            },
+            goal,
+        );
+
+        assert_eq!(status, Status::Success);
+        let reached = BlockLocation::new(0, 3, 0);
+        assert_eq!(BlockLocation::from(local.physics.location()), reached);
+        let dug: Vec<_> = (3..=5).rev().map(|y| BlockLocation::new(0, y, 0)).collect();
+        assert_eq!(mined(&out), dug);
+    }
 }
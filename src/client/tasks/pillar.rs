@@ -1,9 +1,10 @@
 use interfaces::types::{BlockLocation, SimpleType};
+use tracing::debug;
 
 use crate::{
     client::{
         state::{global::GlobalState, local::LocalState},
-        tasks::Task,
+        tasks::{Status, Task},
     },
     protocol::InterfaceOut,
     types::{Direction, Displacement},
@@ -15,7 +16,7 @@ pub struct PillarTask {
 
 impl PillarTask {
     pub fn new(dest_y: u32) -> Self {
-        println!("pillar dest {dest_y}");
+        debug!("pillar dest {dest_y}");
         Self { dest_y }
     }
 }
@@ -26,7 +27,7 @@ impl Task for PillarTask {
         out: &mut dyn InterfaceOut,
         local: &mut LocalState,
         global: &mut GlobalState,
-    ) -> bool {
+    ) -> Status {
         local.inventory.switch_block(out);
 
         // equal OR GREATER because we don't want to pillar if we are higher than we
@@ -36,7 +37,7 @@ impl Task for PillarTask {
 
             // return true if block below us is solid
             if global.blocks.get_block_simple(below_loc) == Some(SimpleType::Solid) {
-                return true;
+                return Status::Success;
             }
         }
 
@@ -69,6 +70,6 @@ impl Task for PillarTask {
             }
         }
 
-        false
+        Status::Running
     }
 }
@@ -1,4 +1,5 @@
 use interfaces::types::{BlockLocation, BlockLocation2D};
+use tracing::debug;
 
 use crate::client::{
     state::{
@@ -35,7 +36,7 @@ impl Lazy for SafeMineRegion {
             match global.blocks.get_block_exact(loc).map(|x| x.kind().id()) {
                 // water or lava
                 Some(8..=11) => {
-                    println!(
+                    debug!(
                         "skipping region {}, {} because of {:?} at {}",
                         center.x,
                         center.z,
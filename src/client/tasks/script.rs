@@ -0,0 +1,480 @@
+//! Bot behaviors written in [Rhai](https://rhai.rs) instead of Rust.
+//!
+//! The top level of a script runs when its [`ScriptTask`] starts. If the
+//! script defines `fn tick()`, it is then called every tick until it returns
+//! `true`. Top-level variables keep their values between ticks:
+//!
+//! ```rhai
+//! let home = location();
+//!
+//! fn tick() {
+//!     if food() < 10 { eat(); }
+//!     false
+//! }
+//! ```
+//!
+//! Scripts see the bot and its world through the functions registered in
+//! [`engine`]. Built-in tasks (`goto`, `mine`, `eat` and `wait`) run one after
+//! another and `tick` is not called until they are done.
+
+use std::{cell::Cell, path::PathBuf, sync::Arc, time::Instant};
+
+use anyhow::{bail, Context};
+use interfaces::{
+    query::{BlockInfo, TaskInfo},
+    types::{BlockLocation, Location},
+};
+use once_cell::sync::Lazy;
+use rhai::{
+    module_resolvers::DummyModuleResolver, Array, CallFnOptions, Dynamic, Engine, EvalAltResult,
+    Map, Scope, AST, FLOAT, INT,
+};
+use tracing::{info, warn};
+
+use crate::{
+    client::{
+        query,
+        state::{global::GlobalState, local::LocalState},
+        tasks::{
+            compound::CompoundTask, delay::DelayTask, describe, eat::EatTask, mine::MineTask,
+            navigate::BlockTravelTask, Status, Task,
+        },
+    },
+    protocol::InterfaceOut,
+};
+
+/// the operations a script may do per tick before it is stopped. This keeps
+/// a script from holding up the game loop.
+const OPERATIONS_PER_TICK: u64 = 50_000;
+
+/// the directory scripts are loaded from by name (see [`load`])
+const SCRIPTS_DIR: &str = "scripts";
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// What the script functions work on while a script is called. The pointers
+/// are only set (and valid) within [`Env::enter`].
+#[derive(Copy, Clone)]
+struct Env {
+    out: *mut (dyn InterfaceOut + 'static),
+    local: *mut LocalState,
+    global: *mut GlobalState,
+    tasks: *mut CompoundTask,
+}
+
+thread_local! {
+    static ENV: Cell<Option<Env>> = const { Cell::new(None) };
+}
+
+/// Clears [`ENV`] when dropped
+struct Entered(Option<Env>);
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        ENV.set(self.0);
+    }
+}
+
+impl Env {
+    /// make the state of the bot available to script functions until the
+    /// returned guard is dropped
+    fn enter(
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+        tasks: &mut CompoundTask,
+    ) -> Entered {
+        let out: *mut (dyn InterfaceOut + '_) = out;
+
+        // SAFETY: only the lifetime is erased. The pointer is not used after
+        // the guard is dropped, which happens before the borrow ends.
+        #[allow(clippy::transmute_ptr_to_ptr)]
+        let out = unsafe {
+            std::mem::transmute::<*mut (dyn InterfaceOut + '_), *mut (dyn InterfaceOut + 'static)>(
+                out,
+            )
+        };
+
+        let env = Self {
+            out,
+            local,
+            global,
+            tasks,
+        };
+
+        Entered(ENV.replace(Some(env)))
+    }
+}
+
+/// run `f` with the state of the bot running the script
+fn with_env<R>(
+    f: impl FnOnce(&mut dyn InterfaceOut, &mut LocalState, &mut GlobalState, &mut CompoundTask) -> R,
+) -> ScriptResult<R> {
+    let env = ENV.get().ok_or("not called by a bot")?;
+
+    // SAFETY: the pointers are valid while a script is called (see
+    // `Env::enter`). Script functions do not call back into the engine so no
+    // other references to the state exist while `f` runs.
+    unsafe {
+        Ok(f(
+            &mut *env.out,
+            &mut *env.local,
+            &mut *env.global,
+            &mut *env.tasks,
+        ))
+    }
+}
+
+fn block_location(x: INT, y: INT, z: INT) -> ScriptResult<BlockLocation> {
+    match (i32::try_from(x), i16::try_from(y), i32::try_from(z)) {
+        (Ok(x), Ok(y), Ok(z)) => Ok(BlockLocation::new(x, y, z)),
+        _ => Err(format!("{x} {y} {z} is out of the world").into()),
+    }
+}
+
+fn location_map(location: Location) -> Map {
+    let mut map = Map::new();
+    map.insert("x".into(), location.x.into());
+    map.insert("y".into(), location.y.into());
+    map.insert("z".into(), location.z.into());
+    map
+}
+
+/// the engine every script runs in. It cannot access files or the network.
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+
+    engine
+        .set_max_operations(OPERATIONS_PER_TICK)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(10_000)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000)
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval");
+
+    engine.on_print(|text| {
+        info!(target: "script", "{text}");
+        let _ = with_env(|_, local, _, _| local.record_chat(text.to_string()));
+    });
+
+    register_bot(&mut engine);
+    register_world(&mut engine);
+    register_actions(&mut engine);
+    register_tasks(&mut engine);
+
+    engine
+}
+
+/// reading the state of the bot
+fn register_bot(engine: &mut Engine) {
+    engine
+        .register_fn("bot_id", || {
+            with_env(|_, local, _, _| INT::from(local.bot_id))
+        })
+        .register_fn("username", || {
+            with_env(|_, local, _, _| local.info.username.clone())
+        })
+        .register_fn("health", || {
+            with_env(|_, local, _, _| FLOAT::from(local.health))
+        })
+        .register_fn("food", || with_env(|_, local, _, _| INT::from(local.food)))
+        .register_fn("location", || {
+            with_env(|_, local, _, _| location_map(local.physics.location()))
+        })
+        .register_fn("on_ground", || {
+            with_env(|_, local, _, _| local.physics.on_ground())
+        })
+        .register_fn("ticks", || {
+            with_env(|_, local, _, _| INT::try_from(local.ticks).unwrap_or(INT::MAX))
+        });
+}
+
+/// reading blocks, entities and players
+fn register_world(engine: &mut Engine) {
+    engine
+        .register_fn("block", |x: INT, y: INT, z: INT| {
+            let location = block_location(x, y, z)?;
+            with_env(|_, _, global, _| match query::block(global, location) {
+                BlockInfo::Known { id, .. } => Dynamic::from(INT::from(id)),
+                BlockInfo::Unknown | BlockInfo::Estimate { .. } => Dynamic::UNIT,
+            })
+        })
+        .register_fn("entities", |radius: FLOAT| {
+            with_env(|_, local, global, _| {
+                query::entities(global, local.physics.location(), radius)
+                    .into_iter()
+                    .map(|entity| {
+                        let mut map = location_map(entity.location);
+                        map.insert("id".into(), INT::from(entity.id).into());
+                        map.insert(
+                            "player".into(),
+                            entity.player.map_or(Dynamic::UNIT, Dynamic::from),
+                        );
+                        Dynamic::from_map(map)
+                    })
+                    .collect::<Array>()
+            })
+        })
+        .register_fn("players", || {
+            with_env(|_, _, global, _| {
+                query::players(global)
+                    .into_iter()
+                    .map(|player| Dynamic::from(player.name))
+                    .collect::<Array>()
+            })
+        });
+}
+
+/// acting right away
+fn register_actions(engine: &mut Engine) {
+    engine
+        .register_fn("chat", |message: &str| {
+            with_env(|out, _, _, _| out.send_chat(message))
+        })
+        .register_fn("look_at", |x: FLOAT, y: FLOAT, z: FLOAT| {
+            with_env(|_, local, _, _| local.physics.look_at(Location::new(x, y, z)))
+        })
+        .register_fn("jump", || with_env(|_, local, _, _| local.physics.jump()))
+        .register_fn("swing", || with_env(|out, _, _, _| out.swing_arm()))
+        .register_fn("use_item", || with_env(|out, _, _, _| out.use_item()))
+        .register_fn("attack", |id: INT| {
+            let id = u32::try_from(id).map_err(|_| format!("no entity has id {id}"))?;
+            with_env(|out, _, _, _| out.attack_entity(id))
+        })
+        .register_fn("slot", |slot: INT| {
+            let slot = u8::try_from(slot)
+                .ok()
+                .filter(|slot| *slot < 9)
+                .ok_or_else(|| format!("{slot} is not a hotbar slot"))?;
+            with_env(|out, local, _, _| local.inventory.change_slot(slot, out))
+        });
+}
+
+/// scheduling built-in tasks
+fn register_tasks(engine: &mut Engine) {
+    engine
+        .register_fn("goto", |x: INT, y: INT, z: INT| {
+            let location = block_location(x, y, z)?;
+            with_env(|_, local, _, tasks| {
+                tasks.add(BlockTravelTask::new(location, local));
+            })
+        })
+        .register_fn("mine", |x: INT, y: INT, z: INT| -> ScriptResult<()> {
+            let location = block_location(x, y, z)?;
+            with_env(|out, local, global, tasks| {
+                if global.blocks.get_block_kind(location).is_none() {
+                    return Err(format!("the block at {location} is not known").into());
+                }
+                tasks.add(MineTask::new(location, out, local, global));
+                Ok(())
+            })?
+        })
+        .register_fn("eat", || {
+            with_env(|_, _, _, tasks| {
+                tasks.add(EatTask::default());
+            })
+        })
+        .register_fn("wait", |ticks: INT| {
+            let ticks = u32::try_from(ticks).map_err(|_| format!("cannot wait {ticks} ticks"))?;
+            with_env(|_, _, _, tasks| {
+                tasks.add(DelayTask::new(ticks));
+            })
+        });
+}
+
+static ENGINE: Lazy<Engine> = Lazy::new(engine);
+
+/// compile a script so it can be run by several bots
+pub fn compile(source: &str) -> anyhow::Result<Arc<AST>> {
+    let ast = ENGINE.compile(source)?;
+    Ok(Arc::new(ast))
+}
+
+/// compile the script `name` in [`SCRIPTS_DIR`] (i.e., `scripts/farm.rhai`
+/// for `farm`)
+pub fn load(name: &str) -> anyhow::Result<Arc<AST>> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    if name.is_empty() || !name.chars().all(valid) {
+        bail!("invalid script name {name}");
+    }
+
+    let path: PathBuf = [SCRIPTS_DIR, &format!("{name}.rhai")].iter().collect();
+    let source = std::fs::read_to_string(&path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+
+    compile(&source)
+}
+
+/// Runs a script (see the [module docs](self))
+pub struct ScriptTask {
+    ast: Arc<AST>,
+    scope: Scope<'static>,
+
+    /// the built-in tasks the script scheduled
+    tasks: CompoundTask,
+
+    started: bool,
+
+    /// the script has no `tick` or it returned `true`
+    done: bool,
+}
+
+impl ScriptTask {
+    pub fn new(ast: Arc<AST>) -> Self {
+        Self {
+            ast,
+            scope: Scope::new(),
+            tasks: CompoundTask::default(),
+            started: false,
+            done: false,
+        }
+    }
+
+    fn has_tick(&self) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|f| f.name == "tick" && f.params.is_empty())
+    }
+
+    /// run the top level of the script or its `tick`
+    fn call(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> ScriptResult<()> {
+        let _entered = Env::enter(out, local, global, &mut self.tasks);
+
+        if !self.started {
+            self.started = true;
+            ENGINE.run_ast_with_scope(&mut self.scope, &self.ast)?;
+            self.done = !self.has_tick();
+            return Ok(());
+        }
+
+        let options = CallFnOptions::new().eval_ast(false).rewind_scope(false);
+        let result: Dynamic =
+            ENGINE.call_fn_with_options(options, &mut self.scope, &self.ast, "tick", ())?;
+        self.done = result.as_bool().unwrap_or(false);
+
+        Ok(())
+    }
+}
+
+impl Task for ScriptTask {
+    fn tick(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) -> Status {
+        // wait for the built-in tasks the script scheduled
+        match self.tasks.tick(out, local, global) {
+            Status::Success => {}
+            status => return status,
+        }
+
+        if self.done {
+            return Status::Success;
+        }
+
+        if let Err(err) = self.call(out, local, global) {
+            warn!("script failed: {err}");
+            local.record_chat(format!("script failed: {err}"));
+            return Status::Failure;
+        }
+
+        // tasks scheduled by this call start right away
+        match self.tasks.tick(out, local, global) {
+            Status::Success if !self.done => Status::Running,
+            status => status,
+        }
+    }
+
+    fn expensive(&mut self, end_at: Instant, local: &mut LocalState, global: &GlobalState) {
+        self.tasks.expensive(end_at, local, global);
+    }
+
+    fn resume(&mut self, local: &mut LocalState, global: &GlobalState) {
+        self.tasks.resume(local, global);
+    }
+
+    fn cancel(
+        &mut self,
+        out: &mut dyn InterfaceOut,
+        local: &mut LocalState,
+        global: &mut GlobalState,
+    ) {
+        self.tasks.cancel(out, local, global);
+    }
+
+    fn describe(&self) -> TaskInfo {
+        describe(self, self.tasks.current())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        client::{
+            state::{global::GlobalState, local::LocalState},
+            tasks::{
+                script::{compile, ScriptTask},
+                Task,
+            },
+        },
+        protocol::MockOut,
+        storage::blocks::WorldBlocks,
+    };
+
+    fn run(source: &str, ticks: usize) -> (MockOut, LocalState, bool) {
+        let mut task = ScriptTask::new(compile(source).unwrap());
+        let mut out = MockOut::default();
+        let mut local = LocalState::mock();
+        let mut global = GlobalState::init();
+        global.blocks = WorldBlocks::flat();
+
+        let done = (0..ticks).any(|_| task.tick(&mut out, &mut local, &mut global).is_done());
+        (out, local, done)
+    }
+
+    #[test]
+    fn test_script() {
+        let source = r#"
+            let count = 0;
+            chat("hello " + username());
+
+            fn tick() {
+                count += 1;
+                print("stone is " + block(0, 0, 0));
+                count == 3
+            }
+        "#;
+
+        let (out, local, done) = run(source, 3);
+        assert!(!done, "the top level runs on the first tick");
+        assert_eq!(out.chat, ["hello abc"]);
+        assert_eq!(local.chat.back().unwrap(), "stone is 1");
+
+        let (_, _, done) = run(source, 4);
+        assert!(done);
+
+        // waiting is a built-in task which holds up `tick`
+        let (_, _, done) = run("wait(5);", 6);
+        assert!(done);
+        let (_, _, done) = run("wait(5);", 5);
+        assert!(!done);
+
+        // stopped after its budget for the tick
+        let (_, local, done) = run("loop {}", 1);
+        assert!(done);
+        assert!(local.chat.back().unwrap().starts_with("script failed"));
+
+        assert!(compile("fn tick( {").is_err());
+        assert!(compile(r#"import "secrets" as s;"#).is_ok());
+        let (_, local, _) = run(r#"import "secrets" as s;"#, 1);
+        assert!(local.chat.back().unwrap().starts_with("script failed"));
+    }
+}
@@ -13,4 +13,10 @@ pub trait TaskStream {
         local: &mut LocalState,
         global: &mut GlobalState,
     ) -> Option<Box<dyn Task>>;
+
+    /// How far along the stream is (see [`Task::progress`]). Checked every
+    /// tick the stream runs.
+    fn progress(&mut self, _local: &LocalState) -> Option<f32> {
+        None
+    }
 }
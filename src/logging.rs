@@ -0,0 +1,102 @@
+//! Logging with `tracing`. Every bot has a `bot` span with its id and username
+//! so logs can be filtered by bot as well as by module and level.
+
+use std::fs::OpenOptions;
+
+use anyhow::Context;
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, EnvFilter};
+
+use crate::bootstrap::opts::{LogFormat, LogOptions};
+
+/// the file logs are written to when the terminal is used by the dashboard
+const LOG_FILE: &str = "swarmbot.log";
+
+/// the filter used if neither `--log` nor `RUST_LOG` is given
+const DEFAULT_FILTER: &str = "info";
+
+/// The filter directives for `opts`. If bots are given, everything else only
+/// shows warnings.
+fn directives(opts: &LogOptions) -> String {
+    let filter = opts
+        .filter
+        .clone()
+        .or_else(|| std::env::var(EnvFilter::DEFAULT_ENV).ok())
+        .unwrap_or_else(|| DEFAULT_FILTER.to_string());
+
+    if opts.bots.is_empty() {
+        return filter;
+    }
+
+    // the level of the bots is the default level of the filter
+    let level = filter
+        .split(',')
+        .find(|directive| !directive.contains('='))
+        .unwrap_or(DEFAULT_FILTER);
+
+    let mut directives = vec!["warn".to_string()];
+    directives.extend(
+        opts.bots
+            .iter()
+            .map(|bot| format!("[bot{{username={bot}}}]={level}")),
+    );
+    directives.join(",")
+}
+
+/// Initialize logging. This must be called once before anything is logged.
+/// If `to_file` is set, logs are appended to [`LOG_FILE`] instead of stderr.
+pub fn init(opts: &LogOptions, to_file: bool) -> anyhow::Result<()> {
+    let directives = directives(opts);
+    let filter = EnvFilter::try_new(&directives)
+        .with_context(|| format!("invalid log filter {directives}"))?;
+
+    let writer = if to_file {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(LOG_FILE)
+            .with_context(|| format!("could not open {LOG_FILE}"))?;
+        BoxMakeWriter::new(file)
+    } else {
+        BoxMakeWriter::new(std::io::stderr)
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(!to_file)
+        .with_writer(writer);
+
+    match opts.format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bootstrap::opts::{LogFormat, LogOptions},
+        logging::directives,
+    };
+
+    #[test]
+    fn test_directives() {
+        let mut opts = LogOptions {
+            filter: Some("debug,hyper=warn".to_string()),
+            bots: Vec::new(),
+            format: LogFormat::Text,
+        };
+        assert_eq!(directives(&opts), "debug,hyper=warn");
+
+        opts.bots = vec!["Bot0001".to_string(), "Bot0002".to_string()];
+        assert_eq!(
+            directives(&opts),
+            "warn,[bot{username=Bot0001}]=debug,[bot{username=Bot0002}]=debug"
+        );
+    }
+}
@@ -3,22 +3,32 @@
 #[macro_use]
 extern crate swarm_bot_packets;
 
-use std::pin::Pin;
+use std::{collections::HashMap, pin::Pin};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use futures::Stream;
 use tokio::{runtime::Runtime, task};
 use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tracing::{error, info, warn};
 
 use crate::{
     bootstrap::{
-        dns::normalize_address, opts::CliOptions, storage::BotConnectionData, BotConnection,
+        accounts,
+        config::{Defaults, FileConfig, ProxyPolicy},
+        dns::normalize_address,
+        mojang,
+        opts::{CliOptions, Command},
+        storage::{load_proxies, BotConnectionData},
+        Address, BotConnection,
     },
     client::runner::{Runner, RunnerOptions},
+    metrics::METRICS,
 };
 
 mod bootstrap;
 mod client;
+mod logging;
+mod metrics;
 mod protocol;
 mod schematic;
 mod storage;
@@ -31,12 +41,27 @@ fn main() {
     let rt = Runtime::new().unwrap();
     let local = task::LocalSet::new();
     local.block_on(&rt, async move {
-        match run().await {
-            // this should never happen as this should be an infinite loop
-            Ok(()) => println!("Program exited without errors somehow"),
+        let mut options = CliOptions::get();
+
+        if let Err(err) = logging::init(&options.log, options.tui && options.command.is_none()) {
+            eprintln!("{err:?}");
+            return;
+        }
+
+        // subcommands which do not launch bots
+        if let Some(Command::Cache(cache)) = options.command.take() {
+            if let Err(err) = accounts::run(cache).await {
+                error!("{err:?}");
+            }
+            return;
+        }
+
+        match run(options).await {
+            // only happens when quitting from the dashboard
+            Ok(()) => info!("exiting"),
 
             // print the error in non-debug fashion
-            Err(err) => println!("{err:?}"),
+            Err(err) => error!("{err:?}"),
         }
     });
 }
@@ -45,61 +70,126 @@ fn default<T: Default>() -> T {
     T::default()
 }
 
-async fn run() -> anyhow::Result<()> {
+async fn run(options: CliOptions) -> anyhow::Result<()> {
     // grab options from CLI
     let CliOptions {
+        command: _,
+        config,
         users_file,
         proxies_file,
         host,
         count,
-        ver: version,
+        ver,
         port,
         delay_ms,
-        ws_port,
+        ws,
+        metrics_port,
+        tui,
         proxy,
+        proxy_policy,
         online,
-    } = CliOptions::get();
+        auth,
+        log: _,
+        encrypt_cache,
+    } = options;
+
+    // options given on the CLI take precedence over the config file
+    let file = FileConfig::load(config.as_deref())?;
+
+    let online = online.or(file.online).unwrap_or(false);
+    let delay_ms = delay_ms.or(file.delay_ms).unwrap_or(500);
+    let ws = ws.resolve(&file)?;
+
+    if let Some(port) = metrics_port.or(file.metrics_port) {
+        metrics::serve(port).await?;
+    }
+
+    let host = host.or_else(|| file.host.clone());
+    let defaults = Defaults {
+        count: count
+            .or(file.count)
+            .unwrap_or_else(|| usize::from(file.groups.is_empty() && file.servers.is_empty())),
+        host,
+        port: port.or(file.port).unwrap_or(25565),
+        version: ver.or(file.version).unwrap_or(340),
+        users_file: users_file
+            .or_else(|| file.users_file.clone())
+            .unwrap_or_else(|| "users.csv".to_string()),
+        proxies_file: proxies_file
+            .or_else(|| file.proxies_file.clone())
+            .unwrap_or_else(|| "proxies.csv".to_string()),
+        proxy: proxy_policy
+            .or_else(|| proxy.then_some(ProxyPolicy::Cycle))
+            .or(file.proxy)
+            .unwrap_or_default(),
+    };
+
+    let mut groups = file.groups(&defaults)?;
+    if groups.is_empty() {
+        bail!("no bots to launch");
+    }
 
-    // A list of users we will login
+    // all servers are run by the same runner so they need the same protocol
+    if let Some(group) = groups.iter().find(|group| group.version != 340) {
+        bail!("version {} does not exist", group.version);
+    }
 
     // looks up DNS records, etc. This is important where there is a redirect
     // for instance, 2b2t.org has a DNS redirect
-    let server_address = normalize_address(&host, port).await;
+    let mut addresses: HashMap<Address, Address> = HashMap::new();
+    for group in &mut groups {
+        let address = if let Some(address) = addresses.get(&group.server) {
+            address.clone()
+        } else {
+            let address = normalize_address(&group.server.host, group.server.port).await;
+            addresses.insert(group.server.clone(), address.clone());
+            address
+        };
+        group.server = address;
+    }
+
+    // a custom auth server is discovered through the proxy of the first bot
+    let proxy = match groups.first() {
+        Some(group) if online => load_proxies(&group.proxies_file, group.proxy)?
+            .next()
+            .flatten(),
+        _ => None,
+    };
+    let auth = auth.resolve(proxy.as_ref()).await?;
+
+    // the dashboard shows device-code prompts as they would be drawn over
+    if tui {
+        mojang::hide_device_code_prompts();
+    }
 
     let connection_data: Pin<Box<dyn Stream<Item = BotConnectionData>>> = match online {
-        false => Box::pin(BotConnectionData::offline_random().take(count)),
+        false => Box::pin(BotConnectionData::offline_random(groups)),
         true => {
-            let bot_receiver =
-                BotConnectionData::load_from_files(&users_file, &proxies_file, proxy, count)?;
+            let bot_receiver = BotConnectionData::load_from_files(groups, auth, encrypt_cache)?;
 
             Box::pin(ReceiverStream::new(bot_receiver))
         }
     };
 
-    // taking the users and generating connections to the Minecraft server
-    let connections: ReceiverStream<_> =
-        BotConnection::stream(server_address, connection_data).into();
+    // taking the users and generating connections to the Minecraft servers
+    let connections: ReceiverStream<_> = BotConnection::stream(connection_data).into();
 
     // only return bot connections which were successful
     let connections = connections.filter_map(|elem| match elem {
         Ok(v) => Some(v),
         Err(e) => {
-            println!("was unable to create a connection for a user: {e}");
+            warn!("was unable to create a connection for a user: {e:#}");
+            METRICS.login_failed();
             None
         }
     });
 
-    let run_options = RunnerOptions { delay_ms, ws_port };
+    let run_options = RunnerOptions { delay_ms, ws, tui };
 
     // launch the runner with the appropriate protocol version
-    match version {
-        340 => Runner::<protocol::v340::Protocol>::run(connections, run_options)
-            .await
-            .context("Error starting up 1.12")?, // 1.12.2
-        _ => {
-            panic!("version {version} does not exist")
-        }
-    }
+    Runner::<protocol::v340::Protocol>::run(connections, run_options)
+        .await
+        .context("Error starting up 1.12")?; // 1.12.2
 
     Ok(())
 }
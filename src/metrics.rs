@@ -0,0 +1,492 @@
+//! Prometheus metrics about the health of the swarm, served at `/metrics`.
+//!
+//! Counters which are updated from many places (packets, logins, ticks) are
+//! atomics in [`METRICS`]. Everything which is derived from the state of the
+//! runner is published as a [`Snapshot`] about once a second.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Context;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, info};
+
+/// the number of packet ids which are counted individually. Packets with
+/// larger ids are counted as the last id.
+const PACKET_IDS: usize = 256;
+
+/// the upper bounds of the tick duration histogram in seconds
+const TICK_BUCKETS: [f64; 8] = [0.005, 0.01, 0.025, 0.05, 0.075, 0.1, 0.25, 1.0];
+
+/// All metrics
+pub static METRICS: Metrics = Metrics::new();
+
+/// Packet and byte counters per packet id
+struct PacketCounters {
+    packets: [AtomicU64; PACKET_IDS],
+    bytes: [AtomicU64; PACKET_IDS],
+}
+
+impl PacketCounters {
+    const fn new() -> Self {
+        Self {
+            packets: [const { AtomicU64::new(0) }; PACKET_IDS],
+            bytes: [const { AtomicU64::new(0) }; PACKET_IDS],
+        }
+    }
+
+    fn record(&self, id: u32, bytes: usize) {
+        let idx = (id as usize).min(PACKET_IDS - 1);
+        self.packets[idx].fetch_add(1, Ordering::Relaxed);
+        self.bytes[idx].fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+/// A histogram of tick durations
+struct Histogram {
+    buckets: [AtomicU64; TICK_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; TICK_BUCKETS.len()],
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter().zip(TICK_BUCKETS) {
+            if secs <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(micros(duration), Ordering::Relaxed);
+    }
+}
+
+/// the name, help and value of a gauge with a value per server
+type ServerGauge = (&'static str, &'static str, fn(&ServerSnapshot) -> usize);
+
+/// The state of a single server
+#[derive(Debug, Default)]
+pub struct ServerSnapshot {
+    pub address: String,
+    pub bots: usize,
+    pub chunks: usize,
+    pub entities: usize,
+
+    /// an estimate of the memory used by the world in bytes
+    pub memory: usize,
+}
+
+/// The state of the runner
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub servers: Vec<ServerSnapshot>,
+
+    /// the number of bots running each task type (by type name)
+    pub tasks: BTreeMap<&'static str, usize>,
+}
+
+pub struct Metrics {
+    packets_in: PacketCounters,
+    packets_out: PacketCounters,
+
+    logins_pending: AtomicI64,
+    logins_failed: AtomicU64,
+
+    tick: Histogram,
+    tick_overruns: AtomicU64,
+    tick_overrun_micros: AtomicU64,
+
+    snapshot: Mutex<Snapshot>,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            packets_in: PacketCounters::new(),
+            packets_out: PacketCounters::new(),
+            logins_pending: AtomicI64::new(0),
+            logins_failed: AtomicU64::new(0),
+            tick: Histogram::new(),
+            tick_overruns: AtomicU64::new(0),
+            tick_overrun_micros: AtomicU64::new(0),
+            snapshot: Mutex::new(Snapshot {
+                servers: Vec::new(),
+                tasks: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// a packet with `bytes` bytes (as sent over the wire) was received
+    pub fn packet_in(&self, id: u32, bytes: usize) {
+        self.packets_in.record(id, bytes);
+    }
+
+    /// a packet with `bytes` bytes (as sent over the wire) was sent
+    pub fn packet_out(&self, id: u32, bytes: usize) {
+        self.packets_out.record(id, bytes);
+    }
+
+    /// a bot started logging in
+    pub fn login_started(&self) {
+        self.logins_pending.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// a bot finished logging in, successfully or not
+    pub fn login_finished(&self, success: bool) {
+        self.logins_pending.fetch_sub(1, Ordering::Relaxed);
+        if !success {
+            self.login_failed();
+        }
+    }
+
+    /// a bot could not log in (or could not even connect)
+    pub fn login_failed(&self) {
+        self.logins_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// the number of bots which are logging in
+    pub fn logins_pending(&self) -> i64 {
+        self.logins_pending.load(Ordering::Relaxed)
+    }
+
+    /// the number of bots which failed to log in
+    pub fn logins_failed(&self) -> u64 {
+        self.logins_failed.load(Ordering::Relaxed)
+    }
+
+    /// a game loop iteration took `duration` and finished `overrun` after
+    /// it should have
+    pub fn tick(&self, duration: Duration, overrun: Duration) {
+        self.tick.observe(duration);
+        if !overrun.is_zero() {
+            self.tick_overruns.fetch_add(1, Ordering::Relaxed);
+            self.tick_overrun_micros
+                .fetch_add(micros(overrun), Ordering::Relaxed);
+        }
+    }
+
+    /// publish the state of the runner
+    pub fn publish(&self, snapshot: Snapshot) {
+        *self.snapshot.lock().unwrap() = snapshot;
+    }
+
+    /// render all metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.render_into(&mut out)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    fn render_into(&self, out: &mut String) -> std::fmt::Result {
+        self.render_snapshot(out)?;
+        self.render_logins_and_ticks(out)?;
+        self.render_packets(out)
+    }
+
+    fn render_snapshot(&self, out: &mut String) -> std::fmt::Result {
+        let snapshot = self.snapshot.lock().unwrap();
+
+        let gauges: [ServerGauge; 4] = [
+            ("bots_connected", "Bots connected to a server", |s| s.bots),
+            ("chunks_stored", "Chunk columns stored", |s| s.chunks),
+            ("entities_stored", "Entities stored", |s| s.entities),
+            (
+                "world_memory_bytes",
+                "Estimated memory used by stored chunks",
+                |s| s.memory,
+            ),
+        ];
+
+        for (name, help, value) in gauges {
+            header(out, name, help, "gauge")?;
+            for server in &snapshot.servers {
+                writeln!(
+                    out,
+                    "swarmbot_{name}{{server=\"{}\"}} {}",
+                    server.address,
+                    value(server)
+                )?;
+            }
+        }
+
+        header(out, "tasks", "Bots running a task by task type", "gauge")?;
+        for (task, count) in &snapshot.tasks {
+            writeln!(
+                out,
+                "swarmbot_tasks{{task=\"{}\"}} {count}",
+                short_type_name(task)
+            )?;
+        }
+        drop(snapshot);
+
+        Ok(())
+    }
+
+    fn render_logins_and_ticks(&self, out: &mut String) -> std::fmt::Result {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        header(out, "logins_pending", "Bots which are logging in", "gauge")?;
+        writeln!(
+            out,
+            "swarmbot_logins_pending {}",
+            self.logins_pending.load(Ordering::Relaxed)
+        )?;
+
+        header(
+            out,
+            "logins_failed_total",
+            "Bots which failed to log in",
+            "counter",
+        )?;
+        writeln!(
+            out,
+            "swarmbot_logins_failed_total {}",
+            load(&self.logins_failed)
+        )?;
+
+        header(
+            out,
+            "tick_duration_seconds",
+            "Duration of game loop iterations",
+            "histogram",
+        )?;
+        for (bucket, bound) in self.tick.buckets.iter().zip(TICK_BUCKETS) {
+            writeln!(
+                out,
+                "swarmbot_tick_duration_seconds_bucket{{le=\"{bound}\"}} {}",
+                load(bucket)
+            )?;
+        }
+        let count = load(&self.tick.count);
+        writeln!(
+            out,
+            "swarmbot_tick_duration_seconds_bucket{{le=\"+Inf\"}} {count}"
+        )?;
+        writeln!(
+            out,
+            "swarmbot_tick_duration_seconds_sum {}",
+            micros_to_secs(load(&self.tick.sum_micros))
+        )?;
+        writeln!(out, "swarmbot_tick_duration_seconds_count {count}")?;
+
+        header(
+            out,
+            "tick_overruns_total",
+            "Game loop iterations which finished after the next should have started",
+            "counter",
+        )?;
+        writeln!(
+            out,
+            "swarmbot_tick_overruns_total {}",
+            load(&self.tick_overruns)
+        )?;
+
+        header(
+            out,
+            "tick_overrun_seconds_total",
+            "Total time game loop iterations ran over",
+            "counter",
+        )?;
+        writeln!(
+            out,
+            "swarmbot_tick_overrun_seconds_total {}",
+            micros_to_secs(load(&self.tick_overrun_micros))
+        )?;
+
+        Ok(())
+    }
+
+    fn render_packets(&self, out: &mut String) -> std::fmt::Result {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        for (direction, counters) in [("in", &self.packets_in), ("out", &self.packets_out)] {
+            header(
+                out,
+                &format!("packets_{direction}_total"),
+                &format!("Packets {direction} by packet id"),
+                "counter",
+            )?;
+            for (id, packets) in counters.packets.iter().enumerate() {
+                let packets = load(packets);
+                if packets > 0 {
+                    writeln!(
+                        out,
+                        "swarmbot_packets_{direction}_total{{id=\"{id:#04x}\"}} {packets}"
+                    )?;
+                }
+            }
+
+            header(
+                out,
+                &format!("bytes_{direction}_total"),
+                &format!("Bytes {direction} by packet id"),
+                "counter",
+            )?;
+            for (id, bytes) in counters.bytes.iter().enumerate() {
+                let bytes = load(bytes);
+                if bytes > 0 {
+                    writeln!(
+                        out,
+                        "swarmbot_bytes_{direction}_total{{id=\"{id:#04x}\"}} {bytes}"
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) -> std::fmt::Result {
+    writeln!(out, "# HELP swarmbot_{name} {help}")?;
+    writeln!(out, "# TYPE swarmbot_{name} {kind}")
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn micros_to_secs(micros: u64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+/// a type name without module paths, for instance
+/// `LazyStream<MineRegion>` instead of
+/// `swarm_bot::client::tasks::lazy_stream::LazyStream<swarm_bot::...>`
+pub fn short_type_name(name: &str) -> String {
+    let mut res = String::new();
+    let mut segment = String::new();
+
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            segment.push(c);
+        } else {
+            res.push_str(segment.rsplit("::").next().unwrap_or_default());
+            segment.clear();
+            res.push(c);
+        }
+    }
+    res.push_str(segment.rsplit("::").next().unwrap_or_default());
+
+    res
+}
+
+async fn respond(mut stream: TcpStream) -> anyhow::Result<()> {
+    let mut buf = [0; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", METRICS.render())
+    } else {
+        ("404 Not Found", "not found\n".to_string())
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+/// Serve the metrics at `http://127.0.0.1:{port}/metrics`
+pub async fn serve(port: u16) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(format!("127.0.0.1:{port}"))
+        .await
+        .with_context(|| format!("could not bind metrics endpoint to port {port}"))?;
+
+    info!("serving metrics at http://127.0.0.1:{port}/metrics");
+
+    tokio::task::spawn_local(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            tokio::task::spawn_local(async move {
+                if let Err(e) = respond(stream).await {
+                    debug!("could not respond to metrics request .. {e}");
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::metrics::{short_type_name, Metrics, ServerSnapshot, Snapshot};
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        metrics.packet_in(0x20, 100);
+        metrics.packet_in(0x20, 50);
+        metrics.login_started();
+        metrics.login_started();
+        metrics.login_finished(false);
+        metrics.tick(Duration::from_millis(30), Duration::ZERO);
+        metrics.tick(Duration::from_millis(80), Duration::from_millis(30));
+
+        let mut snapshot = Snapshot {
+            servers: vec![ServerSnapshot {
+                address: "localhost:25565".to_string(),
+                bots: 3,
+                ..ServerSnapshot::default()
+            }],
+            ..Snapshot::default()
+        };
+        snapshot.tasks.insert(
+            "swarm_bot::client::tasks::lazy_stream::LazyStream<swarm_bot::client::tasks::mine_region::MineRegion>",
+            2,
+        );
+        metrics.publish(snapshot);
+
+        let out = metrics.render();
+        for line in [
+            "swarmbot_bots_connected{server=\"localhost:25565\"} 3",
+            "swarmbot_tasks{task=\"LazyStream<MineRegion>\"} 2",
+            "swarmbot_logins_pending 1",
+            "swarmbot_logins_failed_total 1",
+            "swarmbot_tick_duration_seconds_bucket{le=\"0.05\"} 1",
+            "swarmbot_tick_duration_seconds_bucket{le=\"+Inf\"} 2",
+            "swarmbot_tick_overruns_total 1",
+            "swarmbot_packets_in_total{id=\"0x20\"} 2",
+            "swarmbot_bytes_in_total{id=\"0x20\"} 150",
+        ] {
+            assert!(out.lines().any(|l| l == line), "missing {line} in\n{out}");
+        }
+    }
+
+    #[test]
+    fn test_short_type_name() {
+        assert_eq!(short_type_name("a::b::C<d::E, F>"), "C<E, F>");
+        assert_eq!(short_type_name("Plain"), "Plain");
+    }
+}
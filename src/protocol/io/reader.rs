@@ -13,6 +13,7 @@ use tokio::{
 };
 
 use crate::{
+    metrics::METRICS,
     protocol::io::{Aes, ZLib},
     types::PacketData,
 };
@@ -97,11 +98,10 @@ impl PacketReader {
 
         let mut reader = ByteReader::new(data);
         let VarInt(id) = reader.read();
+        let id = id as u32;
+        METRICS.packet_in(id, pkt_len);
 
-        Ok(PacketData {
-            id: id as u32,
-            reader,
-        })
+        Ok(PacketData { id, reader })
     }
 
     pub async fn read_exact_packet<T>(&mut self) -> anyhow::Result<T>
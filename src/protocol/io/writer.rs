@@ -4,7 +4,10 @@ use swarm_bot_packets::{
 };
 use tokio::{io::AsyncWriteExt, net::tcp::OwnedWriteHalf, sync::mpsc::UnboundedSender};
 
-use crate::protocol::io::{Aes, ZLib};
+use crate::{
+    metrics::METRICS,
+    protocol::io::{Aes, ZLib},
+};
 
 pub struct PacketWriter {
     writer: EncryptedWriter,
@@ -48,7 +51,9 @@ fn data<T: Packet + ByteWritable>(packet: T, compression: Option<ZLib>) -> Vec<u
     let mut writer = ByteWriter::new();
 
     complete_packet.write_to_bytes_like(&mut writer, &compression);
-    writer.freeze()
+    let data = writer.freeze();
+    METRICS.packet_out(T::ID, data.len());
+    data
 }
 
 pub struct PacketWriteChannel {
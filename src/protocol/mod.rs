@@ -21,14 +21,13 @@ mod transform;
 #[allow(clippy::missing_docs_in_private_items)]
 pub enum Mine {
     Start,
-    #[allow(unused)]
     Cancel,
     Finished,
 }
 
 /// The face. This is usually used in combination with a block.
 #[repr(u8)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[allow(clippy::missing_docs_in_private_items)]
 pub enum Face {
     NegY,
@@ -229,3 +228,48 @@ pub struct Login<E: EventQueue, I: InterfaceOut> {
     /// information about the client
     pub info: ClientInfo,
 }
+
+/// An [`InterfaceOut`] for tests which records chat messages, mining and
+/// right-clicks
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockOut {
+    pub chat: Vec<String>,
+    pub mined: Vec<(BlockLocation, Mine)>,
+    pub placed: Vec<(BlockLocation, Face)>,
+}
+
+#[cfg(test)]
+impl InterfaceOut for MockOut {
+    fn place_block(&mut self, against: BlockLocation, face: Face) {
+        self.placed.push((against, face));
+    }
+
+    fn attack_entity(&mut self, _: u32) {}
+
+    fn send_chat(&mut self, message: &str) {
+        self.chat.push(message.to_string());
+    }
+
+    fn inventory_action(&mut self, _: InvAction) {}
+
+    fn swing_arm(&mut self) {}
+
+    fn finish_eating(&mut self) {}
+
+    fn use_item(&mut self) {}
+
+    fn change_slot(&mut self, _: u8) {}
+
+    fn mine(&mut self, location: BlockLocation, mine: Mine, _: Face) {
+        self.mined.push((location, mine));
+    }
+
+    fn respawn(&mut self) {}
+
+    fn teleport(&mut self, _: Location) {}
+
+    fn look(&mut self, _: Direction) {}
+
+    fn teleport_and_look(&mut self, _: Location, _: Direction, _: bool) {}
+}
@@ -395,6 +395,16 @@ pub struct UpdateHealth {
     pub food_saturation: f32,
 }
 
+#[derive(Packet, Debug, Readable)]
+#[packet(0x47, Play)]
+pub struct TimeUpdate {
+    #[allow(unused)]
+    pub world_age: i64,
+
+    /// negative if the daylight cycle is stopped
+    pub time_of_day: i64,
+}
+
 #[derive(Packet, Debug, Readable)]
 #[packet(0x1f, Play)]
 pub struct KeepAlive {
@@ -175,6 +175,10 @@ impl EventQueue340 {
                     self.alive = false;
                 }
             }
+            TimeUpdate::ID => {
+                let TimeUpdate { time_of_day, .. } = data.read();
+                processor.on_time_update(time_of_day);
+            }
             Respawn::ID => {
                 let Respawn { dimension, .. } = data.read();
                 processor.on_dimension_change(dimension);
@@ -410,6 +414,7 @@ impl Minecraft for Protocol {
         let BotConnection {
             server_address,
             bot,
+            config: _,
             read,
             write,
         } = conn;
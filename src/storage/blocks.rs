@@ -159,6 +159,19 @@ impl WorldBlocks {
         }
     }
 
+    /// the number of chunk columns stored
+    pub fn chunk_count(&self) -> usize {
+        self.storage.len()
+    }
+
+    /// an estimate of the memory used by all chunk columns in bytes
+    pub fn memory_estimate(&self) -> usize {
+        self.storage
+            .values()
+            .map(|column| size_of::<(ChunkLocation, Column)>() + column.memory_estimate())
+            .sum()
+    }
+
     pub fn add_column(&mut self, location: ChunkLocation, column: Column) {
         self.storage.insert(location, column);
     }
@@ -314,6 +327,12 @@ impl WorldBlocks {
         let block = self.get_block_exact(location)?;
         Some(block.kind())
     }
+
+    /// if the block at `location` can be climbed (see [`BlockKind::climbable`])
+    pub fn is_climbable(&self, location: BlockLocation) -> bool {
+        self.get_block_kind(location)
+            .is_some_and(BlockKind::climbable)
+    }
 }
 
 #[cfg(test)]
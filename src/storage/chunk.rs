@@ -221,6 +221,12 @@ impl Default for Palette {
 }
 
 impl Palette {
+    /// an estimate of the heap memory used by the palette in bytes
+    fn memory_estimate(&self) -> usize {
+        let states = self.id_to_state.as_ref().map_or(0, Vec::len);
+        self.storage.len() * size_of::<u64>() + states * size_of::<BlockState>()
+    }
+
     pub const fn direct(storage: Vec<u64>) -> Self {
         Self {
             bits_per_block: 13,
@@ -420,6 +426,19 @@ impl Default for Column {
 }
 
 impl Column {
+    /// an estimate of the heap memory used by the column in bytes
+    pub fn memory_estimate(&self) -> usize {
+        match self {
+            Self::LowMemory { data } => data.sections.iter().flatten().count() * SECTION_BYTES,
+            Self::HighMemory { data } => data
+                .sections
+                .iter()
+                .flatten()
+                .map(|section| section.palette.memory_estimate())
+                .sum(),
+        }
+    }
+
     /// modify a column
     /// TODO: is this needed? can we just use *
     pub fn modify(&mut self, column: Self) {
@@ -30,6 +30,11 @@ impl WorldEntities {
         }
     }
 
+    /// the number of entities stored
+    pub fn count(&self) -> usize {
+        self.entities.len()
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&u32, &EntityData)> + '_ {
         self.entities.iter()
     }
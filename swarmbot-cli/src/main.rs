@@ -1,20 +1,29 @@
-#![feature(never_type)]
-
-use std::{io, io::Write};
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{bail, Context};
 use clap::Parser;
-use futures::SinkExt;
-use swarmbot_interfaces::{types::BlockLocation, GoTo, Tag};
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio_tungstenite::tungstenite::Message;
+use colored::Colorize;
+use futures::{stream::SplitSink, SinkExt, StreamExt};
+use swarmbot_interfaces::{Command, Connect, Id, Response};
+use tokio::{net::TcpStream, sync::mpsc::UnboundedReceiver};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::{
+    parse::{parse, Input, COMMANDS, SELECTOR_HELP},
+    print::Printer,
+};
+
+mod parse;
+mod print;
+mod repl;
 
 /// Options parsed from CLI
 ///
-/// Commands:
-///
-///
-/// goto {x} {y} {z}    — go to the coordinates {x} {y} {z}
+/// Type `help` at the prompt for the commands. Commands are read from
+/// `script` (or stdin if it is not a terminal) instead of the prompt if given.
 #[derive(Parser, Debug)]
 #[command(about, author, version)]
 struct CliOptions {
@@ -27,66 +36,275 @@ struct CliOptions {
     /// can be used for anything.
     #[clap(long, default_value = "8080")]
     pub port: u16,
-}
 
-async fn run() -> anyhow::Result<!> {
-    let CliOptions { ip, port } = CliOptions::parse();
+    /// The token of the swarm if it was launched with tokens
+    #[clap(long, env = "SWARMBOT_WS_TOKEN")]
+    pub token: Option<String>,
 
-    let (mut web_socket, _) = tokio_tungstenite::connect_async(format!("ws://{ip}:{port}"))
-        .await
-        .context("could not create websocket")?;
+    /// Connect with TLS (`wss://`)
+    #[clap(long)]
+    pub tls: bool,
 
-    println!("connected to websocket");
-    println!();
-    let stdin = BufReader::new(tokio::io::stdin());
+    /// A certificate (PEM) to trust besides the system roots, for instance
+    /// the self-signed certificate of the swarm. Implies `--tls`
+    #[clap(long)]
+    pub ca_cert: Option<PathBuf>,
 
-    let mut lines = stdin.lines();
+    /// A file of commands (one per line) to run one after another. Each
+    /// command is run until it finishes.
+    pub script: Option<PathBuf>,
 
-    loop {
-        print!("> ");
-        io::stdout().flush()?;
+    /// Only wait for commands of a script to be acknowledged rather than
+    /// finished
+    #[clap(long)]
+    pub no_wait: bool,
 
-        let s = lines.next_line().await?.unwrap_or_default();
+    /// Do not keep a history of the commands typed at the prompt
+    #[clap(long)]
+    pub no_history: bool,
+}
+
+type Sink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+
+/// Where output goes
+enum Output {
+    Stdout,
+    /// above the prompt
+    Prompt(repl::Printer),
+}
 
-        match send_string(&s) {
-            Ok(s) => web_socket.send(Message::Text(s)).await?,
-            Err(e) => println!("invalid... {e}"),
+impl Output {
+    fn print(&mut self, text: String) {
+        match self {
+            Self::Stdout => println!("{text}"),
+            Self::Prompt(printer) => {
+                if printer.print(text.clone()).is_err() {
+                    println!("{text}");
+                }
+            }
         }
     }
 }
 
-fn send_string(input: &str) -> anyhow::Result<String> {
-    let to_send = to_command_data(input)
-        .with_context(|| format!("invalid converting to command data for {input}"))?;
+/// A connection to the swarm
+struct Session {
+    sink: Sink,
+    responses: UnboundedReceiver<Response>,
+    printer: Printer,
+    output: Output,
+    next_id: Id,
+    connected: bool,
+}
 
-    println!("sending {to_send}");
-    println!();
-    Ok(to_send)
+fn help() -> String {
+    let mut text = String::from("commands:");
+    for (name, args, description) in COMMANDS {
+        let usage = format!("{name} {args}");
+        text.push_str(&format!("\n  {usage:<55} {description}"));
+    }
+    text.push_str(&format!("\n{SELECTOR_HELP}"));
+    text
 }
 
-fn to_command_data(input_str: &str) -> anyhow::Result<String> {
-    let mut input = input_str.trim().split(' ');
+/// if `response` is the last one for a command with `id` which was sent
+/// by a script. Subscriptions last until unsubscribed, so they are done when
+/// acknowledged.
+fn done(response: &Response, id: Id, path: &str, wait: bool) -> bool {
+    match response {
+        Response::Ack(ack) => ack.id == id && (!wait || path == "subscribe"),
+        Response::Finished(finished) => finished.id == id,
+        Response::Cancelled(cancelled) => cancelled.id == id,
+        Response::Data(data) => data.id == id,
+        Response::Error(error) => error.id == Some(id),
+        Response::Progress(_) | Response::Event(_) => false,
+    }
+}
+
+impl Session {
+    async fn send(&mut self, command: Command) -> anyhow::Result<()> {
+        if !self.connected {
+            bail!("not connected");
+        }
+        let text = serde_json::to_string(&command)?;
+        self.printer.sent(&command);
+        self.sink.send(Message::Text(text)).await?;
+        Ok(())
+    }
+
+    /// handle the next response. Returns [`None`] if disconnected.
+    async fn response(&mut self) -> Option<Response> {
+        let Some(response) = self.responses.recv().await else {
+            if self.connected {
+                self.connected = false;
+                self.output.print("disconnected".red().to_string());
+            }
+            return None;
+        };
 
-    let cmd_name = input.next().context("no command name specified")?;
+        let text = self.printer.format(&response);
+        self.output.print(text);
+        Some(response)
+    }
+
+    async fn prompt(&mut self, mut lines: repl::Lines) -> anyhow::Result<()> {
+        self.output
+            .print("type help for the commands".dimmed().to_string());
+
+        loop {
+            tokio::select! {
+                line = lines.next() => {
+                    let Some(line) = line else {
+                        return Ok(());
+                    };
 
-    match cmd_name {
-        "goto" => {
-            let goto = GoTo {
-                location: BlockLocation {
-                    x: input.next().context("no x in goto")?.parse()?,
-                    y: input.next().context("no y in goto")?.parse()?,
-                    z: input.next().context("no z in goto")?.parse()?,
-                },
+                    let command = match parse(&line, self.next_id) {
+                        Ok(Input::Empty) => continue,
+                        Ok(Input::Help) => {
+                            self.output.print(help());
+                            continue;
+                        }
+                        Ok(Input::Quit) => return Ok(()),
+                        Ok(Input::Command(command)) => command,
+                        Err(e) => {
+                            self.output.print(format!("{e:#}").red().to_string());
+                            continue;
+                        }
+                    };
+
+                    self.next_id += 1;
+                    let (id, path) = (command.id, command.path.clone());
+                    match self.send(command).await {
+                        Ok(()) => self.output.print(format!("#{id} {path}").dimmed().to_string()),
+                        Err(e) => self.output.print(format!("{e:#}").red().to_string()),
+                    }
+                }
+                // keep reading lines after disconnecting so the user can quit
+                Some(_) = self.response(), if self.connected => {}
+            }
+        }
+    }
+
+    /// close the connection so the swarm sees we are gone
+    async fn close(&mut self) {
+        if self.connected {
+            let _ = self.sink.close().await;
+        }
+    }
+
+    async fn script(&mut self, script: &str, wait: bool) -> anyhow::Result<()> {
+        for (line, text) in (1..).zip(script.lines()) {
+            let command = match parse(text, self.next_id).with_context(|| format!("line {line}"))? {
+                Input::Empty | Input::Help => continue,
+                Input::Quit => return Ok(()),
+                Input::Command(command) => command,
+            };
+
+            self.next_id += 1;
+            let (id, path) = (command.id, command.path.clone());
+            self.output
+                .print(format!("#{id} {}", text.trim()).dimmed().to_string());
+            self.send(command).await?;
+
+            loop {
+                let response = self
+                    .response()
+                    .await
+                    .with_context(|| format!("disconnected while running line {line}"))?;
+                if done(&response, id, &path, wait) {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn read_script(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))
+}
+
+async fn run() -> anyhow::Result<()> {
+    let CliOptions {
+        ip,
+        port,
+        token,
+        tls,
+        ca_cert,
+        script,
+        no_wait,
+        no_history,
+    } = CliOptions::parse();
+
+    let ca_cert = ca_cert.as_deref().map(std::fs::read).transpose()?;
+    let connect = Connect {
+        token,
+        tls: tls || ca_cert.is_some(),
+        ca_cert,
+    };
+
+    let web_socket = swarmbot_interfaces::connect(&format!("{ip}:{port}"), &connect)
+        .await
+        .map_err(|e| anyhow::anyhow!("could not connect to {ip}:{port}: {e}"))?;
+
+    let (sink, mut stream) = web_socket.split();
+    let (tx, responses) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Some(Ok(msg)) = stream.next().await {
+            let Ok(text) = msg.into_text() else {
+                continue;
             };
-            Ok(goto.encode())
+            match serde_json::from_str::<Response>(&text) {
+                Ok(response) => {
+                    if tx.send(response).is_err() {
+                        return;
+                    }
+                }
+                Err(_) => println!("unknown response {text}"),
+            }
         }
-        _ => bail!("input '{input_str}' could not be parsed into a CommandData struct"),
+    });
+
+    let mut session = Session {
+        sink,
+        responses,
+        printer: Printer::default(),
+        output: Output::Stdout,
+        next_id: 1,
+        connected: true,
+    };
+
+    let script = match script {
+        Some(script) => Some(read_script(&script)?),
+        None if !std::io::stdin().is_terminal() => Some(std::io::read_to_string(std::io::stdin())?),
+        None => None,
+    };
+
+    if let Some(script) = script {
+        let result = session.script(&script, !no_wait).await;
+        session.close().await;
+        return result;
     }
+
+    let history = if no_history {
+        None
+    } else {
+        repl::history_file()
+    };
+    let (lines, printer) = repl::start(history)?;
+    session.output = Output::Prompt(printer);
+
+    println!("connected to {ip}:{port}");
+    let result = session.prompt(lines).await;
+    session.close().await;
+    result
 }
 
 #[tokio::main]
 async fn main() {
     if let Err(err) = run().await {
-        println!("got error {err}")
+        println!("got error {err:#}");
+        std::process::exit(1);
     }
 }
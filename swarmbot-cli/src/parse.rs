@@ -0,0 +1,447 @@
+//! Turning a line typed by the user into a [`Command`]
+
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context};
+use swarmbot_interfaces::{
+    events::{EventKind, Region, Subscribe, Unsubscribe},
+    query::{GetBlock, GetBlocks, GetBots, GetEntities, GetPlayers},
+    types::{BlockLocation, BlockLocation2D, Location, Selection2D},
+    Assign, Attack, Cancel, Command, GoTo, Id, Mine, Nearest, Script, Selector, Tag,
+};
+
+/// The commands with their arguments and what they do
+pub const COMMANDS: &[(&str, &str, &str)] = &[
+    ("goto", "<x> <y> <z> [bots]", "go to a block"),
+    (
+        "mine",
+        "<x1> <z1> <x2> <z2> [bots]",
+        "mine the columns of a region",
+    ),
+    ("attack", "<player> [bots]", "attack a player"),
+    (
+        "assign",
+        "<group|-> [bots]",
+        "put bots in a group (or no group with -)",
+    ),
+    ("cancel", "<id>", "cancel a command"),
+    ("script", "<file> [bots]", "run a Rhai script"),
+    ("bots", "[bots]", "show the state of bots"),
+    ("block", "<x> <y> <z> [server=]", "show a block"),
+    (
+        "blocks",
+        "<x1> <y1> <z1> <x2> <y2> <z2> [server=]",
+        "show the blocks of a region",
+    ),
+    (
+        "entities",
+        "<x> <y> <z> <radius> [server=]",
+        "show the entities near a point",
+    ),
+    ("players", "[server=]", "show the players in the tab list"),
+    (
+        "subscribe",
+        "[event..] [bots] [server=] [region=x,y,z:x,y,z]",
+        "receive events",
+    ),
+    (
+        "unsubscribe",
+        "<id>",
+        "stop receiving the events of a subscription",
+    ),
+    ("send", "<path> [json]", "send any command"),
+    ("help", "", "show this help"),
+    ("quit", "", "exit"),
+];
+
+/// The options selecting bots
+pub const SELECTOR_HELP: &str = "bots are all bots unless selected with id=1,2 name=Alice,Bob \
+                                 group=miners idle nearest=x,y,z[,count]";
+
+/// The options of commands (for completion)
+pub const OPTIONS: &[&str] = &[
+    "id=", "name=", "group=", "idle", "nearest=", "server=", "region=",
+];
+
+/// The kinds of events which can be subscribed to (for completion)
+pub const EVENTS: &[&str] = &[
+    "chat",
+    "joined",
+    "disconnected",
+    "death",
+    "health",
+    "task",
+    "block_change",
+    "player_join",
+    "player_leave",
+];
+
+/// What a line asks for
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    /// a blank line or a comment
+    Empty,
+    Help,
+    Quit,
+    Command(Command),
+}
+
+/// The arguments of a command. Options (`key=value` and `idle`) can be given
+/// anywhere and must be used by the command.
+struct Args {
+    command: String,
+    positional: std::vec::IntoIter<String>,
+    selector: Option<Selector>,
+    server: Option<String>,
+    region: Option<Region>,
+}
+
+fn list<T: FromStr>(value: &str) -> anyhow::Result<Vec<T>>
+where
+    T::Err: Display,
+{
+    value
+        .split(',')
+        .map(|item| {
+            item.parse()
+                .map_err(|e| anyhow::anyhow!("invalid '{item}': {e}"))
+        })
+        .collect()
+}
+
+fn block_location(value: &str) -> anyhow::Result<BlockLocation> {
+    let [x, y, z] = list(value)?[..] else {
+        bail!("expected x,y,z but got '{value}'");
+    };
+    Ok(BlockLocation::new(x, y.try_into()?, z))
+}
+
+impl Args {
+    fn new(words: Vec<String>) -> anyhow::Result<Self> {
+        let mut words = words.into_iter();
+        let command = words.next().unwrap_or_default();
+
+        let mut positional = Vec::new();
+        let mut selector = None;
+        let mut server = None;
+        let mut region = None;
+
+        for word in words {
+            if word == "idle" {
+                selector.get_or_insert_with(Selector::default).idle = true;
+                continue;
+            }
+
+            let Some((key, value)) = word.split_once('=') else {
+                positional.push(word);
+                continue;
+            };
+
+            match key {
+                "id" => selector
+                    .get_or_insert_with(Selector::default)
+                    .ids
+                    .extend(list::<u32>(value)?),
+                "name" => selector
+                    .get_or_insert_with(Selector::default)
+                    .names
+                    .extend(list::<String>(value)?),
+                "group" => selector
+                    .get_or_insert_with(Selector::default)
+                    .groups
+                    .extend(list::<String>(value)?),
+                "nearest" => {
+                    let (location, count) = match list::<i32>(value)?[..] {
+                        [x, y, z] => ((x, y, z), 1),
+                        [x, y, z, count] => ((x, y, z), count),
+                        _ => bail!("expected nearest=x,y,z[,count] but got '{value}'"),
+                    };
+                    let (x, y, z) = location;
+                    selector.get_or_insert_with(Selector::default).nearest = Some(Nearest {
+                        location: BlockLocation::new(x, y.try_into()?, z),
+                        count: count.try_into()?,
+                    });
+                }
+                "server" => server = Some(value.to_string()),
+                "region" => {
+                    let (from, to) = value
+                        .split_once(':')
+                        .context("expected region=x,y,z:x,y,z")?;
+                    region = Some(Region {
+                        from: block_location(from)?,
+                        to: block_location(to)?,
+                    });
+                }
+                _ => positional.push(word),
+            }
+        }
+
+        Ok(Self {
+            command,
+            positional: positional.into_iter(),
+            selector,
+            server,
+            region,
+        })
+    }
+
+    /// the next positional argument
+    fn next<T: FromStr>(&mut self, name: &str) -> anyhow::Result<T>
+    where
+        T::Err: Display,
+    {
+        let command = &self.command;
+        let value = self
+            .positional
+            .next()
+            .with_context(|| format!("{command} needs <{name}>"))?;
+        value
+            .parse()
+            .map_err(|e| anyhow::anyhow!("invalid <{name}> '{value}': {e}"))
+    }
+
+    fn block_location(&mut self) -> anyhow::Result<BlockLocation> {
+        Ok(BlockLocation::new(
+            self.next("x")?,
+            self.next("y")?,
+            self.next("z")?,
+        ))
+    }
+
+    fn bots(&mut self) -> Selector {
+        self.selector.take().unwrap_or_default()
+    }
+
+    fn server(&mut self) -> Option<String> {
+        self.server.take()
+    }
+
+    /// the remaining positional arguments
+    fn rest(&mut self) -> Vec<String> {
+        self.positional.by_ref().collect()
+    }
+
+    /// fails if any argument was not used
+    fn finish(mut self) -> anyhow::Result<()> {
+        let command = &self.command;
+        if let Some(extra) = self.positional.next() {
+            bail!("unexpected argument '{extra}' for {command}");
+        }
+        if self.selector.is_some() {
+            bail!("{command} does not select bots");
+        }
+        if self.server.is_some() {
+            bail!("{command} does not take a server");
+        }
+        if self.region.is_some() {
+            bail!("{command} does not take a region");
+        }
+        Ok(())
+    }
+}
+
+/// `send <path> [json]`
+fn send(raw: &str, id: Id) -> anyhow::Result<Input> {
+    let raw = raw.trim();
+    let (path, data) = raw.split_once(char::is_whitespace).unwrap_or((raw, ""));
+    let data = if data.trim().is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_str(data).context("invalid json")?
+    };
+
+    Ok(Input::Command(Command {
+        id,
+        path: path.to_string(),
+        data,
+    }))
+}
+
+/// parse a line. The command gets `id` if the line is one.
+pub fn parse(line: &str, id: Id) -> anyhow::Result<Input> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(Input::Empty);
+    }
+
+    // the data is JSON rather than words
+    if let Some(raw) = line.strip_prefix("send ") {
+        return send(raw, id);
+    }
+
+    let words = shlex::split(line).context("unclosed quote")?;
+    let mut args = Args::new(words)?;
+
+    let command = match args.command.as_str() {
+        "help" => Input::Help,
+        "quit" | "exit" => Input::Quit,
+        "goto" => Input::Command(
+            GoTo {
+                location: args.block_location()?,
+                bots: args.bots(),
+            }
+            .command(id),
+        ),
+        "mine" => {
+            let from = BlockLocation2D::new(args.next("x1")?, args.next("z1")?);
+            let to = BlockLocation2D::new(args.next("x2")?, args.next("z2")?);
+            Input::Command(
+                Mine {
+                    sel: Selection2D { from, to },
+                    bots: args.bots(),
+                }
+                .command(id),
+            )
+        }
+        "attack" => Input::Command(
+            Attack {
+                name: args.next("player")?,
+                bots: args.bots(),
+            }
+            .command(id),
+        ),
+        "assign" => {
+            let group: String = args.next("group")?;
+            Input::Command(
+                Assign {
+                    bots: args.bots(),
+                    group: (group != "-").then_some(group),
+                }
+                .command(id),
+            )
+        }
+        "cancel" => Input::Command(
+            Cancel {
+                command: args.next("id")?,
+            }
+            .command(id),
+        ),
+        "script" => {
+            let file: String = args.next("file")?;
+            let source =
+                std::fs::read_to_string(&file).with_context(|| format!("could not read {file}"))?;
+            Input::Command(
+                Script {
+                    source,
+                    bots: args.bots(),
+                }
+                .command(id),
+            )
+        }
+        "bots" => Input::Command(GetBots { bots: args.bots() }.command(id)),
+        "block" => Input::Command(
+            GetBlock {
+                location: args.block_location()?,
+                server: args.server(),
+            }
+            .command(id),
+        ),
+        "blocks" => Input::Command(
+            GetBlocks {
+                from: args.block_location()?,
+                to: args.block_location()?,
+                server: args.server(),
+            }
+            .command(id),
+        ),
+        "entities" => Input::Command(
+            GetEntities {
+                location: Location::new(args.next("x")?, args.next("y")?, args.next("z")?),
+                radius: args.next("radius")?,
+                server: args.server(),
+            }
+            .command(id),
+        ),
+        "players" => Input::Command(
+            GetPlayers {
+                server: args.server(),
+            }
+            .command(id),
+        ),
+        "subscribe" => {
+            let events = args
+                .rest()
+                .into_iter()
+                .map(|event| {
+                    serde_json::from_value::<EventKind>(serde_json::Value::String(event.clone()))
+                        .with_context(|| format!("unknown event '{event}'"))
+                })
+                .collect::<anyhow::Result<_>>()?;
+            Input::Command(
+                Subscribe {
+                    events,
+                    bots: args.bots(),
+                    server: args.server(),
+                    region: args.region.take(),
+                }
+                .command(id),
+            )
+        }
+        "unsubscribe" => Input::Command(
+            Unsubscribe {
+                subscription: args.next("id")?,
+            }
+            .command(id),
+        ),
+        other => bail!("unknown command '{other}' (see help)"),
+    };
+
+    args.finish()?;
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use swarmbot_interfaces::{
+        events::{EventKind, Region, Subscribe},
+        types::BlockLocation,
+        GoTo, Nearest, Selector, Tag,
+    };
+
+    use crate::parse::{parse, Input};
+
+    #[test]
+    fn test_parse() {
+        let goto = GoTo {
+            location: BlockLocation::new(1, 64, -3),
+            bots: Selector {
+                names: vec!["Alice".to_string(), "Bob".to_string()],
+                idle: true,
+                nearest: Some(Nearest {
+                    location: BlockLocation::new(0, 70, 0),
+                    count: 2,
+                }),
+                ..Selector::default()
+            },
+        };
+        assert_eq!(
+            parse("goto 1 name=Alice,Bob 64 -3 idle nearest=0,70,0,2", 5).unwrap(),
+            Input::Command(goto.command(5))
+        );
+
+        let subscribe = Subscribe {
+            events: vec![EventKind::Chat, EventKind::BlockChange],
+            region: Some(Region {
+                from: BlockLocation::new(0, 0, 0),
+                to: BlockLocation::new(15, 255, 15),
+            }),
+            ..Subscribe::default()
+        };
+        assert_eq!(
+            parse("subscribe chat block_change region=0,0,0:15,255,15", 6).unwrap(),
+            Input::Command(subscribe.command(6))
+        );
+
+        let Input::Command(send) = parse(r#"send get_bots {"bots": {"ids": [0]}}"#, 7).unwrap()
+        else {
+            panic!("send is a command");
+        };
+        assert_eq!(send.path, "get_bots");
+        assert_eq!(send.data, serde_json::json!({ "bots": { "ids": [0] } }));
+
+        assert_eq!(parse("  # a comment", 7).unwrap(), Input::Empty);
+        assert!(parse("goto 1 2", 7).is_err());
+        assert!(parse("players id=1", 7).is_err());
+        assert!(parse("cancel 1 2", 7).is_err());
+    }
+}
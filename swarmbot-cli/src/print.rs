@@ -0,0 +1,212 @@
+//! Turning responses of the swarm into text for the user
+
+use std::{collections::HashMap, fmt::Write};
+
+use colored::Colorize;
+use serde::de::DeserializeOwned;
+use swarmbot_interfaces::{
+    events::{Event, EventMessage},
+    query::{BlockInfo, BlockRegion, BotInfo, EntityInfo, PlayerInfo},
+    Ack, Command, Data, Error, Id, Outcome, Progress, Response,
+};
+
+/// Formats responses. Remembers the path of each command sent so the data
+/// answering a query can be shown according to its type.
+#[derive(Default)]
+pub struct Printer {
+    paths: HashMap<Id, String>,
+}
+
+fn block(block: &BlockInfo) -> String {
+    match block {
+        BlockInfo::Unknown => "unknown".to_string(),
+        BlockInfo::Known { id, metadata } => format!("{id}:{metadata}"),
+        BlockInfo::Estimate { simple } => format!("{simple:?} (estimate)").to_lowercase(),
+    }
+}
+
+fn bots(bots: Vec<BotInfo>) -> String {
+    let mut text = format!("{} bots", bots.len());
+    for bot in bots {
+        let group = bot
+            .group
+            .map(|group| format!(" ({group})"))
+            .unwrap_or_default();
+        let task = bot
+            .activity
+            .map(|activity| activity.to_string())
+            .or(bot.task);
+        let task = match (task, bot.command) {
+            (None, _) => "idle".dimmed().to_string(),
+            (Some(task), None) => task,
+            (Some(task), Some(command)) => format!("{task} #{command}"),
+        };
+        let _ = write!(
+            text,
+            "\n  {:>3} {}{group} on {} {:?} {} health {:.1} food {} {task}",
+            bot.id,
+            bot.username.bold(),
+            bot.server,
+            bot.dimension,
+            bot.location,
+            bot.health,
+            bot.food,
+        );
+    }
+    text
+}
+
+fn blocks(region: &BlockRegion) -> String {
+    let known = region
+        .blocks
+        .iter()
+        .filter(|block| !matches!(block, BlockInfo::Unknown))
+        .count();
+    format!(
+        "{} blocks from {} to {} ({known} known)",
+        region.blocks.len(),
+        region.from,
+        region.to
+    )
+}
+
+fn entities(entities: Vec<EntityInfo>) -> String {
+    let mut text = format!("{} entities", entities.len());
+    for entity in entities {
+        let player = entity
+            .player
+            .map(|uuid| format!(" player {uuid}"))
+            .unwrap_or_default();
+        let _ = write!(text, "\n  {:>6} {}{player}", entity.id, entity.location);
+    }
+    text
+}
+
+fn players(players: Vec<PlayerInfo>) -> String {
+    let mut text = format!("{} players", players.len());
+    for player in players {
+        let _ = write!(text, "\n  {} {}", player.name.bold(), player.uuid.dimmed());
+    }
+    text
+}
+
+fn event(message: &EventMessage) -> String {
+    let EventMessage {
+        subscription,
+        bot,
+        server,
+        event,
+    } = message;
+
+    let what = match event {
+        Event::Chat { message } => format!("chat {message}"),
+        Event::Joined { username } => format!("joined as {username}"),
+        Event::Disconnected { reason } => format!("disconnected: {reason}").red().to_string(),
+        Event::Death => "died".red().to_string(),
+        Event::Health { health, food } => format!("health {health:.1} food {food}"),
+        Event::Task { task, outcome } => format!("{task} {}", outcome_name(*outcome)),
+        Event::BlockChange { location, block: b } => {
+            format!("block {location} is now {}", block(b))
+        }
+        Event::PlayerJoin { name, .. } => format!("{name} joined"),
+        Event::PlayerLeave { name, uuid } => {
+            format!("{} left", name.as_deref().unwrap_or(uuid))
+        }
+    };
+
+    format!(
+        "{} bot {bot} on {server}: {what}",
+        format!("[{subscription}]").cyan()
+    )
+}
+
+const fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Finished => "finished",
+        Outcome::Cancelled => "cancelled",
+        Outcome::Failed => "failed",
+    }
+}
+
+/// `data` as `T` or as JSON if it is not one
+fn data<T: DeserializeOwned>(data: &serde_json::Value, show: impl FnOnce(T) -> String) -> String {
+    match serde_json::from_value(data.clone()) {
+        Ok(value) => show(value),
+        Err(_) => serde_json::to_string_pretty(data).unwrap_or_default(),
+    }
+}
+
+impl Printer {
+    /// `command` was sent
+    pub fn sent(&mut self, command: &Command) {
+        self.paths.insert(command.id, command.path.clone());
+    }
+
+    /// the path of the command with `id`
+    fn path(&self, id: Id) -> &str {
+        self.paths.get(&id).map_or("?", String::as_str)
+    }
+
+    pub fn format(&mut self, response: &Response) -> String {
+        let id = |id: Id| format!("#{id}").bold();
+
+        match response {
+            Response::Ack(Ack { id: i, bots }) => match self.path(*i) {
+                "subscribe" => format!("{} subscribed", id(*i)),
+                "assign" | "cancel" | "unsubscribe" => format!("{} done", id(*i)),
+                path if bots.is_empty() => format!("{} {path}: no bots selected", id(*i))
+                    .yellow()
+                    .to_string(),
+                path => format!("{} {path} given to bots {bots:?}", id(*i)),
+            },
+            Response::Progress(Progress {
+                id: i,
+                bot,
+                outcome,
+                remaining,
+            }) => format!(
+                "{} bot {bot} {} ({remaining} remaining)",
+                id(*i),
+                outcome_name(*outcome)
+            ),
+            Response::Finished(finished) => {
+                let i = finished.id;
+                self.paths.remove(&i);
+                format!("{} finished", id(i)).green().to_string()
+            }
+            Response::Cancelled(cancelled) => {
+                let i = cancelled.id;
+                self.paths.remove(&i);
+                format!("{} cancelled", id(i)).yellow().to_string()
+            }
+            Response::Data(Data { id: i, data: d }) => {
+                let text = match self.paths.remove(i).as_deref() {
+                    Some("get_bots") => data(d, bots),
+                    Some("get_block") => data(d, |b: BlockInfo| block(&b)),
+                    Some("get_blocks") => data(d, |region: BlockRegion| blocks(&region)),
+                    Some("get_entities") => data(d, entities),
+                    Some("get_players") => data(d, players),
+                    _ => serde_json::to_string_pretty(d).unwrap_or_default(),
+                };
+                format!("{} {text}", id(*i))
+            }
+            Response::Event(message) => event(message),
+            Response::Error(Error {
+                id: i,
+                kind,
+                message,
+            }) => {
+                let prefix = match i {
+                    Some(i) => {
+                        self.paths.remove(i);
+                        format!("#{i} ")
+                    }
+                    None => String::new(),
+                };
+                format!("{prefix}error ({kind:?}): {message}")
+                    .red()
+                    .to_string()
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! The interactive prompt. Lines are read on their own thread (readline
+//! blocks) while responses are printed above the prompt.
+
+use std::path::PathBuf;
+
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::FileHistory, validate::Validator, Context, Editor, ExternalPrinter, Helper,
+};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use crate::parse::{COMMANDS, EVENTS, OPTIONS};
+
+/// Completes command names, then options (and event kinds for `subscribe`)
+struct Completion;
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &line[start..];
+
+        let candidates: Vec<&str> = if start == 0 {
+            COMMANDS.iter().map(|(name, ..)| *name).collect()
+        } else if line.starts_with("subscribe ") {
+            EVENTS.iter().chain(OPTIONS).copied().collect()
+        } else {
+            OPTIONS.to_vec()
+        };
+
+        let candidates = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .map(|candidate| {
+                // commands and flags are complete words
+                let space = if start == 0 || !candidate.ends_with('=') {
+                    " "
+                } else {
+                    ""
+                };
+                format!("{candidate}{space}")
+            })
+            .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+/// the file history is kept in (`~/.swarmbot_history`)
+pub fn history_file() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(PathBuf::from(home).join(".swarmbot_history"))
+}
+
+/// A printer which prints above the prompt
+pub type Printer = Box<dyn ExternalPrinter + Send>;
+
+/// Lines typed by the user. A line is only read when asked for so the terminal
+/// is not left in raw mode if we exit while the prompt is shown.
+pub struct Lines {
+    lines: UnboundedReceiver<String>,
+    read: std::sync::mpsc::Sender<()>,
+    requested: bool,
+}
+
+impl Lines {
+    /// the next line or [`None`] if the user ended the input (ctrl-d). This is
+    /// cancel safe.
+    pub async fn next(&mut self) -> Option<String> {
+        if !self.requested {
+            self.read.send(()).ok()?;
+            self.requested = true;
+        }
+
+        let line = self.lines.recv().await;
+        self.requested = false;
+        line
+    }
+}
+
+/// start the prompt
+pub fn start(history: Option<PathBuf>) -> anyhow::Result<(Lines, Printer)> {
+    let mut editor: Editor<Completion, FileHistory> = Editor::new()?;
+    editor.set_helper(Some(Completion));
+
+    if let Some(history) = &history {
+        // there is no history the first time
+        let _ = editor.load_history(history);
+    }
+
+    let printer = Box::new(editor.create_external_printer()?);
+    let (tx, lines) = tokio::sync::mpsc::unbounded_channel();
+    let (read, requests) = std::sync::mpsc::channel();
+
+    std::thread::spawn(move || read_lines(editor, history, &requests, &tx));
+
+    let lines = Lines {
+        lines,
+        read,
+        requested: false,
+    };
+
+    Ok((lines, printer))
+}
+
+fn read_lines(
+    mut editor: Editor<Completion, FileHistory>,
+    history: Option<PathBuf>,
+    requests: &std::sync::mpsc::Receiver<()>,
+    tx: &UnboundedSender<String>,
+) {
+    while requests.recv().is_ok() {
+        let line = loop {
+            match editor.readline("> ") {
+                Ok(line) => break line,
+                // like a shell, ctrl-c only discards the line
+                Err(ReadlineError::Interrupted) => continue,
+                Err(_) => return,
+            }
+        };
+
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+            if let Some(history) = &history {
+                let _ = editor.save_history(history);
+            }
+        }
+
+        if tx.send(line).is_err() {
+            return;
+        }
+    }
+}